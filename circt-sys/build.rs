@@ -48,7 +48,8 @@ fn main() -> Result<()> {
     let lib_dir = &circt_prefix.join("lib");
     let include_dir = &circt_prefix.join("include");
 
-    link_libs(lib_dir)?;
+    let features = enabled_features();
+    link_libs(lib_dir, &features)?;
 
     let bindings_dir = cargo_root.join("bindings");
     std::fs::create_dir_all(&bindings_dir).into_diagnostic()?;
@@ -60,6 +61,7 @@ fn main() -> Result<()> {
         .generate_block(true)
         .generate_inline_functions(true)
         .clang_args(&["-I", include_dir.to_str().unwrap()])
+        .clang_args(features.iter().map(|f| format!("-D{}", f.define)))
         .generate()
         .expect("Unable to generate bindings")
         .write_to_file(&bindings_dir.join("bindings.rs"))
@@ -67,7 +69,11 @@ fn main() -> Result<()> {
 
     // Additional wrapper code
     rerun_if_changed!("wrapper.cpp");
-    cc::Build::new()
+    let mut wrapper = cc::Build::new();
+    for feature in features.iter() {
+        wrapper.define(feature.define, None);
+    }
+    wrapper
         .cpp(true)
         .file("wrapper.cpp")
         .flag("-std=c++17")
@@ -162,9 +168,168 @@ fn build_circt(circt_src_dir: &Path, circt_install_dir: &Path) -> Result<()> {
     Ok(())
 }
 
-fn link_libs(lib_dir: &Path) -> Result<()> {
+/// An optional CIRCT dialect, enabled by the cargo feature `name`.
+struct DialectFeature {
+    name: &'static str,
+    /// Preprocessor macro guarding the dialect's declarations in `wrapper.h` and `wrapper.cpp`.
+    define: &'static str,
+    libs: &'static [&'static str],
+}
+
+const DIALECT_FEATURES: &[DialectFeature] = &[
+    DialectFeature {
+        name: "llhd",
+        define: "CIRCT_SYS_LLHD",
+        libs: &["CIRCTCAPILLHD", "CIRCTLLHDTransforms", "CIRCTLLHD"],
+    },
+    DialectFeature {
+        name: "moore",
+        define: "CIRCT_SYS_MOORE",
+        libs: &["CIRCTCAPIMoore", "CIRCTMoore"],
+    },
+    DialectFeature {
+        name: "msft",
+        define: "CIRCT_SYS_MSFT",
+        libs: &["CIRCTCAPIMSFT", "CIRCTMSFTTransforms", "CIRCTMSFT"],
+    },
+    DialectFeature {
+        name: "calyx",
+        define: "CIRCT_SYS_CALYX",
+        libs: &[
+            "CIRCTCAPICalyx",
+            "CIRCTCalyxToHW",
+            "CIRCTCalyxTransforms",
+            "CIRCTCalyx",
+        ],
+    },
+    DialectFeature {
+        name: "arc",
+        define: "CIRCT_SYS_ARC",
+        libs: &[
+            "CIRCTCAPIArc",
+            "CIRCTArcToLLVM",
+            "CIRCTConvertToArcs",
+            "CIRCTArcTransforms",
+            "CIRCTArc",
+            // The execution engine JIT-compiling Arc models.
+            "MLIRCAPIExecutionEngine",
+            "MLIRExecutionEngine",
+            "MLIRExecutionEngineUtils",
+            "MLIRTargetLLVMIRExport",
+            "MLIRLLVMToLLVMIRTranslation",
+            "MLIRBuiltinToLLVMIRTranslation",
+            "MLIRLLVMIRTransforms",
+            "MLIRLLVMDialect",
+            "MLIRTranslateLib",
+            "LLVMOrcJIT",
+            "LLVMOrcTargetProcess",
+            "LLVMOrcShared",
+            "LLVMJITLink",
+            "LLVMExecutionEngine",
+            "LLVMRuntimeDyld",
+            "LLVMPasses",
+            "LLVMipo",
+            "LLVMCoroutines",
+            "LLVMObjCARCOpts",
+            "LLVMVectorize",
+            "LLVMInstrumentation",
+            "LLVMLinker",
+            "LLVMFrontendOpenMP",
+            "LLVMAsmPrinter",
+            "LLVMGlobalISel",
+            "LLVMSelectionDAG",
+            "LLVMCodeGen",
+            "LLVMCFGuard",
+            "LLVMScalarOpts",
+            "LLVMAggressiveInstCombine",
+            "LLVMInstCombine",
+            "LLVMTransformUtils",
+            "LLVMBitWriter",
+            "LLVMTarget",
+            "LLVMAnalysis",
+            "LLVMProfileData",
+            "LLVMSymbolize",
+            "LLVMDebugInfoPDB",
+            "LLVMDebugInfoMSF",
+            "LLVMDebugInfoDWARF",
+            "LLVMObject",
+            "LLVMTextAPI",
+            "LLVMMCParser",
+            "LLVMIRReader",
+            "LLVMAsmParser",
+            "LLVMMCDisassembler",
+            "LLVMMC",
+            "LLVMDebugInfoCodeView",
+            "LLVMBitReader",
+            "LLVMRemarks",
+            "LLVMBitstreamReader",
+        ],
+    },
+    DialectFeature {
+        name: "verif",
+        define: "CIRCT_SYS_VERIF",
+        libs: &["CIRCTCAPIVerif", "CIRCTVerif"],
+    },
+    DialectFeature {
+        name: "ltl",
+        define: "CIRCT_SYS_LTL",
+        libs: &["CIRCTCAPILTL", "CIRCTLTL"],
+    },
+    DialectFeature {
+        name: "om",
+        define: "CIRCT_SYS_OM",
+        libs: &["CIRCTCAPIOM", "CIRCTOM"],
+    },
+    DialectFeature {
+        name: "debug",
+        define: "CIRCT_SYS_DEBUG",
+        libs: &["CIRCTCAPIDebug", "CIRCTDebug"],
+    },
+    DialectFeature {
+        name: "systemc",
+        define: "CIRCT_SYS_SYSTEMC",
+        libs: &["CIRCTCAPISystemC", "CIRCTSystemCTransforms", "CIRCTSystemC"],
+    },
+];
+
+fn enabled_features() -> Vec<&'static DialectFeature> {
+    DIALECT_FEATURES
+        .iter()
+        .filter(|f| env::var_os(format!("CARGO_FEATURE_{}", f.name.to_uppercase())).is_some())
+        .collect()
+}
+
+/// LLVM code generation libraries of the target architecture, as built with
+///  `LLVM_TARGETS_TO_BUILD=host`.
+fn native_target_libs() -> Vec<String> {
+    let arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap_or_default();
+    let target = match arch.as_str() {
+        "x86_64" | "x86" => "X86",
+        "aarch64" => "AArch64",
+        "arm" => "ARM",
+        "riscv64" | "riscv32" => "RISCV",
+        _ => return vec![],
+    };
+    ["CodeGen", "AsmParser", "Desc", "Disassembler", "Info"]
+        .iter()
+        .map(|component| format!("LLVM{}{}", target, component))
+        .collect()
+}
+
+fn link_libs(lib_dir: &Path, features: &[&DialectFeature]) -> Result<()> {
     rustc_link_search!(lib_dir.to_str().unwrap());
 
+    // Static libraries must precede the libraries they depend on.
+    for lib in features.iter().flat_map(|f| f.libs.iter()) {
+        rustc_link_lib!(lib => "static");
+    }
+    // The JIT of the Arc simulator generates code for the host.
+    if features.iter().any(|f| f.name == "arc") {
+        for lib in native_target_libs() {
+            rustc_link_lib!(lib => "static");
+        }
+    }
+
     let lib_names = [
         "LLVMCore",
         "LLVMTargetParser",
@@ -219,11 +384,16 @@ fn link_libs(lib_dir: &Path) -> Result<()> {
         "CIRCTCAPIHWArith",
         "CIRCTHWArith",
         "CIRCTHWArithToHW",
+        "CIRCTPipelineOps",
+        "CIRCTPipelineTransforms",
         "CIRCTPipelineToHW",
         "CIRCTCAPIHW",
         "CIRCTHWTransforms",
         "CIRCTHWToLLVM",
         "CIRCTHandshakeToHW",
+        "CIRCTHandshake",
+        "CIRCTHandshakeTransforms",
+        "CIRCTCAPIHandshake",
         "CIRCTCAPIComb",
         "CIRCTComb",
         "CIRCTCombToLLVM",
@@ -245,6 +415,8 @@ fn link_libs(lib_dir: &Path) -> Result<()> {
         "CIRCTExportChiselInterface",
         "CIRCTFIRRTLToHW",
         "CIRCTFIRRTLTransforms",
+        "CIRCTESI",
+        "CIRCTCAPIESI",
     ];
     for lib in lib_names {
         rustc_link_lib!(lib => "static");
@@ -13,6 +13,8 @@
 #include "circt/Dialect/Pipeline/Pipeline.h"
 #include "circt/Dialect/HW/HWPasses.h"
 #include "circt/Dialect/HW/HWDialect.h"
+#include "circt/Dialect/HW/HWAttributes.h"
+#include "circt/Dialect/HW/HWTypes.h"
 #include "circt/Dialect/HWArith/HWArithDialect.h"
 #include "circt/Dialect/Comb/CombDialect.h"
 #include "circt/Dialect/Seq/SeqPasses.h"
@@ -30,6 +32,24 @@
 #include "circt/Dialect/SV/SVPasses.h"
 #include "circt/Dialect/SV/SVDialect.h"
 // #include "circt/Conversion/ExportVerilog.h"
+#ifdef CIRCT_SYS_LLHD
+#include "circt/Dialect/LLHD/Transforms/Passes.h"
+#endif
+#ifdef CIRCT_SYS_MSFT
+#include "circt/Dialect/MSFT/MSFTPasses.h"
+#endif
+#ifdef CIRCT_SYS_CALYX
+#include "circt/Dialect/Calyx/CalyxPasses.h"
+#endif
+#ifdef CIRCT_SYS_ARC
+#include "circt/Dialect/Arc/ArcPasses.h"
+#include "circt/Dialect/Arc/ArcTypes.h"
+#include "mlir/Target/LLVMIR/Dialect/Builtin/BuiltinToLLVMIRTranslation.h"
+#include "mlir/Target/LLVMIR/Dialect/LLVMIR/LLVMToLLVMIRTranslation.h"
+#endif
+#ifdef CIRCT_SYS_SYSTEMC
+#include "circt/Dialect/SystemC/SystemCPasses.h"
+#endif
 
 using namespace llvm;
 using namespace mlir;
@@ -44,6 +64,13 @@ MlirAttribute mlirIntegerAttrGetFromString(MlirType type, MlirStringRef value) {
         intType, APInt(tmpWidth, valueStr, 10).trunc(intWidth)));
 }
 
+void mlirIntegerAttrPrintValue(MlirAttribute attr, bool isSigned,
+                               MlirStringCallback callback, void *userData) {
+    SmallString<32> str;
+    unwrap(attr).cast<IntegerAttr>().getValue().toString(str, 10, isSigned);
+    callback(wrap(StringRef(str)), userData);
+}
+
 bool mlirLocationIsFileLineCol(MlirLocation loc) {
     return unwrap(loc).isa<FileLineColLoc>();
 }
@@ -228,3 +255,248 @@ void hwRegisterHWSpecializePass() {
     return ::circt::hw::createHWSpecializePass();
   });
 }
+
+MlirPass handshakeCreateHandshakeToHWPass() {
+  return wrap(::circt::createHandshakeToHWPass().release());
+}
+
+void handshakeRegisterHandshakeToHWPass() {
+    ::mlir::registerPass([]() -> std::unique_ptr<::mlir::Pass> {
+    return ::circt::createHandshakeToHWPass();
+  });
+}
+
+MLIR_DEFINE_CAPI_DIALECT_REGISTRATION(Pipeline, pipeline,
+                                      circt::pipeline::PipelineDialect)
+
+void registerPipelinePasses() {
+  circt::pipeline::registerPasses();
+}
+
+MlirPass pipelineCreateExplicitRegsPass() {
+  return wrap(::circt::pipeline::createExplicitRegsPass().release());
+}
+
+void pipelineRegisterExplicitRegsPass() {
+    ::mlir::registerPass([]() -> std::unique_ptr<::mlir::Pass> {
+    return ::circt::pipeline::createExplicitRegsPass();
+  });
+}
+
+MlirPass pipelineCreatePipelineToHWPass() {
+  return wrap(::circt::createPipelineToHWPass().release());
+}
+
+void pipelineRegisterPipelineToHWPass() {
+    ::mlir::registerPass([]() -> std::unique_ptr<::mlir::Pass> {
+    return ::circt::createPipelineToHWPass();
+  });
+}
+
+#ifdef CIRCT_SYS_LLHD
+void llhdRegisterPasses() {
+  circt::llhd::registerPasses();
+}
+#endif
+
+#ifdef CIRCT_SYS_MSFT
+void msftRegisterPasses() {
+  circt::msft::registerMSFTPasses();
+}
+#endif
+
+#ifdef CIRCT_SYS_CALYX
+void calyxRegisterPasses() {
+  circt::calyx::registerPasses();
+}
+
+MlirPass calyxCreateCalyxToHWPass() {
+  return wrap(::circt::createCalyxToHWPass().release());
+}
+
+void calyxRegisterCalyxToHWPass() {
+    ::mlir::registerPass([]() -> std::unique_ptr<::mlir::Pass> {
+    return ::circt::createCalyxToHWPass();
+  });
+}
+#endif
+
+#ifdef CIRCT_SYS_ARC
+void arcRegisterPasses() {
+  circt::arc::registerPasses();
+}
+
+void arcRegisterConversionPasses() {
+  ::mlir::registerPass([]() -> std::unique_ptr<::mlir::Pass> {
+    return ::circt::createConvertToArcsPass();
+  });
+  ::mlir::registerPass([]() -> std::unique_ptr<::mlir::Pass> {
+    return ::circt::createLowerArcToLLVMPass();
+  });
+}
+
+void arcRegisterLLVMTranslations(MlirContext ctx) {
+  registerBuiltinDialectTranslation(*unwrap(ctx));
+  registerLLVMDialectTranslation(*unwrap(ctx));
+}
+
+bool arcTypeIsAStorageType(MlirType type) {
+  return unwrap(type).isa<arc::StorageType>();
+}
+
+unsigned arcStorageTypeGetSize(MlirType type) {
+  return unwrap(type).cast<arc::StorageType>().getSize();
+}
+
+bool arcTypeIsAStateType(MlirType type) {
+  return unwrap(type).isa<arc::StateType>();
+}
+
+unsigned arcStateTypeGetBitWidth(MlirType type) {
+  return unwrap(type).cast<arc::StateType>().getBitWidth();
+}
+#endif
+
+#ifdef CIRCT_SYS_SYSTEMC
+void systemcRegisterPasses() {
+  circt::systemc::registerPasses();
+}
+#endif
+
+MlirAttribute hwParamDeclRefAttrGetWithType(MlirContext ctx, MlirStringRef name,
+                                            MlirType type) {
+  return wrap(hw::ParamDeclRefAttr::get(StringAttr::get(unwrap(ctx), unwrap(name)),
+                                        unwrap(type)));
+}
+
+bool hwAttrIsAParamExprAttr(MlirAttribute attr) {
+  return unwrap(attr).isa<hw::ParamExprAttr>();
+}
+
+MlirAttribute hwParamExprAttrGet(MlirContext ctx, MlirStringRef opcode,
+                                 intptr_t numOperands,
+                                 MlirAttribute const *operands) {
+  auto peo = hw::symbolizePEO(unwrap(opcode));
+  if (!peo)
+    return MlirAttribute{nullptr};
+  SmallVector<TypedAttr> typedOperands;
+  for (intptr_t i = 0; i < numOperands; ++i) {
+    auto typed = unwrap(operands[i]).dyn_cast_or_null<TypedAttr>();
+    if (!typed)
+      return MlirAttribute{nullptr};
+    typedOperands.push_back(typed);
+  }
+  return wrap(hw::ParamExprAttr::get(*peo, typedOperands));
+}
+
+MlirType hwEvaluateParametricType(MlirLocation loc, MlirAttribute parameters,
+                                  MlirType type) {
+  auto params = unwrap(parameters).dyn_cast_or_null<ArrayAttr>();
+  if (!params)
+    return MlirType{nullptr};
+  auto result = hw::evaluateParametricType(unwrap(loc), params, unwrap(type));
+  if (failed(result))
+    return MlirType{nullptr};
+  return wrap(*result);
+}
+
+bool hwTypeIsAEnumType(MlirType type) {
+  return unwrap(type).isa<hw::EnumType>();
+}
+
+MlirType hwEnumTypeGet(MlirContext ctx, intptr_t numFields,
+                       MlirStringRef const *fields) {
+  auto *context = unwrap(ctx);
+  SmallVector<Attribute> fieldAttrs;
+  for (intptr_t i = 0; i < numFields; ++i)
+    fieldAttrs.push_back(StringAttr::get(context, unwrap(fields[i])));
+  return wrap(hw::EnumType::get(context, ArrayAttr::get(context, fieldAttrs)));
+}
+
+intptr_t hwEnumTypeGetNumFields(MlirType enumType) {
+  return unwrap(enumType).cast<hw::EnumType>().getFields().size();
+}
+
+MlirStringRef hwEnumTypeGetField(MlirType enumType, intptr_t idx) {
+  auto fields = unwrap(enumType).cast<hw::EnumType>().getFields();
+  return wrap(fields[idx].cast<StringAttr>().getValue());
+}
+
+bool hwAttrIsAEnumFieldAttr(MlirAttribute attr) {
+  return unwrap(attr).isa<hw::EnumFieldAttr>();
+}
+
+MlirAttribute hwEnumFieldAttrGet(MlirType enumType, MlirStringRef field) {
+  auto type = unwrap(enumType);
+  return wrap(hw::EnumFieldAttr::get(
+      UnknownLoc::get(type.getContext()),
+      StringAttr::get(type.getContext(), unwrap(field)), type));
+}
+
+bool hwTypeIsAUnionType(MlirType type) {
+  return unwrap(type).isa<hw::UnionType>();
+}
+
+MlirType hwUnionTypeGet(MlirContext ctx, intptr_t numElements,
+                        HWStructFieldInfo const *elements) {
+  SmallVector<hw::UnionType::FieldInfo> fieldInfos;
+  for (intptr_t i = 0; i < numElements; ++i) {
+    hw::UnionType::FieldInfo info{};
+    info.name = unwrap(elements[i].name);
+    info.type = unwrap(elements[i].type);
+    fieldInfos.push_back(info);
+  }
+  return wrap(hw::UnionType::get(unwrap(ctx), fieldInfos));
+}
+
+MlirType hwUnionTypeGetField(MlirType unionType, MlirStringRef fieldName) {
+  auto name = unwrap(fieldName);
+  for (auto &field : unwrap(unionType).cast<hw::UnionType>().getElements())
+    if (field.name.getValue() == name)
+      return wrap(field.type);
+  return MlirType{nullptr};
+}
+
+HWStructFieldInfo hwUnionTypeGetFieldNum(MlirType unionType, unsigned idx) {
+  auto field = unwrap(unionType).cast<hw::UnionType>().getElements()[idx];
+  HWStructFieldInfo ret;
+  ret.name = wrap(field.name);
+  ret.type = wrap(field.type);
+  return ret;
+}
+
+intptr_t hwUnionTypeGetNumFields(MlirType unionType) {
+  return unwrap(unionType).cast<hw::UnionType>().getElements().size();
+}
+
+bool hwTypeIsAUnpackedArrayType(MlirType type) {
+  return unwrap(type).isa<hw::UnpackedArrayType>();
+}
+
+MlirType hwUnpackedArrayTypeGet(MlirType element, size_t size) {
+  return wrap(hw::UnpackedArrayType::get(unwrap(element), size));
+}
+
+MlirType hwUnpackedArrayTypeGetElementType(MlirType type) {
+  return wrap(unwrap(type).cast<hw::UnpackedArrayType>().getElementType());
+}
+
+intptr_t hwUnpackedArrayTypeGetSize(MlirType type) {
+  return unwrap(type).cast<hw::UnpackedArrayType>().getSize();
+}
+
+bool hwAttrIsAInnerSymAttr(MlirAttribute attr) {
+  return unwrap(attr).isa<hw::InnerSymAttr>();
+}
+
+MlirAttribute hwInnerSymAttrGet(MlirAttribute symName) {
+  return wrap(hw::InnerSymAttr::get(unwrap(symName).cast<StringAttr>()));
+}
+
+MlirAttribute hwInnerSymAttrGetSymName(MlirAttribute attr) {
+  return wrap(unwrap(attr).cast<hw::InnerSymAttr>().getSymName());
+}
+
+MlirStringRef hwOutputFileAttrGetFilename(MlirAttribute attr) {
+  return wrap(unwrap(attr).cast<hw::OutputFileAttr>().getFilename().getValue());
+}
@@ -37,9 +37,38 @@
 #include "circt-c/Dialect/Handshake.h"
 #include "circt-c/Dialect/FIRRTL.h"
 #include "circt-c/Dialect/ESI.h"
-// #include "circt-c/Dialect/LLHD.h"
-// #include "circt-c/Dialect/MSFT.h"
-// #include "circt-c/Dialect/Moore.h"
+
+// Optional dialects, enabled by the cargo feature of the same name.
+#ifdef CIRCT_SYS_LLHD
+#include "circt-c/Dialect/LLHD.h"
+#endif
+#ifdef CIRCT_SYS_MOORE
+#include "circt-c/Dialect/Moore.h"
+#endif
+#ifdef CIRCT_SYS_MSFT
+#include "circt-c/Dialect/MSFT.h"
+#endif
+#ifdef CIRCT_SYS_CALYX
+#include "circt-c/Dialect/Calyx.h"
+#endif
+#ifdef CIRCT_SYS_ARC
+#include "circt-c/Dialect/Arc.h"
+#endif
+#ifdef CIRCT_SYS_VERIF
+#include "circt-c/Dialect/Verif.h"
+#endif
+#ifdef CIRCT_SYS_LTL
+#include "circt-c/Dialect/LTL.h"
+#endif
+#ifdef CIRCT_SYS_OM
+#include "circt-c/Dialect/OM.h"
+#endif
+#ifdef CIRCT_SYS_DEBUG
+#include "circt-c/Dialect/Debug.h"
+#endif
+#ifdef CIRCT_SYS_SYSTEMC
+#include "circt-c/Dialect/SystemC.h"
+#endif
 
 
 #ifdef __cplusplus
@@ -51,6 +80,13 @@ extern "C" {
 MLIR_CAPI_EXPORTED MlirAttribute
 mlirIntegerAttrGetFromString(MlirType type, MlirStringRef value);
 
+/// Prints the value of an integer attribute of any width in decimal, as a
+/// signed or unsigned integer.
+MLIR_CAPI_EXPORTED void mlirIntegerAttrPrintValue(MlirAttribute attr,
+                                                  bool isSigned,
+                                                  MlirStringCallback callback,
+                                                  void *userData);
+
 //===----------------------------------------------------------------------===//
 // Location API Extensions
 //===----------------------------------------------------------------------===//
@@ -82,6 +118,104 @@ MLIR_CAPI_EXPORTED void hwRegisterFlattenIOPass();
 MLIR_CAPI_EXPORTED MlirPass hwCreateHWSpecializePass();
 MLIR_CAPI_EXPORTED void hwRegisterHWSpecializePass();
 
+MLIR_CAPI_EXPORTED MlirPass handshakeCreateHandshakeToHWPass();
+MLIR_CAPI_EXPORTED void handshakeRegisterHandshakeToHWPass();
+
+MLIR_DECLARE_CAPI_DIALECT_REGISTRATION(Pipeline, pipeline);
+MLIR_CAPI_EXPORTED void registerPipelinePasses();
+
+MLIR_CAPI_EXPORTED MlirPass pipelineCreateExplicitRegsPass();
+MLIR_CAPI_EXPORTED void pipelineRegisterExplicitRegsPass();
+
+MLIR_CAPI_EXPORTED MlirPass pipelineCreatePipelineToHWPass();
+MLIR_CAPI_EXPORTED void pipelineRegisterPipelineToHWPass();
+
+#ifdef CIRCT_SYS_LLHD
+MLIR_CAPI_EXPORTED void llhdRegisterPasses();
+#endif
+#ifdef CIRCT_SYS_MSFT
+MLIR_CAPI_EXPORTED void msftRegisterPasses();
+#endif
+#ifdef CIRCT_SYS_CALYX
+MLIR_CAPI_EXPORTED void calyxRegisterPasses();
+MLIR_CAPI_EXPORTED MlirPass calyxCreateCalyxToHWPass();
+MLIR_CAPI_EXPORTED void calyxRegisterCalyxToHWPass();
+#endif
+#ifdef CIRCT_SYS_ARC
+MLIR_CAPI_EXPORTED void arcRegisterPasses();
+/// Registers `convert-to-arcs` and `lower-arc-to-llvm`.
+MLIR_CAPI_EXPORTED void arcRegisterConversionPasses();
+/// Registers the translations of the builtin and LLVM dialects to LLVM IR, as
+/// needed by the execution engine.
+MLIR_CAPI_EXPORTED void arcRegisterLLVMTranslations(MlirContext ctx);
+MLIR_CAPI_EXPORTED bool arcTypeIsAStorageType(MlirType);
+MLIR_CAPI_EXPORTED unsigned arcStorageTypeGetSize(MlirType);
+MLIR_CAPI_EXPORTED bool arcTypeIsAStateType(MlirType);
+MLIR_CAPI_EXPORTED unsigned arcStateTypeGetBitWidth(MlirType);
+#endif
+#ifdef CIRCT_SYS_SYSTEMC
+MLIR_CAPI_EXPORTED void systemcRegisterPasses();
+#endif
+
+//===----------------------------------------------------------------------===//
+// HW Parameter API Extensions
+//===----------------------------------------------------------------------===//
+
+/// Creates a `#hw.param.decl.ref` of the given type. Returns null if creation fails.
+MLIR_CAPI_EXPORTED MlirAttribute hwParamDeclRefAttrGetWithType(MlirContext ctx,
+                                                               MlirStringRef name,
+                                                               MlirType type);
+
+MLIR_CAPI_EXPORTED bool hwAttrIsAParamExprAttr(MlirAttribute);
+
+/// Creates a (folded) `#hw.param.expr` attribute. `opcode` is the name of a
+/// `PEO` enum case, e.g. "Add" or "CLog2". Returns null on failure.
+MLIR_CAPI_EXPORTED MlirAttribute
+hwParamExprAttrGet(MlirContext ctx, MlirStringRef opcode, intptr_t numOperands,
+                   MlirAttribute const *operands);
+
+/// Substitutes the parameter references in a parametric type given an array of
+/// `ParamDeclAttr`s. Returns null if the evaluation fails.
+MLIR_CAPI_EXPORTED MlirType hwEvaluateParametricType(MlirLocation loc,
+                                                     MlirAttribute parameters,
+                                                     MlirType type);
+
+//===----------------------------------------------------------------------===//
+// HW Enum, Union and Unpacked Array API Extensions
+//===----------------------------------------------------------------------===//
+
+MLIR_CAPI_EXPORTED bool hwTypeIsAEnumType(MlirType);
+MLIR_CAPI_EXPORTED MlirType hwEnumTypeGet(MlirContext ctx, intptr_t numFields,
+                                          MlirStringRef const *fields);
+MLIR_CAPI_EXPORTED intptr_t hwEnumTypeGetNumFields(MlirType enumType);
+MLIR_CAPI_EXPORTED MlirStringRef hwEnumTypeGetField(MlirType enumType,
+                                                    intptr_t idx);
+
+MLIR_CAPI_EXPORTED bool hwAttrIsAEnumFieldAttr(MlirAttribute);
+MLIR_CAPI_EXPORTED MlirAttribute hwEnumFieldAttrGet(MlirType enumType,
+                                                    MlirStringRef field);
+
+MLIR_CAPI_EXPORTED bool hwTypeIsAUnionType(MlirType);
+MLIR_CAPI_EXPORTED MlirType hwUnionTypeGet(MlirContext ctx,
+                                           intptr_t numElements,
+                                           HWStructFieldInfo const *elements);
+MLIR_CAPI_EXPORTED MlirType hwUnionTypeGetField(MlirType unionType,
+                                                MlirStringRef fieldName);
+MLIR_CAPI_EXPORTED HWStructFieldInfo hwUnionTypeGetFieldNum(MlirType unionType,
+                                                            unsigned idx);
+MLIR_CAPI_EXPORTED intptr_t hwUnionTypeGetNumFields(MlirType unionType);
+
+MLIR_CAPI_EXPORTED bool hwTypeIsAUnpackedArrayType(MlirType);
+MLIR_CAPI_EXPORTED MlirType hwUnpackedArrayTypeGet(MlirType element,
+                                                   size_t size);
+MLIR_CAPI_EXPORTED MlirType hwUnpackedArrayTypeGetElementType(MlirType);
+MLIR_CAPI_EXPORTED intptr_t hwUnpackedArrayTypeGetSize(MlirType);
+
+MLIR_CAPI_EXPORTED bool hwAttrIsAInnerSymAttr(MlirAttribute);
+MLIR_CAPI_EXPORTED MlirAttribute hwInnerSymAttrGet(MlirAttribute symName);
+MLIR_CAPI_EXPORTED MlirAttribute hwInnerSymAttrGetSymName(MlirAttribute);
+
+MLIR_CAPI_EXPORTED MlirStringRef hwOutputFileAttrGetFilename(MlirAttribute);
 
 #ifdef __cplusplus
 }
@@ -0,0 +1,687 @@
+// Copyright (c) 2022-2023 Kamyar Mohajerani
+
+//! And-inverter graphs (AIGs): bit-level netlists of two-input AND gates and inverters.
+//! `bitblast` lowers an HW module of comb logic and `seq` registers into an `Aig`, whose words
+//!  are vectors of `Lit`s with the least significant bit first. `aiger` and `blif` write it
+//!  for formal and logic synthesis tools.
+
+use crate::analysis::loops::operand_order;
+use crate::analysis::{int_width, string_attr};
+use crate::crate_prelude::*;
+use hw::HwModuleLike;
+use num::{BigUint, One, Zero};
+use std::collections::HashMap;
+use std::ops::Not;
+
+pub mod aiger;
+pub mod blif;
+
+/// A possibly inverted edge to a node. As in AIGER, the literal of node `n` is `2 * n` and its
+///  inversion is `2 * n + 1`; node 0 is constant false.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Lit(u32);
+
+impl Lit {
+    pub const FALSE: Lit = Lit(0);
+    pub const TRUE: Lit = Lit(1);
+
+    pub fn new(node: usize, negated: bool) -> Self {
+        Lit((node as u32) << 1 | negated as u32)
+    }
+
+    pub fn node(self) -> usize {
+        (self.0 >> 1) as usize
+    }
+
+    pub fn is_negated(self) -> bool {
+        self.0 & 1 == 1
+    }
+
+    /// The AIGER encoding of this literal.
+    pub fn code(self) -> u32 {
+        self.0
+    }
+}
+
+impl Not for Lit {
+    type Output = Lit;
+
+    fn not(self) -> Lit {
+        Lit(self.0 ^ 1)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Node {
+    False,
+    /// A bit of an input port.
+    Input,
+    /// The current state of a register bit.
+    Latch,
+    And(Lit, Lit),
+}
+
+/// A named word.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Port {
+    pub name: String,
+    pub bits: Vec<Lit>,
+}
+
+/// A register, whose `bits` take the value of `next` on every clock edge, starting from `init`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Register {
+    pub name: String,
+    pub bits: Vec<Lit>,
+    pub next: Vec<Lit>,
+    pub init: BigUint,
+}
+
+/// An AIG with named input, output and register words. AND nodes are structurally hashed and
+///  always come after their fanins.
+#[derive(Debug, Clone)]
+pub struct Aig {
+    nodes: Vec<Node>,
+    strash: HashMap<(Lit, Lit), Lit>,
+    inputs: Vec<Port>,
+    outputs: Vec<Port>,
+    registers: Vec<Register>,
+}
+
+impl Default for Aig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Aig {
+    pub fn new() -> Self {
+        Self {
+            nodes: vec![Node::False],
+            strash: HashMap::new(),
+            inputs: vec![],
+            outputs: vec![],
+            registers: vec![],
+        }
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    pub fn inputs(&self) -> &[Port] {
+        &self.inputs
+    }
+
+    pub fn outputs(&self) -> &[Port] {
+        &self.outputs
+    }
+
+    pub fn registers(&self) -> &[Register] {
+        &self.registers
+    }
+
+    pub fn num_ands(&self) -> usize {
+        self.nodes.iter().filter(|node| matches!(node, Node::And(..))).count()
+    }
+
+    fn add_nodes(&mut self, node: Node, width: u32) -> Vec<Lit> {
+        (0..width)
+            .map(|_| {
+                self.nodes.push(node);
+                Lit::new(self.nodes.len() - 1, false)
+            })
+            .collect()
+    }
+
+    pub fn add_input(&mut self, name: &str, width: u32) -> Vec<Lit> {
+        let bits = self.add_nodes(Node::Input, width);
+        self.inputs.push(Port {
+            name: name.to_string(),
+            bits: bits.clone(),
+        });
+        bits
+    }
+
+    pub fn add_output(&mut self, name: &str, bits: Vec<Lit>) {
+        self.outputs.push(Port {
+            name: name.to_string(),
+            bits,
+        });
+    }
+
+    /// Add a register holding its value until `set_register_next`, and return its index.
+    pub fn add_register(&mut self, name: &str, width: u32, init: BigUint) -> usize {
+        let bits = self.add_nodes(Node::Latch, width);
+        self.registers.push(Register {
+            name: name.to_string(),
+            next: bits.clone(),
+            bits,
+            init,
+        });
+        self.registers.len() - 1
+    }
+
+    pub fn set_register_next(&mut self, index: usize, next: Vec<Lit>) {
+        self.registers[index].next = next;
+    }
+
+    pub fn and(&mut self, a: Lit, b: Lit) -> Lit {
+        if a == Lit::FALSE || b == Lit::FALSE || a == !b {
+            return Lit::FALSE;
+        }
+        if a == Lit::TRUE || a == b {
+            return b;
+        }
+        if b == Lit::TRUE {
+            return a;
+        }
+        let key = (a.min(b), a.max(b));
+        if let Some(lit) = self.strash.get(&key) {
+            return *lit;
+        }
+        self.nodes.push(Node::And(key.0, key.1));
+        let lit = Lit::new(self.nodes.len() - 1, false);
+        self.strash.insert(key, lit);
+        lit
+    }
+
+    pub fn or(&mut self, a: Lit, b: Lit) -> Lit {
+        !self.and(!a, !b)
+    }
+
+    pub fn xor(&mut self, a: Lit, b: Lit) -> Lit {
+        let (x, y) = (self.and(a, !b), self.and(!a, b));
+        self.or(x, y)
+    }
+
+    /// `t` if `cond` is set, else `e`.
+    pub fn mux(&mut self, cond: Lit, t: Lit, e: Lit) -> Lit {
+        let (x, y) = (self.and(cond, t), self.and(!cond, e));
+        self.or(x, y)
+    }
+
+    /// The `width` lowest bits of `value`.
+    pub fn constant(value: &BigUint, width: u32) -> Vec<Lit> {
+        (0..width)
+            .map(|i| {
+                if value.bit(i as u64) {
+                    Lit::TRUE
+                } else {
+                    Lit::FALSE
+                }
+            })
+            .collect()
+    }
+
+    fn bitwise(&mut self, a: &[Lit], b: &[Lit], f: fn(&mut Self, Lit, Lit) -> Lit) -> Vec<Lit> {
+        a.iter().zip(b).map(|(a, b)| f(self, *a, *b)).collect()
+    }
+
+    pub fn and_word(&mut self, a: &[Lit], b: &[Lit]) -> Vec<Lit> {
+        self.bitwise(a, b, Self::and)
+    }
+
+    pub fn or_word(&mut self, a: &[Lit], b: &[Lit]) -> Vec<Lit> {
+        self.bitwise(a, b, Self::or)
+    }
+
+    pub fn xor_word(&mut self, a: &[Lit], b: &[Lit]) -> Vec<Lit> {
+        self.bitwise(a, b, Self::xor)
+    }
+
+    pub fn mux_word(&mut self, cond: Lit, t: &[Lit], e: &[Lit]) -> Vec<Lit> {
+        t.iter().zip(e).map(|(t, e)| self.mux(cond, *t, *e)).collect()
+    }
+
+    /// A ripple-carry adder, returning the sum and the carry out.
+    pub fn add_carry(&mut self, a: &[Lit], b: &[Lit], mut carry: Lit) -> (Vec<Lit>, Lit) {
+        let mut sum = Vec::with_capacity(a.len());
+        for (a, b) in a.iter().zip(b) {
+            let half = self.xor(*a, *b);
+            sum.push(self.xor(half, carry));
+            let (generate, propagate) = (self.and(*a, *b), self.and(half, carry));
+            carry = self.or(generate, propagate);
+        }
+        (sum, carry)
+    }
+
+    pub fn add(&mut self, a: &[Lit], b: &[Lit]) -> Vec<Lit> {
+        self.add_carry(a, b, Lit::FALSE).0
+    }
+
+    pub fn sub(&mut self, a: &[Lit], b: &[Lit]) -> Vec<Lit> {
+        self.add_carry(a, &not_word(b), Lit::TRUE).0
+    }
+
+    pub fn neg(&mut self, a: &[Lit]) -> Vec<Lit> {
+        self.sub(&vec![Lit::FALSE; a.len()], a)
+    }
+
+    /// A shift-and-add multiplier, truncated to the operand width.
+    pub fn mul(&mut self, a: &[Lit], b: &[Lit]) -> Vec<Lit> {
+        let mut product = vec![Lit::FALSE; a.len()];
+        for (i, b) in b.iter().enumerate() {
+            let partial: Vec<Lit> = (0..a.len())
+                .map(|j| {
+                    if j < i {
+                        Lit::FALSE
+                    } else {
+                        self.and(a[j - i], *b)
+                    }
+                })
+                .collect();
+            product = self.add(&product, &partial);
+        }
+        product
+    }
+
+    /// Restoring division, returning the quotient and the remainder. Division by zero gives a
+    ///  quotient of all ones and the dividend as remainder.
+    pub fn udivrem(&mut self, a: &[Lit], b: &[Lit]) -> (Vec<Lit>, Vec<Lit>) {
+        let width = a.len();
+        let divisor: Vec<Lit> = not_word(b).into_iter().chain([Lit::TRUE]).collect();
+        let mut quotient = vec![Lit::FALSE; width];
+        let mut remainder = vec![Lit::FALSE; width];
+        for i in (0..width).rev() {
+            let shifted: Vec<Lit> = [a[i]].into_iter().chain(remainder).collect();
+            let (difference, fits) = self.add_carry(&shifted, &divisor, Lit::TRUE);
+            quotient[i] = fits;
+            remainder = self.mux_word(fits, &difference[..width], &shifted[..width]);
+        }
+        (quotient, remainder)
+    }
+
+    /// Signed division truncating towards zero, returning the quotient and the remainder, which
+    ///  has the sign of the dividend.
+    pub fn sdivrem(&mut self, a: &[Lit], b: &[Lit]) -> (Vec<Lit>, Vec<Lit>) {
+        let (Some(&sign_a), Some(&sign_b)) = (a.last(), b.last()) else {
+            return (vec![], vec![]);
+        };
+        let (neg_a, neg_b) = (self.neg(a), self.neg(b));
+        let abs_a = self.mux_word(sign_a, &neg_a, a);
+        let abs_b = self.mux_word(sign_b, &neg_b, b);
+        let (quotient, remainder) = self.udivrem(&abs_a, &abs_b);
+        let (neg_quotient, neg_remainder) = (self.neg(&quotient), self.neg(&remainder));
+        let sign = self.xor(sign_a, sign_b);
+        (
+            self.mux_word(sign, &neg_quotient, &quotient),
+            self.mux_word(sign_a, &neg_remainder, &remainder),
+        )
+    }
+
+    /// A barrel shifter. Shifting by the width or more gives zero, or copies of the sign bit for
+    ///  arithmetic right shifts.
+    pub fn shift(&mut self, a: &[Lit], amount: &[Lit], kind: Shift) -> Vec<Lit> {
+        let width = a.len();
+        let fill = match (kind, a.last()) {
+            (Shift::RightArithmetic, Some(sign)) => *sign,
+            _ => Lit::FALSE,
+        };
+        let mut result = a.to_vec();
+        let mut overflow = Lit::FALSE;
+        for (k, bit) in amount.iter().enumerate() {
+            match 1usize.checked_shl(k as u32).filter(|distance| *distance < width) {
+                Some(distance) => {
+                    let shifted: Vec<Lit> = (0..width)
+                        .map(|i| match kind {
+                            Shift::Left if i >= distance => result[i - distance],
+                            Shift::Left => Lit::FALSE,
+                            _ if i + distance < width => result[i + distance],
+                            _ => fill,
+                        })
+                        .collect();
+                    result = self.mux_word(*bit, &shifted, &result);
+                }
+                None => overflow = self.or(overflow, *bit),
+            }
+        }
+        self.mux_word(overflow, &vec![fill; width], &result)
+    }
+
+    pub fn eq(&mut self, a: &[Lit], b: &[Lit]) -> Lit {
+        a.iter().zip(b).fold(Lit::TRUE, |acc, (a, b)| {
+            let bit = self.xor(*a, *b);
+            self.and(acc, !bit)
+        })
+    }
+
+    /// Unsigned `a < b`, from the borrow of `a - b`.
+    pub fn ult(&mut self, a: &[Lit], b: &[Lit]) -> Lit {
+        !self.add_carry(a, &not_word(b), Lit::TRUE).1
+    }
+
+    /// Signed `a < b`, as unsigned with inverted sign bits.
+    pub fn slt(&mut self, a: &[Lit], b: &[Lit]) -> Lit {
+        let flip = |word: &[Lit]| {
+            let mut word = word.to_vec();
+            if let Some(sign) = word.last_mut() {
+                *sign = !*sign;
+            }
+            word
+        };
+        self.ult(&flip(a), &flip(b))
+    }
+
+    pub fn icmp(&mut self, pred: comb::CmpPred, a: &[Lit], b: &[Lit]) -> Lit {
+        use comb::CmpPred::*;
+        match pred {
+            Eq => self.eq(a, b),
+            Neq => !self.eq(a, b),
+            Ult => self.ult(a, b),
+            Ule => !self.ult(b, a),
+            Ugt => self.ult(b, a),
+            Uge => !self.ult(a, b),
+            Slt => self.slt(a, b),
+            Sle => !self.slt(b, a),
+            Sgt => self.slt(b, a),
+            Sge => !self.slt(a, b),
+        }
+    }
+
+    pub fn parity(&mut self, a: &[Lit]) -> Lit {
+        a.iter().fold(Lit::FALSE, |acc, bit| self.xor(acc, *bit))
+    }
+
+    /// Evaluate the AIG for the values of its input ports and registers, in order, returning the
+    ///  values of its output ports and the next values of its registers.
+    pub fn eval(&self, inputs: &[BigUint], registers: &[BigUint]) -> (Vec<BigUint>, Vec<BigUint>) {
+        let mut values = vec![false; self.nodes.len()];
+        let sources = self.inputs.iter().map(|port| &port.bits).zip(inputs);
+        let states = self.registers.iter().map(|reg| &reg.bits).zip(registers);
+        for (bits, value) in sources.chain(states) {
+            for (i, bit) in bits.iter().enumerate() {
+                values[bit.node()] = value.bit(i as u64);
+            }
+        }
+        let value = |values: &[bool], lit: &Lit| values[lit.node()] ^ lit.is_negated();
+        for (index, node) in self.nodes.iter().enumerate() {
+            if let Node::And(a, b) = node {
+                values[index] = value(&values, a) && value(&values, b);
+            }
+        }
+        let word = |bits: &[Lit]| {
+            bits.iter().enumerate().fold(BigUint::zero(), |acc, (i, bit)| {
+                if value(&values, bit) {
+                    acc | (BigUint::one() << i)
+                } else {
+                    acc
+                }
+            })
+        };
+        (
+            self.outputs.iter().map(|port| word(&port.bits)).collect(),
+            self.registers.iter().map(|reg| word(&reg.next)).collect(),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shift {
+    Left,
+    RightLogical,
+    RightArithmetic,
+}
+
+/// The name of bit `index` of the word `name`, which is the name itself for single bits.
+pub fn bit_name(name: &str, width: usize, index: usize) -> String {
+    if width == 1 {
+        name.to_string()
+    } else {
+        format!("{}[{}]", name, index)
+    }
+}
+
+pub fn not_word(a: &[Lit]) -> Vec<Lit> {
+    a.iter().map(|bit| !*bit).collect()
+}
+
+/// The results of a comb, `hw.constant` or `hw.wire` operation.
+fn blast_op(
+    aig: &mut Aig,
+    op: &Operation,
+    words: &HashMap<Value, Vec<Lit>>,
+) -> Result<Vec<Lit>, Error> {
+    let name = op.name().to_string();
+    let args: Vec<&[Lit]> = op.operands().iter().map(|arg| words[arg].as_slice()).collect();
+    let arg = |i: usize| args.get(i).copied().ok_or(Error::IsNone);
+    let width = int_width(&op.result_at(0).ok_or(Error::IsNone)?)?;
+    let int_attr = |attr_name: &str| {
+        op.attribute(attr_name)
+            .and_then(|attr| IntegerAttr::try_from(attr).ok())
+            .ok_or(Error::IsNone)
+    };
+    let variadic = |aig: &mut Aig, f: fn(&mut Aig, &[Lit], &[Lit]) -> Vec<Lit>| {
+        let (first, rest) = args.split_first().ok_or(Error::IsNone)?;
+        Ok::<_, Error>(rest.iter().fold(first.to_vec(), |acc, arg| f(aig, &acc, arg)))
+    };
+    Ok(match name.as_str() {
+        "hw.constant" => Aig::constant(&int_attr("value")?.to_biguint(), width),
+        "hw.wire" => arg(0)?.to_vec(),
+        "comb.add" => variadic(aig, Aig::add)?,
+        "comb.mul" => variadic(aig, Aig::mul)?,
+        "comb.and" => variadic(aig, Aig::and_word)?,
+        "comb.or" => variadic(aig, Aig::or_word)?,
+        "comb.xor" => variadic(aig, Aig::xor_word)?,
+        "comb.sub" => aig.sub(arg(0)?, arg(1)?),
+        "comb.divu" => aig.udivrem(arg(0)?, arg(1)?).0,
+        "comb.modu" => aig.udivrem(arg(0)?, arg(1)?).1,
+        "comb.divs" => aig.sdivrem(arg(0)?, arg(1)?).0,
+        "comb.mods" => aig.sdivrem(arg(0)?, arg(1)?).1,
+        "comb.shl" => aig.shift(arg(0)?, arg(1)?, Shift::Left),
+        "comb.shru" => aig.shift(arg(0)?, arg(1)?, Shift::RightLogical),
+        "comb.shrs" => aig.shift(arg(0)?, arg(1)?, Shift::RightArithmetic),
+        "comb.icmp" => {
+            let pred = comb::CmpPred::from_attr_value(int_attr("predicate")?.value())
+                .ok_or(Error::IsNone)?;
+            vec![aig.icmp(pred, arg(0)?, arg(1)?)]
+        }
+        // The first operand is the most significant.
+        "comb.concat" => args.iter().rev().flat_map(|arg| arg.iter().copied()).collect(),
+        "comb.extract" => {
+            let low = int_attr("lowBit")?.value() as usize;
+            arg(0)?.get(low..low + width as usize).ok_or(Error::IsNone)?.to_vec()
+        }
+        "comb.replicate" => arg(0)?.iter().copied().cycle().take(width as usize).collect(),
+        "comb.mux" => {
+            let cond = *arg(0)?.first().ok_or(Error::IsNone)?;
+            aig.mux_word(cond, arg(1)?, arg(2)?)
+        }
+        "comb.parity" => vec![aig.parity(arg(0)?)],
+        _ => {
+            return Err(Error::simple(format!("Unsupported operation `{}` for bit-blasting", name)))
+        }
+    })
+}
+
+/// Lower `module` into an AIG. It may contain comb operations, `hw.constant`, `hw.wire` and
+///  `seq.compreg`, `seq.compreg.ce` and `seq.firreg` registers, all on integers. Registers are
+///  named by their `name` attribute, or `_reg<index>` if they have none; clocks are ignored and
+///  asynchronous resets are treated as synchronous.
+pub fn bitblast(module: &hw::HwModuleOp) -> Result<Aig, Error> {
+    let module_name = module.module_name();
+    let body = module.first_block().ok_or(Error::IsNone)?;
+    let ports = module.port_info();
+    let mut aig = Aig::new();
+    let mut words: HashMap<Value, Vec<Lit>> = HashMap::new();
+    for (port, arg) in ports.inputs.iter().zip(body.arguments()) {
+        words.insert(arg, aig.add_input(&port.name, int_width(&arg)?));
+    }
+
+    let mut results = vec![];
+    let mut registers = vec![];
+    let mut comb = vec![];
+    for op in body.operations() {
+        match op.name().to_string().as_str() {
+            "hw.output" => results = op.operands(),
+            "seq.compreg" | "seq.compreg.ce" | "seq.firreg" => {
+                let result = op.result_at(0).ok_or(Error::IsNone)?;
+                let reg_name = string_attr(&op, "name")
+                    .unwrap_or_else(|| format!("_reg{}", registers.len()));
+                let init = op
+                    .attribute("preset")
+                    .and_then(|attr| IntegerAttr::try_from(attr).ok())
+                    .map(|attr| attr.to_biguint())
+                    .unwrap_or_default();
+                let index = aig.add_register(&reg_name, int_width(&result)?, init);
+                words.insert(result, aig.registers()[index].bits.clone());
+                registers.push((index, op));
+            }
+            _ => comb.push(op),
+        }
+    }
+
+    // Operations are lowered after the operations defining their operands.
+    let order = operand_order(&comb).map_err(|op| {
+        Error::simple(format!(
+            "Combinational loop through `{}` in `{}`",
+            op.name().to_string(),
+            module_name
+        ))
+    })?;
+    for op in order {
+        let bits = blast_op(&mut aig, &op, &words)?;
+        words.extend(op.result_at(0).map(|result| (result, bits)));
+    }
+
+    let word = |value: Option<Value>| {
+        value.and_then(|value| words.get(&value).cloned()).ok_or(Error::IsNone)
+    };
+    for (index, op) in registers {
+        let state = aig.registers()[index].bits.clone();
+        let (mut next, reset) = match op.name().to_string().as_str() {
+            "seq.compreg.ce" => {
+                let (input, enable) = (word(op.operand(0))?, word(op.operand(2))?);
+                let enable = *enable.first().ok_or(Error::IsNone)?;
+                (aig.mux_word(enable, &input, &state), 3)
+            }
+            _ => (word(op.operand(0))?, 2),
+        };
+        if op.num_operands() >= reset + 2 {
+            let (reset, value) = (word(op.operand(reset))?, word(op.operand(reset + 1))?);
+            next = aig.mux_word(*reset.first().ok_or(Error::IsNone)?, &value, &next);
+        }
+        aig.set_register_next(index, next);
+    }
+    for (port, value) in ports.outputs.iter().zip(results) {
+        aig.add_output(&port.name, word(Some(value))?);
+    }
+    Ok(aig)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use comb::fold::{self, Bits};
+    use hw::{HwModuleOp, ModulePortInfo};
+
+    #[test]
+    fn word_semantics() {
+        let mut aig = Aig::new();
+        let (a, b) = (aig.add_input("a", 4), aig.add_input("b", 4));
+        let binary = [
+            ("comb.add", aig.add(&a, &b)),
+            ("comb.sub", aig.sub(&a, &b)),
+            ("comb.mul", aig.mul(&a, &b)),
+            ("comb.divu", aig.udivrem(&a, &b).0),
+            ("comb.modu", aig.udivrem(&a, &b).1),
+            ("comb.divs", aig.sdivrem(&a, &b).0),
+            ("comb.mods", aig.sdivrem(&a, &b).1),
+            ("comb.shl", aig.shift(&a, &b, Shift::Left)),
+            ("comb.shru", aig.shift(&a, &b, Shift::RightLogical)),
+            ("comb.shrs", aig.shift(&a, &b, Shift::RightArithmetic)),
+        ];
+        for (name, bits) in &binary {
+            aig.add_output(name, bits.clone());
+        }
+        for pred in [comb::CmpPred::Ule, comb::CmpPred::Slt, comb::CmpPred::Sge] {
+            let bit = aig.icmp(pred, &a, &b);
+            aig.add_output("icmp", vec![bit]);
+        }
+        assert_eq!(aig.num_ands(), aig.strash.len());
+
+        for (x, y) in (0..16u32).flat_map(|x| (0..16u32).map(move |y| (x, y))) {
+            let (lhs, rhs) = (Bits::new(4, x.into()), Bits::new(4, y.into()));
+            let (outputs, _) = aig.eval(&[x.into(), y.into()], &[]);
+            for ((name, _), value) in binary.iter().zip(&outputs) {
+                if let Some(expected) = fold::binary(name, &lhs, &rhs) {
+                    assert_eq!(value, expected.value(), "{} {} {}", name, x, y);
+                }
+            }
+            let compares = [
+                fold::icmp(comb::CmpPred::Ule, &lhs, &rhs).unwrap(),
+                fold::icmp(comb::CmpPred::Slt, &lhs, &rhs).unwrap(),
+                fold::icmp(comb::CmpPred::Sge, &lhs, &rhs).unwrap(),
+            ];
+            for (value, expected) in outputs[binary.len()..].iter().zip(compares) {
+                assert_eq!(*value, BigUint::from(expected as u32));
+            }
+        }
+    }
+
+    #[test]
+    fn bitblast_counter() -> miette::Result<()> {
+        let ctx = OwnedContext::default();
+        hw::dialect().load(&ctx).unwrap();
+        comb::dialect().load(&ctx).unwrap();
+        seq::dialect().load(&ctx).unwrap();
+
+        let mut builder = OpBuilder::new(&ctx);
+        let module = Module::create(builder.loc());
+        let i1 = IntegerType::new(&ctx, 1);
+        let i8 = IntegerType::new(&ctx, 8);
+        let mut ports = ModulePortInfo::default();
+        ports.add_input("clk", &i1);
+        ports.add_input("rst", &i1);
+        ports.add_input("en", &i1);
+        ports.add_input("inc", &i8);
+        ports.add_output("count", &i8);
+        ports.add_output("swapped", &i8);
+        ports.add_output("big", &i1);
+        let counter = HwModuleOp::build_with(
+            &mut builder,
+            &module,
+            "counter",
+            &ports,
+            &[],
+            "",
+            |builder, _, inputs, outputs| {
+                let zero = hw::ConstantOp::build(builder, 8, 0).result();
+                let count = seq::CompRegClockEnabledOp::build(
+                    builder,
+                    "count",
+                    &inputs["inc"],
+                    &inputs["clk"],
+                    &inputs["en"],
+                    Some(&inputs["rst"]),
+                    Some(&zero),
+                )
+                .unwrap();
+                let next = comb::AddOp::build(builder, &count.output(), &inputs["inc"]).unwrap();
+                count.set_input(&next.result());
+                let low = comb::ExtractOp::with_sizes(builder, &count.output(), 0, 4).unwrap();
+                let high = comb::ExtractOp::with_sizes(builder, &count.output(), 4, 4).unwrap();
+                let swapped = comb::ConcatOp::build(builder, [low.result(), high.result()]);
+                let limit = hw::ConstantOp::build(builder, 8, 100).result();
+                let big = comb::ICmpOp::build(builder, comb::CmpPred::Ugt, &count.output(), &limit)
+                    .unwrap();
+                outputs.insert("count".to_string(), count.output());
+                outputs.insert("swapped".to_string(), swapped.unwrap().result());
+                outputs.insert("big".to_string(), big.result());
+            },
+        )?;
+
+        let aig = bitblast(&counter)?;
+        let names: Vec<&str> = aig.inputs().iter().map(|port| port.name.as_str()).collect();
+        assert_eq!(names, ["clk", "rst", "en", "inc"]);
+        assert_eq!(aig.registers().len(), 1);
+        assert_eq!(aig.registers()[0].name, "count");
+        let values =
+            |values: &[u32]| -> Vec<BigUint> { values.iter().map(|v| BigUint::from(*v)).collect() };
+        let eval = |inputs: &[u32], count: u32| aig.eval(&values(inputs), &values(&[count]));
+        assert_eq!(eval(&[0, 0, 1, 7], 0x9a), (values(&[0x9a, 0xa9, 1]), values(&[0xa1])));
+        assert_eq!(eval(&[0, 0, 0, 7], 0x12), (values(&[0x12, 0x21, 0]), values(&[0x12])));
+        assert_eq!(eval(&[0, 1, 0, 7], 0x12).1, values(&[0]));
+        Ok(())
+    }
+}
@@ -0,0 +1,144 @@
+// Copyright (c) 2022-2023 Kamyar Mohajerani
+
+//! AIGER netlists of an `Aig`, in the ASCII (`aag`) or binary (`aig`) format, e.g. for ABC and
+//!  hardware model checkers. Variables are numbered inputs first, then latches, then AND gates,
+//!  and the symbol table names each bit, e.g. `sum[3]`.
+//! See http://fmv.jku.at/aiger/ for the format.
+
+use super::{bit_name, Aig, Lit, Node};
+use crate::crate_prelude::*;
+use std::io::Write;
+
+/// Write `delta` in 7-bit groups, least significant first, with the high bit set on all but
+///  the last.
+fn write_delta(writer: &mut impl Write, mut delta: u32) -> std::io::Result<()> {
+    while delta >= 0x80 {
+        writer.write_all(&[(delta & 0x7f) as u8 | 0x80])?;
+        delta >>= 7;
+    }
+    writer.write_all(&[delta as u8])
+}
+
+fn write(aig: &Aig, mut writer: impl Write, binary: bool) -> Result<(), Error> {
+    let mut vars = vec![0; aig.nodes().len()];
+    let inputs: Vec<Lit> = aig.inputs().iter().flat_map(|port| port.bits.clone()).collect();
+    let latches: Vec<(Lit, Lit, bool)> = aig
+        .registers()
+        .iter()
+        .flat_map(|reg| {
+            let init = |i: usize| reg.init.bit(i as u64);
+            (reg.bits.iter().zip(&reg.next).enumerate())
+                .map(move |(i, (bit, next))| (*bit, *next, init(i)))
+        })
+        .collect();
+    let outputs: Vec<Lit> = aig.outputs().iter().flat_map(|port| port.bits.clone()).collect();
+    let ands: Vec<(usize, Lit, Lit)> = (aig.nodes().iter().enumerate())
+        .filter_map(|(index, node)| match node {
+            Node::And(a, b) => Some((index, *a, *b)),
+            _ => None,
+        })
+        .collect();
+    let sources = inputs.iter().chain(latches.iter().map(|(bit, _, _)| bit)).map(|bit| bit.node());
+    for (var, index) in sources.chain(ands.iter().map(|(index, _, _)| *index)).enumerate() {
+        vars[index] = var as u32 + 1;
+    }
+    let lit = |lit: Lit| 2 * vars[lit.node()] + lit.is_negated() as u32;
+
+    writeln!(
+        writer,
+        "{} {} {} {} {} {}",
+        if binary { "aig" } else { "aag" },
+        inputs.len() + latches.len() + ands.len(),
+        inputs.len(),
+        latches.len(),
+        outputs.len(),
+        ands.len()
+    )?;
+    if !binary {
+        for bit in &inputs {
+            writeln!(writer, "{}", lit(*bit))?;
+        }
+    }
+    for (bit, next, init) in &latches {
+        if !binary {
+            write!(writer, "{} ", lit(*bit))?;
+        }
+        match init {
+            true => writeln!(writer, "{} 1", lit(*next))?,
+            false => writeln!(writer, "{}", lit(*next))?,
+        }
+    }
+    for bit in &outputs {
+        writeln!(writer, "{}", lit(*bit))?;
+    }
+    for (index, a, b) in &ands {
+        let lhs = 2 * vars[*index];
+        let (rhs0, rhs1) = (lit(*a).max(lit(*b)), lit(*a).min(lit(*b)));
+        if binary {
+            write_delta(&mut writer, lhs - rhs0)?;
+            write_delta(&mut writer, rhs0 - rhs1)?;
+        } else {
+            writeln!(writer, "{} {} {}", lhs, rhs0, rhs1)?;
+        }
+    }
+
+    let inputs = aig.inputs().iter().map(|port| ('i', &port.name, port.bits.len()));
+    let latches = aig.registers().iter().map(|reg| ('l', &reg.name, reg.bits.len()));
+    let outputs = aig.outputs().iter().map(|port| ('o', &port.name, port.bits.len()));
+    let mut counts = [0; 3];
+    for (kind, name, width) in inputs.chain(latches).chain(outputs) {
+        let count = &mut counts["ilo".find(kind).unwrap()];
+        for i in 0..width {
+            writeln!(writer, "{}{} {}", kind, count, bit_name(name, width, i))?;
+            *count += 1;
+        }
+    }
+    Ok(())
+}
+
+/// Write `aig` in the ASCII AIGER format.
+pub fn write_ascii(aig: &Aig, writer: impl Write) -> Result<(), Error> {
+    write(aig, writer, false)
+}
+
+/// Write `aig` in the binary AIGER format.
+pub fn write_binary(aig: &Aig, writer: impl Write) -> Result<(), Error> {
+    write(aig, writer, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num::BigUint;
+
+    #[test]
+    fn ascii_and_binary() -> Result<(), Error> {
+        let mut aig = Aig::new();
+        let (a, b) = (aig.add_input("a", 1)[0], aig.add_input("b", 1)[0]);
+        let q = aig.add_register("q", 1, BigUint::from(1u32));
+        let state = aig.registers()[q].bits[0];
+        let x = aig.and(a, b);
+        aig.add_output("x", vec![!x]);
+        let next = aig.and(a, !state);
+        aig.set_register_next(q, vec![next]);
+
+        let mut text = vec![];
+        write_ascii(&aig, &mut text)?;
+        assert_eq!(
+            String::from_utf8(text).unwrap(),
+            "aag 5 2 1 1 2\n2\n4\n6 10 1\n9\n8 4 2\n10 7 2\ni0 a\ni1 b\nl0 q\no0 x\n"
+        );
+
+        let mut bytes = vec![];
+        write_binary(&aig, &mut bytes)?;
+        let mut expected = b"aig 5 2 1 1 2\n10 1\n9\n".to_vec();
+        expected.extend([4, 2, 3, 5]);
+        expected.extend(b"i0 a\ni1 b\nl0 q\no0 x\n");
+        assert_eq!(bytes, expected);
+
+        let mut bytes = vec![];
+        write_delta(&mut bytes, 300)?;
+        assert_eq!(bytes, [0xac, 0x02]);
+        Ok(())
+    }
+}
@@ -0,0 +1,172 @@
+// Copyright (c) 2022-2023 Kamyar Mohajerani
+
+//! BLIF netlists of an `Aig`, e.g. for ABC and other logic synthesis tools. Each AND gate is a
+//!  two-input `.names` cover with the polarities of its inputs, and each register bit a `.latch`
+//!  from a net suffixed with `$next`.
+//! Ports keep their names. The nets of AND gates are named `$n` and their node index, and the nets
+//!  of registers and gates are suffixed with `$` and a number where they clash with other nets.
+
+use super::{bit_name, Aig, Lit, Node, Port};
+use crate::crate_prelude::*;
+use std::collections::HashSet;
+use std::io::Write;
+
+/// `name`, suffixed with `$` and a number if needed to differ from the names in `used`, to which
+///  it is added.
+fn unique_name(used: &mut HashSet<String>, name: String) -> String {
+    let mut unique = name.clone();
+    let mut suffix = 0;
+    while !used.insert(unique.clone()) {
+        unique = format!("{}${}", name, suffix);
+        suffix += 1;
+    }
+    unique
+}
+
+/// Drive the net `name` from `lit`.
+fn write_buffer(
+    writer: &mut impl Write,
+    nets: &[String],
+    lit: Lit,
+    name: &str,
+) -> Result<(), Error> {
+    match lit {
+        Lit::FALSE => writeln!(writer, ".names {}", name)?,
+        Lit::TRUE => writeln!(writer, ".names {}\n1", name)?,
+        _ => {
+            writeln!(writer, ".names {} {}\n{} 1", nets[lit.node()], name, !lit.is_negated() as u8)?
+        }
+    }
+    Ok(())
+}
+
+/// Write `aig` as the BLIF model `model`. Fails if two port bits have the same name, e.g. an input
+///  and an output.
+pub fn write(aig: &Aig, mut writer: impl Write, model: &str) -> Result<(), Error> {
+    let names = |ports: &[Port]| -> Vec<String> {
+        (ports.iter())
+            .flat_map(|port| (0..port.bits.len()).map(|i| bit_name(&port.name, port.bits.len(), i)))
+            .collect()
+    };
+    let (inputs, outputs) = (names(aig.inputs()), names(aig.outputs()));
+    let mut used = HashSet::new();
+    for name in inputs.iter().chain(&outputs) {
+        if !used.insert(name.clone()) {
+            return Err(Error::simple(format!(
+                "Duplicate port `{}` in BLIF model `{}`",
+                name, model
+            )));
+        }
+    }
+    let mut nets: Vec<String> =
+        (0..aig.nodes().len()).map(|index| format!("$n{}", index)).collect();
+    let input_bits = aig.inputs().iter().flat_map(|port| &port.bits);
+    for (bit, name) in input_bits.zip(&inputs) {
+        nets[bit.node()] = name.clone();
+    }
+    // The state and next state nets of each register bit.
+    let mut latches: Vec<Vec<(String, String)>> = vec![];
+    for reg in aig.registers() {
+        let mut bits = vec![];
+        for (i, bit) in reg.bits.iter().enumerate() {
+            let name = bit_name(&reg.name, reg.bits.len(), i);
+            nets[bit.node()] = unique_name(&mut used, name.clone());
+            bits.push((nets[bit.node()].clone(), unique_name(&mut used, format!("{}$next", name))));
+        }
+        latches.push(bits);
+    }
+    for (index, node) in aig.nodes().iter().enumerate() {
+        if let Node::And(..) = node {
+            nets[index] = unique_name(&mut used, format!("$n{}", index));
+        }
+    }
+
+    writeln!(writer, ".model {}", model)?;
+    writeln!(writer, ".inputs {}", inputs.join(" "))?;
+    writeln!(writer, ".outputs {}", outputs.join(" "))?;
+    for (reg, bits) in aig.registers().iter().zip(&latches) {
+        for (i, (state, next)) in bits.iter().enumerate() {
+            writeln!(writer, ".latch {} {} {}", next, state, reg.init.bit(i as u64) as u8)?;
+        }
+    }
+    for (index, node) in aig.nodes().iter().enumerate() {
+        if let Node::And(a, b) = node {
+            writeln!(
+                writer,
+                ".names {} {} {}\n{}{} 1",
+                nets[a.node()],
+                nets[b.node()],
+                nets[index],
+                !a.is_negated() as u8,
+                !b.is_negated() as u8
+            )?;
+        }
+    }
+    let output_bits = aig.outputs().iter().flat_map(|port| &port.bits);
+    for (bit, name) in output_bits.zip(&outputs) {
+        write_buffer(&mut writer, &nets, *bit, name)?;
+    }
+    for (reg, bits) in aig.registers().iter().zip(&latches) {
+        for (bit, (_, next)) in reg.next.iter().zip(bits) {
+            write_buffer(&mut writer, &nets, *bit, next)?;
+        }
+    }
+    writeln!(writer, ".end")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num::BigUint;
+
+    #[test]
+    fn latch_and_gates() -> Result<(), Error> {
+        let mut aig = Aig::new();
+        let (a, b) = (aig.add_input("a", 1)[0], aig.add_input("b", 1)[0]);
+        let q = aig.add_register("q", 1, BigUint::from(1u32));
+        let state = aig.registers()[q].bits[0];
+        let x = aig.and(a, b);
+        aig.add_output("x", vec![!x, Lit::TRUE]);
+        let next = aig.and(a, !state);
+        aig.set_register_next(q, vec![next]);
+
+        let mut text = vec![];
+        write(&aig, &mut text, "m")?;
+        assert_eq!(
+            String::from_utf8(text).unwrap(),
+            ".model m\n.inputs a b\n.outputs x[0] x[1]\n.latch q$next q 1\n\
+             .names a b $n4\n11 1\n.names a q $n5\n10 1\n\
+             .names $n4 x[0]\n0 1\n.names x[1]\n1\n.names $n5 q$next\n1 1\n.end\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn net_name_collisions() -> Result<(), Error> {
+        // The input `n4` and the AND gate of node 4, and the register `y` and the output `y`.
+        let mut aig = Aig::new();
+        let (a, b) = (aig.add_input("n4", 1)[0], aig.add_input("b", 1)[0]);
+        let q = aig.add_register("y", 1, BigUint::from(0u32));
+        let state = aig.registers()[q].bits[0];
+        let y = aig.and(a, b);
+        aig.add_output("y", vec![y]);
+        let next = aig.and(state, b);
+        aig.set_register_next(q, vec![next]);
+
+        let mut text = vec![];
+        write(&aig, &mut text, "m")?;
+        assert_eq!(
+            String::from_utf8(text).unwrap(),
+            ".model m\n.inputs n4 b\n.outputs y\n.latch y$next y$0 0\n\
+             .names n4 b $n4\n11 1\n.names b y$0 $n5\n11 1\n\
+             .names $n4 y\n1 1\n.names $n5 y$next\n1 1\n.end\n"
+        );
+
+        let mut aig = Aig::new();
+        let a = aig.add_input("a", 1)[0];
+        aig.add_output("a", vec![!a]);
+        assert!(write(&aig, &mut vec![], "m").is_err());
+        Ok(())
+    }
+}
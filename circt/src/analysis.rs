@@ -0,0 +1,66 @@
+// Copyright (c) 2022-2023 Kamyar Mohajerani
+
+//! Analyses of HW designs which give quick feedback on generated hardware without running
+//!  synthesis. `stats` reports the size of modules and `timing` their critical paths, with areas
+//!  and delays from a `cost::CostModel`. `loops` finds combinational loops, and `graph` builds
+//!  petgraph graphs of dataflow and hierarchy.
+
+use crate::crate_prelude::*;
+use circt_sys::hwGetBitWidth;
+
+pub mod cost;
+pub mod graph;
+pub mod loops;
+pub mod stats;
+pub mod timing;
+
+/// The `seq` register operations.
+pub(crate) const REGISTER_OPS: &[&str] = &["seq.compreg", "seq.compreg.ce", "seq.firreg"];
+
+/// Hardware bit width of a type, or `None` if it is not statically known.
+pub(crate) fn bit_width(ty: &Type) -> Option<usize> {
+    unsafe { hwGetBitWidth(ty.raw()) }.try_into().ok()
+}
+
+/// Width of an integer-typed value, failing for other types.
+pub(crate) fn int_width(value: &Value) -> Result<u32, Error> {
+    IntegerType::try_from(value.ty())
+        .map(|ty| ty.width())
+        .map_err(|_| Error::simple(format!("Unsupported type {}", value.ty())))
+}
+
+/// The string attribute `attr_name` of `op`, e.g. the `name` of a register.
+pub(crate) fn string_attr(op: &Operation, attr_name: &str) -> Option<String> {
+    op.attribute(attr_name)
+        .and_then(|attr| StringAttr::try_from(attr).ok())
+        .map(|attr| attr.get_value())
+}
+
+/// The strings of the array attribute `attr_name` of `op`, e.g. the `resultNames` of an instance.
+pub(crate) fn string_array(op: &Operation, attr_name: &str) -> Vec<String> {
+    op.attribute(attr_name)
+        .and_then(|attr| ArrayAttr::try_from(attr).ok())
+        .map(|names| {
+            (names.elements())
+                .filter_map(|name| StringAttr::try_from(name).ok())
+                .map(|name| name.get_value())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Describe `loc` as `filename:line:col` when it is a file location.
+pub(crate) fn source_string(loc: &Location) -> String {
+    match loc.file_line_col() {
+        Some((filename, line, col)) => format!("{}:{}:{}", filename, line, col),
+        None => loc.to_string(),
+    }
+}
+
+/// The width an operation is costed at: the widest of its operands and results.
+pub(crate) fn op_width(op: &Operation) -> usize {
+    (op.operands().into_iter().chain(op.results()))
+        .filter_map(|value| bit_width(&value.ty()))
+        .max()
+        .unwrap_or(0)
+}
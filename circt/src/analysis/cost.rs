@@ -0,0 +1,145 @@
+// Copyright (c) 2022-2023 Kamyar Mohajerani
+
+//! Area and delay of operations as functions of their bit width, looked up by operation name.
+//! The default model is a rough gate-level one, in gate equivalents and gate delays, with
+//!  ripple dividers, log-depth adders and comparators, and array multipliers.
+
+use crate::crate_prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A cost of `constant + linear * w + log * log2(w) + linear_log * w * log2(w) + quadratic * w^2`
+///  for bit width `w`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Cost {
+    pub constant: f64,
+    pub linear: f64,
+    pub log: f64,
+    pub linear_log: f64,
+    pub quadratic: f64,
+}
+
+impl Cost {
+    pub const ZERO: Self = Self::constant(0.0);
+
+    pub const fn constant(constant: f64) -> Self {
+        Self {
+            constant,
+            linear: 0.0,
+            log: 0.0,
+            linear_log: 0.0,
+            quadratic: 0.0,
+        }
+    }
+
+    pub const fn linear(linear: f64) -> Self {
+        Self {
+            linear,
+            ..Self::ZERO
+        }
+    }
+
+    pub fn eval(&self, width: usize) -> f64 {
+        let w = width as f64;
+        let log = if width > 1 { w.log2() } else { 0.0 };
+        self.constant
+            + self.linear * w
+            + self.log * log
+            + self.linear_log * w * log
+            + self.quadratic * w * w
+    }
+}
+
+/// Per-operation area and delay tables, with defaults for the operations they do not list.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CostModel {
+    pub area: BTreeMap<String, Cost>,
+    pub delay: BTreeMap<String, Cost>,
+    pub default_area: Cost,
+    pub default_delay: Cost,
+}
+
+/// Operations which only rewire bits and cost nothing.
+pub const WIRING_OPS: &[&str] = &["comb.concat", "comb.extract", "comb.replicate", "hw.wire"];
+
+impl CostModel {
+    /// A unit model: one unit of area per bit and one unit of delay per operation, except for
+    ///  the wiring operations, which are free.
+    pub fn unit() -> Self {
+        let free = || WIRING_OPS.iter().map(|name| (name.to_string(), Cost::ZERO));
+        Self {
+            area: free().collect(),
+            delay: free().collect(),
+            default_area: Cost::linear(1.0),
+            default_delay: Cost::constant(1.0),
+        }
+    }
+
+    pub fn area(&self, op_name: &str, width: usize) -> f64 {
+        self.area.get(op_name).unwrap_or(&self.default_area).eval(width)
+    }
+
+    pub fn delay(&self, op_name: &str, width: usize) -> f64 {
+        self.delay.get(op_name).unwrap_or(&self.default_delay).eval(width)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        serde_json::from_str(json)
+            .map_err(|e| Error::simple(format!("Failed to parse cost model: {}", e)))
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        let log_depth = |log: f64| Cost {
+            constant: 1.0,
+            log,
+            ..Cost::ZERO
+        };
+        let quadratic = |quadratic: f64| Cost {
+            quadratic,
+            ..Cost::ZERO
+        };
+        let shifter = Cost {
+            linear_log: 1.0,
+            ..Cost::ZERO
+        };
+        let mut area = BTreeMap::new();
+        let mut delay = BTreeMap::new();
+        let mut add = |names: &[&str], area_cost: Cost, delay_cost: Cost| {
+            for name in names {
+                area.insert(name.to_string(), area_cost);
+                delay.insert(name.to_string(), delay_cost);
+            }
+        };
+        add(WIRING_OPS, Cost::ZERO, Cost::ZERO);
+        add(&["comb.and", "comb.or", "comb.xor"], Cost::linear(1.0), Cost::constant(1.0));
+        add(&["comb.add", "comb.sub"], Cost::linear(5.0), log_depth(2.0));
+        add(&["comb.mul"], quadratic(6.0), log_depth(4.0));
+        let divider = Cost {
+            linear: 4.0,
+            ..Cost::ZERO
+        };
+        add(&["comb.divu", "comb.divs", "comb.modu", "comb.mods"], quadratic(8.0), divider);
+        add(&["comb.shl", "comb.shru", "comb.shrs"], shifter, log_depth(1.0));
+        add(&["comb.icmp"], Cost::linear(2.0), log_depth(1.0));
+        add(&["comb.mux"], Cost::linear(3.0), Cost::constant(2.0));
+        add(&["comb.parity"], Cost::linear(1.0), log_depth(1.0));
+        // Flip-flops, and memory cells per bit.
+        add(&["seq.compreg", "seq.firreg"], Cost::linear(6.0), Cost::constant(1.0));
+        add(&["seq.compreg.ce"], Cost::linear(9.0), Cost::constant(1.0));
+        add(&["seq.hlmem", "seq.firmem", "sv.reg"], Cost::linear(1.0), Cost::constant(2.0));
+        Self {
+            area,
+            delay,
+            default_area: Cost::linear(1.0),
+            default_delay: Cost::constant(1.0),
+        }
+    }
+}
@@ -0,0 +1,407 @@
+// Copyright (c) 2022-2023 Kamyar Mohajerani
+
+//! Graphs of designs for tooling: the dataflow of a module, with operations as nodes and the uses
+//!  of their results as edges, and the instance hierarchy. `design_dataflow` flattens instances
+//!  into clusters of nodes, which `to_dot` renders as DOT subgraphs and `to_graphml` as nested
+//!  GraphML graphs.
+
+use crate::crate_prelude::*;
+use hw::{HwModuleLike, HwModuleOp, InstanceGraph};
+use petgraph::graph::{Graph, NodeIndex};
+use petgraph::visit::EdgeRef;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+/// Rendering of graph nodes.
+pub trait GraphNode {
+    fn label(&self) -> String;
+
+    /// Path of instance names of the cluster the node belongs to, empty at the top level.
+    fn cluster(&self) -> &[String] {
+        &[]
+    }
+}
+
+/// Rendering of graph edges.
+pub trait GraphEdge {
+    fn label(&self) -> String;
+}
+
+#[derive(Debug, Clone)]
+pub enum DataflowKind {
+    Input(String),
+    Output(String),
+    Op(Operation),
+}
+
+#[derive(Debug, Clone)]
+pub struct DataflowNode {
+    pub kind: DataflowKind,
+    /// Instance names from the top module down to the module containing the node.
+    pub path: Vec<String>,
+}
+
+impl GraphNode for DataflowNode {
+    fn label(&self) -> String {
+        match &self.kind {
+            DataflowKind::Input(name) | DataflowKind::Output(name) => name.clone(),
+            DataflowKind::Op(op) => {
+                let name = ["instanceName", "name"]
+                    .iter()
+                    .find_map(|attr_name| op.attribute(attr_name))
+                    .and_then(|attr| StringAttr::try_from(attr).ok())
+                    .map(|attr| attr.get_value());
+                match name {
+                    Some(name) => format!("{} {}", op.name().to_string(), name),
+                    None => op.name().to_string(),
+                }
+            }
+        }
+    }
+
+    fn cluster(&self) -> &[String] {
+        &self.path
+    }
+}
+
+/// A use of a value: the edge from the node defining it to the node using it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DataflowEdge {
+    /// Index of the operand using the value, or of the output port.
+    pub operand: usize,
+    pub width: Option<usize>,
+}
+
+impl GraphEdge for DataflowEdge {
+    fn label(&self) -> String {
+        self.width.map(|width| width.to_string()).unwrap_or_default()
+    }
+}
+
+pub type DataflowGraph = Graph<DataflowNode, DataflowEdge>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HierarchyNode {
+    pub name: String,
+    pub is_extern: bool,
+}
+
+impl GraphNode for HierarchyNode {
+    fn label(&self) -> String {
+        self.name.clone()
+    }
+}
+
+impl GraphEdge for String {
+    fn label(&self) -> String {
+        self.clone()
+    }
+}
+
+/// Modules as nodes and instances, named by their edges, from parent to instantiated module.
+pub type HierarchyGraph = Graph<HierarchyNode, String>;
+
+struct DataflowBuilder<'a> {
+    graph: DataflowGraph,
+    /// The hierarchy to flatten instances of, or `None` to keep them as nodes.
+    hierarchy: Option<&'a InstanceGraph>,
+}
+
+impl<'a> DataflowBuilder<'a> {
+    /// Add the dataflow of `hw_module` at `path`, returning its input and output port nodes.
+    fn module(
+        &mut self,
+        hw_module: &HwModuleOp,
+        path: &[String],
+    ) -> Result<(Vec<NodeIndex>, Vec<NodeIndex>), Error> {
+        let body = hw_module.first_block().ok_or(Error::IsNone)?;
+        let ports = hw_module.port_info();
+        let add_node = |graph: &mut DataflowGraph, kind| {
+            graph.add_node(DataflowNode {
+                kind,
+                path: path.to_vec(),
+            })
+        };
+        let mut sources = HashMap::new();
+        let mut inputs = vec![];
+        for (port, arg) in ports.inputs.iter().zip(body.arguments()) {
+            let node = add_node(&mut self.graph, DataflowKind::Input(port.name.clone()));
+            sources.insert(arg, node);
+            inputs.push(node);
+        }
+        // Nodes with their operands, which are connected once every value has a source.
+        let mut users: Vec<(NodeIndex, Vec<Value>)> = vec![];
+        let mut outputs = vec![];
+        for op in body.operations() {
+            if op.name().to_string() == "hw.output" {
+                for (port, value) in ports.outputs.iter().zip(op.operands()) {
+                    let node = add_node(&mut self.graph, DataflowKind::Output(port.name.clone()));
+                    users.push((node, vec![value]));
+                    outputs.push(node);
+                }
+                continue;
+            }
+            let target = match (op.try_into_op::<hw::InstanceOp>(), self.hierarchy) {
+                (Some(inst), Some(hierarchy)) => (hierarchy.lookup(&inst.module_name()))
+                    .and_then(|id| hierarchy.node(id).op.try_into_op::<HwModuleOp>())
+                    .map(|target| (inst, target)),
+                _ => None,
+            };
+            match target {
+                Some((inst, target)) => {
+                    let mut inst_path = path.to_vec();
+                    inst_path.push(inst.instance_name());
+                    let (inst_inputs, inst_outputs) = self.module(&target, &inst_path)?;
+                    for (node, operand) in inst_inputs.into_iter().zip(op.operands()) {
+                        users.push((node, vec![operand]));
+                    }
+                    sources.extend(op.results().into_iter().zip(inst_outputs));
+                }
+                None => {
+                    let node = add_node(&mut self.graph, DataflowKind::Op(op));
+                    sources.extend(op.results().into_iter().map(|result| (result, node)));
+                    users.push((node, op.operands()));
+                }
+            }
+        }
+        for (node, operands) in users {
+            for (operand, value) in operands.into_iter().enumerate() {
+                if let Some(&source) = sources.get(&value) {
+                    let width = super::bit_width(&value.ty());
+                    self.graph.add_edge(source, node, DataflowEdge { operand, width });
+                }
+            }
+        }
+        Ok((inputs, outputs))
+    }
+}
+
+/// The dataflow of `hw_module`, with its instances as nodes.
+pub fn module_dataflow(hw_module: &HwModuleOp) -> Result<DataflowGraph, Error> {
+    let mut builder = DataflowBuilder {
+        graph: Graph::new(),
+        hierarchy: None,
+    };
+    builder.module(hw_module, &[])?;
+    Ok(builder.graph)
+}
+
+/// The dataflow of the module named `top` in `module` with the `hw.module`s it instantiates
+///  flattened into it. The nodes of each instance, including its ports, are in its cluster.
+pub fn design_dataflow(module: &Module, top: &str) -> Result<DataflowGraph, Error> {
+    let hierarchy = InstanceGraph::new(module)?;
+    // Flattening recurses through the hierarchy, which must be acyclic.
+    hierarchy.post_order()?;
+    let id = (hierarchy.lookup(top))
+        .ok_or_else(|| Error::simple(format!("Unknown module `{}`", top)))?;
+    let top_module = (hierarchy.node(id).op.try_into_op::<HwModuleOp>())
+        .ok_or_else(|| Error::simple(format!("`{}` is not an `hw.module`", top)))?;
+    let mut builder = DataflowBuilder {
+        graph: Graph::new(),
+        hierarchy: Some(&hierarchy),
+    };
+    builder.module(&top_module, &[])?;
+    Ok(builder.graph)
+}
+
+/// The instance hierarchy of the modules at the top level of `module`.
+pub fn hierarchy_graph(module: &Module) -> Result<HierarchyGraph, Error> {
+    let hierarchy = InstanceGraph::new(module)?;
+    let mut graph = Graph::new();
+    let nodes: Vec<NodeIndex> = (hierarchy.nodes().iter())
+        .map(|node| {
+            graph.add_node(HierarchyNode {
+                name: node.name.clone(),
+                is_extern: node.is_extern(),
+            })
+        })
+        .collect();
+    for record in hierarchy.records() {
+        graph.add_edge(nodes[record.parent], nodes[record.target], record.name.clone());
+    }
+    Ok(graph)
+}
+
+/// Nodes of a graph grouped by cluster.
+#[derive(Default)]
+struct Cluster {
+    nodes: Vec<NodeIndex>,
+    children: BTreeMap<String, Cluster>,
+}
+
+impl Cluster {
+    fn new<N: GraphNode, E>(graph: &Graph<N, E>) -> Self {
+        let mut root = Cluster::default();
+        for node in graph.node_indices() {
+            let cluster = (graph[node].cluster().iter())
+                .fold(&mut root, |cluster, name| cluster.children.entry(name.clone()).or_default());
+            cluster.nodes.push(node);
+        }
+        root
+    }
+}
+
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Render `graph` in DOT, with a subgraph for each cluster.
+pub fn to_dot<N: GraphNode, E: GraphEdge>(graph: &Graph<N, E>) -> String {
+    fn write_cluster<N: GraphNode, E>(
+        out: &mut String,
+        graph: &Graph<N, E>,
+        cluster: &Cluster,
+        path: &mut Vec<String>,
+    ) {
+        let indent = "  ".repeat(path.len() + 1);
+        for &node in &cluster.nodes {
+            let label = dot_escape(&graph[node].label());
+            writeln!(out, "{}n{} [label=\"{}\"];", indent, node.index(), label).unwrap();
+        }
+        for (name, child) in &cluster.children {
+            path.push(name.clone());
+            let id = dot_escape(&path.join("/"));
+            writeln!(out, "{}subgraph \"cluster_{}\" {{", indent, id).unwrap();
+            writeln!(out, "{}  label=\"{}\";", indent, dot_escape(name)).unwrap();
+            write_cluster(out, graph, child, path);
+            writeln!(out, "{}}}", indent).unwrap();
+            path.pop();
+        }
+    }
+    let mut out = String::from("digraph {\n");
+    write_cluster(&mut out, graph, &Cluster::new(graph), &mut vec![]);
+    for edge in graph.edge_references() {
+        let (source, target) = (edge.source().index(), edge.target().index());
+        let label = dot_escape(&edge.weight().label());
+        writeln!(out, "  n{} -> n{} [label=\"{}\"];", source, target, label).unwrap();
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// Render `graph` in GraphML, with each cluster as a node containing a nested graph.
+pub fn to_graphml<N: GraphNode, E: GraphEdge>(graph: &Graph<N, E>) -> String {
+    fn write_cluster<N: GraphNode, E>(
+        out: &mut String,
+        graph: &Graph<N, E>,
+        cluster: &Cluster,
+        path: &mut Vec<String>,
+    ) {
+        let indent = "  ".repeat(2 * path.len() + 2);
+        for &node in &cluster.nodes {
+            let label = xml_escape(&graph[node].label());
+            writeln!(
+                out,
+                "{}<node id=\"n{}\"><data key=\"label\">{}</data></node>",
+                indent,
+                node.index(),
+                label
+            )
+            .unwrap();
+        }
+        for (name, child) in &cluster.children {
+            path.push(name.clone());
+            let id = xml_escape(&path.join("/"));
+            writeln!(out, "{}<node id=\"cluster:{}\">", indent, id).unwrap();
+            writeln!(out, "{}  <data key=\"label\">{}</data>", indent, xml_escape(name)).unwrap();
+            writeln!(out, "{}  <graph id=\"cluster:{}:\" edgedefault=\"directed\">", indent, id)
+                .unwrap();
+            write_cluster(out, graph, child, path);
+            writeln!(out, "{}  </graph>", indent).unwrap();
+            writeln!(out, "{}</node>", indent).unwrap();
+            path.pop();
+        }
+    }
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+    out.push_str("  <key id=\"label\" for=\"all\" attr.name=\"label\" attr.type=\"string\"/>\n");
+    out.push_str("  <graph id=\"G\" edgedefault=\"directed\">\n");
+    write_cluster(&mut out, graph, &Cluster::new(graph), &mut vec![]);
+    for edge in graph.edge_references() {
+        let (source, target) = (edge.source().index(), edge.target().index());
+        let label = xml_escape(&edge.weight().label());
+        writeln!(
+            out,
+            "    <edge source=\"n{}\" target=\"n{}\"><data key=\"label\">{}</data></edge>",
+            source, target, label
+        )
+        .unwrap();
+    }
+    out.push_str("  </graph>\n</graphml>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hw::{InstanceOp, ModulePortInfo};
+
+    #[test]
+    fn dataflow_and_hierarchy() -> miette::Result<()> {
+        let ctx = OwnedContext::default();
+        hw::dialect().load(&ctx).unwrap();
+        comb::dialect().load(&ctx).unwrap();
+
+        let mut builder = OpBuilder::new(&ctx);
+        let module = Module::create(builder.loc());
+        let i8 = IntegerType::new(&ctx, 8);
+        let mut ports = ModulePortInfo::default();
+        ports.add_input("a", &i8);
+        ports.add_output("y", &i8);
+        let leaf = HwModuleOp::build_with(
+            &mut builder,
+            &module,
+            "leaf",
+            &ports,
+            &[],
+            "",
+            |builder, _, inputs, outputs| {
+                let one = hw::ConstantOp::build(builder, 8, 1).result();
+                let sum = comb::AddOp::build(builder, &inputs["a"], &one).unwrap();
+                outputs.insert("y".to_string(), sum.result());
+            },
+        )?;
+        let top = HwModuleOp::build_with(
+            &mut builder,
+            &module,
+            "top",
+            &ports,
+            &[],
+            "",
+            |builder, _, inputs, outputs| {
+                let u0 = InstanceOp::build(builder, "u0", &leaf, [inputs["a"]], &[]).unwrap();
+                let u1 = InstanceOp::build(builder, "u1", &leaf, u0.results(), &[]).unwrap();
+                outputs.insert("y".to_string(), u1.result_at(0).unwrap());
+            },
+        )?;
+
+        let dataflow = module_dataflow(&top)?;
+        let labels: Vec<String> = dataflow.node_weights().map(|node| node.label()).collect();
+        assert_eq!(labels, ["a", "hw.instance u0", "hw.instance u1", "y"]);
+        assert_eq!(dataflow.edge_count(), 3);
+
+        let flat = design_dataflow(&module, "top")?;
+        assert_eq!((flat.node_count(), flat.edge_count()), (10, 9));
+        let adds: Vec<&[String]> = (flat.node_weights())
+            .filter(|node| node.label() == "comb.add")
+            .map(|node| node.cluster())
+            .collect();
+        assert_eq!(adds, [["u0".to_string()], ["u1".to_string()]]);
+        let dot = to_dot(&flat);
+        assert!(dot.contains("  subgraph \"cluster_u1\" {\n    label=\"u1\";\n"));
+        assert!(dot.contains("[label=\"8\"]"));
+        let graphml = to_graphml(&flat);
+        assert!(graphml.contains("<graph id=\"cluster:u0:\" edgedefault=\"directed\">"));
+        assert_eq!(graphml.matches("<edge ").count(), 9);
+
+        let hierarchy = hierarchy_graph(&module)?;
+        let edges: Vec<String> = hierarchy.edge_weights().cloned().collect();
+        assert_eq!(edges, ["u0", "u1"]);
+        assert!(to_dot(&hierarchy).contains("n1 -> n0 [label=\"u0\"];"));
+        Ok(())
+    }
+}
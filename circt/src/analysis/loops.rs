@@ -0,0 +1,295 @@
+// Copyright (c) 2022-2023 Kamyar Mohajerani
+
+//! Detection of combinational loops: strongly connected components of the operations in the body
+//!  of an `hw.module`, through values and `sv.assign`s to wires but not through `seq` registers.
+//! Instances are opaque, so loops through other modules are not found.
+
+use super::{source_string, REGISTER_OPS};
+use crate::crate_prelude::*;
+use hw::{HwModuleLike, HwModuleOp};
+use itertools::Itertools;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fmt;
+
+#[derive(Debug, Clone)]
+pub struct CombLoop {
+    pub module: String,
+    /// The operations of the loop, in the order of the module body.
+    pub ops: Vec<Operation>,
+}
+
+impl CombLoop {
+    /// Emit the loop as an error diagnostic at the location of its first operation.
+    pub fn emit(&self) {
+        if let Some(op) = self.ops.first() {
+            op.loc().emit_error(&self.to_string());
+        }
+    }
+}
+
+impl fmt::Display for CombLoop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Combinational loop in `{}` through {} operations:",
+            self.module,
+            self.ops.len()
+        )?;
+        for op in &self.ops {
+            write!(f, "\n  {} at {}", op.name().to_string(), source_string(&op.loc()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Strongly connected components of the graph with successors `succ`, by Tarjan's algorithm.
+fn strongly_connected(succ: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    const UNVISITED: usize = usize::MAX;
+    let n = succ.len();
+    let (mut index, mut low, mut on_stack) = (vec![UNVISITED; n], vec![0; n], vec![false; n]);
+    let (mut stack, mut components, mut next) = (vec![], vec![], 0);
+    for root in 0..n {
+        if index[root] != UNVISITED {
+            continue;
+        }
+        // Nodes being visited, with the position of the next successor to visit.
+        let mut work = vec![(root, 0)];
+        index[root] = next;
+        low[root] = next;
+        next += 1;
+        stack.push(root);
+        on_stack[root] = true;
+        while let Some(&(v, i)) = work.last() {
+            if let Some(&w) = succ[v].get(i) {
+                work.last_mut().unwrap().1 += 1;
+                if index[w] == UNVISITED {
+                    index[w] = next;
+                    low[w] = next;
+                    next += 1;
+                    stack.push(w);
+                    on_stack[w] = true;
+                    work.push((w, 0));
+                } else if on_stack[w] {
+                    low[v] = low[v].min(index[w]);
+                }
+                continue;
+            }
+            work.pop();
+            if let Some(&(u, _)) = work.last() {
+                low[u] = low[u].min(low[v]);
+            }
+            if low[v] == index[v] {
+                let mut component = vec![];
+                while let Some(w) = stack.pop() {
+                    on_stack[w] = false;
+                    component.push(w);
+                    if w == v {
+                        break;
+                    }
+                }
+                components.push(component);
+            }
+        }
+    }
+    components
+}
+
+/// Order the nodes of the graph with predecessors `preds` so that each comes after its
+///  predecessors, by Kahn's algorithm. Of the ready nodes, the first is taken, so nodes already in
+///  topological order keep their order. Fails with the nodes on or after a cycle.
+pub(crate) fn topological_order(preds: &[Vec<usize>]) -> Result<Vec<usize>, Vec<usize>> {
+    let mut users = vec![vec![]; preds.len()];
+    let mut pending: Vec<usize> = preds.iter().map(Vec::len).collect();
+    for (i, node_preds) in preds.iter().enumerate() {
+        for &j in node_preds {
+            users[j].push(i);
+        }
+    }
+    let mut ready: BinaryHeap<Reverse<usize>> =
+        (0..preds.len()).filter(|&i| pending[i] == 0).map(Reverse).collect();
+    let mut order = Vec::with_capacity(preds.len());
+    while let Some(Reverse(i)) = ready.pop() {
+        order.push(i);
+        for &j in &users[i] {
+            pending[j] -= 1;
+            if pending[j] == 0 {
+                ready.push(Reverse(j));
+            }
+        }
+    }
+    match order.len() == preds.len() {
+        true => Ok(order),
+        false => Err((0..preds.len()).filter(|&i| pending[i] > 0).collect()),
+    }
+}
+
+/// Order `ops` so that each comes after the operations in `ops` defining its operands. Fails with
+///  an operation on or after a loop.
+pub(crate) fn operand_order(ops: &[Operation]) -> Result<Vec<Operation>, Operation> {
+    let index: HashMap<Value, usize> = (ops.iter().enumerate())
+        .flat_map(|(i, op)| op.results().into_iter().map(move |result| (result, i)))
+        .collect();
+    let preds: Vec<Vec<usize>> = (ops.iter())
+        .map(|op| op.operands().iter().filter_map(|operand| index.get(operand).copied()).collect())
+        .collect();
+    match topological_order(&preds) {
+        Ok(order) => Ok(order.into_iter().map(|i| ops[i]).collect()),
+        Err(blocked) => Err(ops[blocked[0]]),
+    }
+}
+
+/// The combinational loops in the body of `hw_module`.
+pub fn module_comb_loops(hw_module: &HwModuleOp) -> Result<Vec<CombLoop>, Error> {
+    let body = hw_module.first_block().ok_or(Error::IsNone)?;
+    let ops: Vec<Operation> = (body.operations())
+        .filter(|op| {
+            let name = op.name().to_string();
+            !REGISTER_OPS.contains(&name.as_str()) && name != "hw.instance" && name != "hw.output"
+        })
+        .collect();
+    let index: HashMap<Value, usize> = (ops.iter().enumerate())
+        .flat_map(|(i, op)| op.results().into_iter().map(move |result| (result, i)))
+        .collect();
+    let mut succ = vec![vec![]; ops.len()];
+    for (i, op) in ops.iter().enumerate() {
+        let operands = op.operands();
+        if op.name().to_string() == "sv.assign" {
+            // The assigned value flows into the wire, operand 0.
+            let wire = operands.first().and_then(|value| index.get(value));
+            if let (Some(&wire), Some(&src)) = (wire, operands.get(1).and_then(|v| index.get(v))) {
+                succ[src].push(wire);
+            }
+            continue;
+        }
+        for operand in operands {
+            if let Some(&j) = index.get(&operand) {
+                succ[j].push(i);
+            }
+        }
+    }
+    let mut components: Vec<Vec<usize>> = strongly_connected(&succ)
+        .into_iter()
+        .filter(|component| component.len() > 1 || succ[component[0]].contains(&component[0]))
+        .collect();
+    for component in &mut components {
+        component.sort_unstable();
+    }
+    components.sort_unstable();
+    let loops = (components.into_iter())
+        .map(|component| CombLoop {
+            module: hw_module.module_name(),
+            ops: component.into_iter().map(|i| ops[i]).collect(),
+        })
+        .collect();
+    Ok(loops)
+}
+
+/// The combinational loops of every `hw.module` at the top level of `module`.
+pub fn design_comb_loops(module: &Module) -> Result<Vec<CombLoop>, Error> {
+    let mut loops = vec![];
+    for op in module.body().operations() {
+        if let Some(hw_module) = op.try_into_op::<HwModuleOp>() {
+            loops.extend(module_comb_loops(&hw_module)?);
+        }
+    }
+    Ok(loops)
+}
+
+/// Emit a diagnostic for every combinational loop in `module`, and fail if there are any.
+pub fn check_comb_loops(module: &Module) -> Result<(), Error> {
+    let loops = design_comb_loops(module)?;
+    for comb_loop in &loops {
+        comb_loop.emit();
+    }
+    match loops.is_empty() {
+        true => Ok(()),
+        false => Err(Error::simple(loops.iter().map(|comb_loop| comb_loop.to_string()).join("\n"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hw::ModulePortInfo;
+    use std::{cell::RefCell, rc::Rc};
+
+    struct Collect(Rc<RefCell<Vec<Location>>>);
+
+    impl HandlerObject for Collect {
+        fn handle(&mut self, diag: Diagnostic) -> LogicalResult {
+            self.0.borrow_mut().push(diag.location());
+            true.into()
+        }
+    }
+
+    #[test]
+    fn topological_orders() {
+        // 0 <- 2 <- 1, with 3 independent.
+        let preds = vec![vec![], vec![2], vec![0], vec![]];
+        assert_eq!(topological_order(&preds), Ok(vec![0, 2, 1, 3]));
+        // 1 and 2 form a cycle, which 3 is after.
+        let preds = vec![vec![], vec![2], vec![1], vec![1, 0]];
+        assert_eq!(topological_order(&preds), Err(vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn comb_loops() -> miette::Result<()> {
+        let ctx = OwnedContext::default();
+        hw::dialect().load(&ctx).unwrap();
+        comb::dialect().load(&ctx).unwrap();
+        seq::dialect().load(&ctx).unwrap();
+        let diagnostics = Rc::new(RefCell::new(vec![]));
+        ctx.attach_diagnostic_handler(Box::new(Collect(diagnostics.clone())));
+
+        let mut builder = OpBuilder::new(&ctx);
+        let module = Module::create(builder.loc());
+        let i1 = IntegerType::new(&ctx, 1);
+        let i8 = IntegerType::new(&ctx, 8);
+        let mut ports = ModulePortInfo::default();
+        ports.add_input("clk", &i1);
+        ports.add_input("a", &i8);
+        ports.add_input("b", &i8);
+        ports.add_output("y", &i8);
+        let loc = Location::new(&ctx, "gen.py", 5, 9);
+        HwModuleOp::build_with(
+            &mut builder,
+            &module,
+            "feedback",
+            &ports,
+            &[],
+            "",
+            |builder, _, inputs, outputs| {
+                let (a, b) = (inputs["a"], inputs["b"]);
+                let default_loc = *builder.loc();
+                builder.set_loc(loc);
+                let sum = comb::AddOp::build(builder, &a, &b).unwrap();
+                builder.set_loc(default_loc);
+                let diff = comb::XorOp::build(builder, [sum.result(), b]).unwrap();
+                sum.set_operand(1, &diff.result());
+                let and = comb::AndOp::build(builder, [a, a]).unwrap();
+                and.set_operand(1, &and.result());
+                // Feedback through a register is not a combinational loop.
+                let next = comb::AddOp::build(builder, &a, &and.result()).unwrap();
+                let reg =
+                    seq::CompRegOp::build(builder, "r", &next.result(), &inputs["clk"], None, None)
+                        .unwrap();
+                next.set_operand(0, &reg.output());
+                outputs.insert("y".to_string(), reg.output());
+            },
+        )?;
+
+        let loops = design_comb_loops(&module)?;
+        let names: Vec<Vec<String>> = (loops.iter())
+            .map(|comb_loop| comb_loop.ops.iter().map(|op| op.name().to_string()).collect())
+            .collect();
+        assert_eq!(names, [vec!["comb.add", "comb.xor"], vec!["comb.and"]]);
+        assert_eq!(loops[0].ops[0].loc().file_line_col(), Some(("gen.py".to_string(), 5, 9)));
+        assert!(loops[0].to_string().contains("comb.add at gen.py:5:9"));
+
+        assert!(check_comb_loops(&module).is_err());
+        assert_eq!(diagnostics.borrow().len(), 2);
+        assert!(diagnostics.borrow()[0] == loc);
+        Ok(())
+    }
+}
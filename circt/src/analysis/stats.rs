@@ -0,0 +1,334 @@
+// Copyright (c) 2022-2023 Kamyar Mohajerani
+
+//! Size statistics of a design: per-module counts of `comb` operations weighted by their bit
+//!  width, register bits, mux trees and memory bits, estimated areas, and totals over each
+//!  module's instance hierarchy.
+
+use super::cost::CostModel;
+use super::{bit_width, op_width, REGISTER_OPS};
+use crate::crate_prelude::*;
+use hw::{HwModuleOp, InstanceGraph};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OpStats {
+    pub count: usize,
+    /// Sum of the widths of the operations, each the widest of its operands and results.
+    pub bits: usize,
+    pub area: f64,
+}
+
+impl OpStats {
+    fn add(&mut self, other: &OpStats, times: usize) {
+        self.count += other.count * times;
+        self.bits += other.bits * times;
+        self.area += other.area * times as f64;
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModuleStats {
+    /// Statistics of the `comb` operations, by operation name.
+    pub comb: BTreeMap<String, OpStats>,
+    pub registers: OpStats,
+    /// Trees of `comb.mux`es feeding only into the data inputs of other muxes, counted by root.
+    pub mux_trees: usize,
+    /// Number of muxes on the deepest path through a mux tree.
+    pub max_mux_tree_depth: usize,
+    pub memories: OpStats,
+    /// Number of instances of each module.
+    pub instances: BTreeMap<String, usize>,
+    pub area: f64,
+}
+
+impl ModuleStats {
+    /// Account for `times` instances of a module with statistics `other`.
+    fn add(&mut self, other: &ModuleStats, times: usize) {
+        for (name, stats) in &other.comb {
+            self.comb.entry(name.clone()).or_default().add(stats, times);
+        }
+        self.registers.add(&other.registers, times);
+        self.mux_trees += other.mux_trees * times;
+        self.max_mux_tree_depth = self.max_mux_tree_depth.max(other.max_mux_tree_depth);
+        self.memories.add(&other.memories, times);
+        for (name, count) in &other.instances {
+            *self.instances.entry(name.clone()).or_default() += count * times;
+        }
+        self.area += other.area * times as f64;
+    }
+
+    pub fn comb_bits(&self) -> usize {
+        self.comb.values().map(|stats| stats.bits).sum()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModuleReport {
+    pub is_extern: bool,
+    /// Statistics of the module's own operations.
+    pub local: ModuleStats,
+    /// Statistics of the module and everything it instantiates.
+    pub total: ModuleStats,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StatsReport {
+    pub modules: BTreeMap<String, ModuleReport>,
+    pub top_level: Vec<String>,
+}
+
+impl StatsReport {
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        serde_json::from_str(json)
+            .map_err(|e| Error::simple(format!("Failed to parse statistics report: {}", e)))
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+/// Bits of a memory type: `!seq.hlmem<4x8xi32>`, or `!seq.firmem<16 x 32, mask 4>`.
+fn parse_memory_bits(ty: &str) -> Option<usize> {
+    let (kind, shape) = ty.strip_suffix('>')?.split_once('<')?;
+    let dims: Vec<&str> = match kind {
+        "!seq.hlmem" => shape.split('x').collect(),
+        "!seq.firmem" => shape.split(',').next()?.split('x').collect(),
+        _ => return None,
+    };
+    let (element, dims) = dims.split_last()?;
+    let element = element.trim();
+    let width = element.strip_prefix('i').unwrap_or(element).parse::<usize>().ok()?;
+    dims.iter().try_fold(width, |bits, dim| Some(bits * dim.trim().parse::<usize>().ok()?))
+}
+
+/// Bits of the memory declared by `op`, if it is one.
+fn memory_bits(op: &Operation) -> Option<usize> {
+    let ty = op.result_at(0)?.ty();
+    match op.name().to_string().as_str() {
+        "seq.hlmem" | "seq.firmem" => parse_memory_bits(&ty.to_string()),
+        "sv.reg" | "sv.logic" => {
+            let element = hw::InOutType::try_from(ty).ok()?.element_type();
+            hw::UnpackedArrayType::isa(&element).then(|| bit_width(&element))?
+        }
+        _ => None,
+    }
+}
+
+/// Count the mux trees among `muxes`, returning their number and the depth of the deepest.
+fn mux_trees(muxes: &[Operation], uses: &HashMap<Value, Vec<(String, usize)>>) -> (usize, usize) {
+    let index: HashMap<Value, usize> =
+        (muxes.iter().enumerate()).filter_map(|(i, op)| Some((op.result_at(0)?, i))).collect();
+    // A mux is inside a tree if its only use is as a data input of another mux.
+    let inner = |value: &Value| match uses.get(value).map(Vec::as_slice) {
+        Some([(name, operand)]) => name == "comb.mux" && *operand > 0,
+        _ => false,
+    };
+    fn depth(
+        i: usize,
+        muxes: &[Operation],
+        index: &HashMap<Value, usize>,
+        inner: &dyn Fn(&Value) -> bool,
+        depths: &mut [Option<usize>],
+    ) -> usize {
+        if let Some(depth) = depths[i] {
+            return depth;
+        }
+        // Mark the mux as visited, in case of a combinational loop.
+        depths[i] = Some(1);
+        let mut max = 0;
+        for data in muxes[i].operands().iter().skip(1).filter(|data| inner(data)) {
+            if let Some(&j) = index.get(*data) {
+                max = max.max(depth(j, muxes, index, inner, depths));
+            }
+        }
+        depths[i] = Some(max + 1);
+        max + 1
+    }
+    let mut depths = vec![None; muxes.len()];
+    let roots: Vec<usize> = (0..muxes.len())
+        .filter(|&i| muxes[i].result_at(0).map_or(true, |result| !inner(&result)))
+        .collect();
+    let max_depth =
+        (roots.iter()).map(|&i| depth(i, muxes, &index, &inner, &mut depths)).max().unwrap_or(0);
+    (roots.len(), max_depth)
+}
+
+/// Statistics of the operations of `hw_module`, not including the modules it instantiates.
+pub fn module_stats(hw_module: &HwModuleOp, costs: &CostModel) -> ModuleStats {
+    let mut stats = ModuleStats::default();
+    let mut muxes = vec![];
+    let mut uses: HashMap<Value, Vec<(String, usize)>> = HashMap::new();
+    hw_module.walk(&mut |op| {
+        let name = op.name().to_string();
+        for (i, operand) in op.operands().into_iter().enumerate() {
+            uses.entry(operand).or_default().push((name.clone(), i));
+        }
+        let width = op_width(&op);
+        let op_stats = if name.starts_with("comb.") {
+            if name == "comb.mux" {
+                muxes.push(op);
+            }
+            stats.comb.entry(name.clone()).or_default()
+        } else if REGISTER_OPS.contains(&name.as_str()) {
+            &mut stats.registers
+        } else if let Some(bits) = memory_bits(&op) {
+            stats.memories.add(
+                &OpStats {
+                    count: 1,
+                    bits,
+                    area: costs.area(&name, bits),
+                },
+                1,
+            );
+            return;
+        } else {
+            if let Some(inst) = op.try_into_op::<hw::InstanceOp>() {
+                *stats.instances.entry(inst.module_name()).or_default() += 1;
+            }
+            return;
+        };
+        op_stats.count += 1;
+        op_stats.bits += width;
+        op_stats.area += costs.area(&name, width);
+    });
+    (stats.mux_trees, stats.max_mux_tree_depth) = mux_trees(&muxes, &uses);
+    stats.area = stats.comb.values().map(|op_stats| op_stats.area).sum::<f64>()
+        + stats.registers.area
+        + stats.memories.area;
+    stats
+}
+
+/// Statistics of every module at the top level of `module`, with totals over their instance
+///  hierarchies. External modules have no statistics of their own.
+pub fn design_stats(module: &Module, costs: &CostModel) -> Result<StatsReport, Error> {
+    let graph = InstanceGraph::new(module)?;
+    let mut report = StatsReport {
+        top_level: (graph.top_level_modules().iter())
+            .map(|&id| graph.node(id).name.clone())
+            .collect(),
+        ..Default::default()
+    };
+    for id in graph.post_order()? {
+        let node = graph.node(id);
+        let local = match node.op.try_into_op::<HwModuleOp>() {
+            Some(hw_module) => module_stats(&hw_module, costs),
+            None => ModuleStats::default(),
+        };
+        let mut total = local.clone();
+        for (name, count) in &local.instances {
+            // Modules come after those they instantiate.
+            let sub = &report.modules[name].total;
+            total.add(sub, *count);
+        }
+        let module_report = ModuleReport {
+            is_extern: node.is_extern(),
+            local,
+            total,
+        };
+        report.modules.insert(node.name.clone(), module_report);
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hw::{HwModuleExternOp, InstanceOp, ModulePortInfo};
+
+    #[test]
+    fn memory_types() {
+        assert_eq!(parse_memory_bits("!seq.hlmem<4x8xi32>"), Some(1024));
+        assert_eq!(parse_memory_bits("!seq.firmem<16 x 32, mask 4>"), Some(512));
+        assert_eq!(parse_memory_bits("!hw.array<4xi8>"), None);
+        let model = CostModel::default();
+        assert_eq!(model.area("comb.mul", 8), 384.0);
+        assert_eq!(model.delay("comb.add", 16), 9.0);
+        assert_eq!(model.area("comb.extract", 64), 0.0);
+        assert_eq!(CostModel::from_json(&model.to_json()).unwrap(), model);
+        let partial = CostModel::from_json(r#"{ "area": { "comb.add": { "linear": 2 } } }"#);
+        assert_eq!(partial.unwrap().area("comb.add", 8), 16.0);
+    }
+
+    #[test]
+    fn hierarchical_stats() -> miette::Result<()> {
+        let ctx = OwnedContext::default();
+        hw::dialect().load(&ctx).unwrap();
+        comb::dialect().load(&ctx).unwrap();
+        seq::dialect().load(&ctx).unwrap();
+
+        let mut builder = OpBuilder::new(&ctx);
+        let module = Module::create(builder.loc());
+        let i1 = IntegerType::new(&ctx, 1);
+        let i8 = IntegerType::new(&ctx, 8);
+        let mut ports = ModulePortInfo::default();
+        ports.add_input("clk", &i1);
+        ports.add_input("sel", &i8);
+        ports.add_input("a", &i8);
+        ports.add_output("y", &i8);
+        let ext = HwModuleExternOp::build(&mut builder, &module, "ext", &ports, &[])?;
+        let leaf = HwModuleOp::build_with(
+            &mut builder,
+            &module,
+            "leaf",
+            &ports,
+            &[],
+            "",
+            |builder, _, inputs, outputs| {
+                // A tree of three muxes and a separate mux.
+                let bit = |builder: &mut OpBuilder, i| {
+                    comb::ExtractOp::with_sizes(builder, &inputs["sel"], i, 1).unwrap().result()
+                };
+                let (s0, s1, s2) = (bit(builder, 0), bit(builder, 1), bit(builder, 2));
+                let a = inputs["a"];
+                let sum = comb::AddOp::build(builder, &a, &a).unwrap().result();
+                let m0 = comb::MuxOp::build(builder, &s0, &a, &sum).unwrap().result();
+                let m1 = comb::MuxOp::build(builder, &s1, &m0, &a).unwrap().result();
+                let m2 = comb::MuxOp::build(builder, &s2, &sum, &m1).unwrap().result();
+                let m3 = comb::MuxOp::build(builder, &s0, &m2, &a).unwrap().result();
+                let m4 = comb::MuxOp::build(builder, &s1, &m2, &m3).unwrap().result();
+                let reg = seq::CompRegOp::build(builder, "r", &m4, &inputs["clk"], None, None);
+                outputs.insert("y".to_string(), reg.unwrap().output());
+            },
+        )?;
+        HwModuleOp::build_with(
+            &mut builder,
+            &module,
+            "top",
+            &ports,
+            &[],
+            "",
+            |builder, _, inputs, outputs| {
+                let args = [inputs["clk"], inputs["sel"], inputs["a"]];
+                let l0 = InstanceOp::build(builder, "l0", &leaf, args, &[]).unwrap();
+                let l1 = InstanceOp::build(builder, "l1", &leaf, args, &[]).unwrap();
+                let e = InstanceOp::build(builder, "e", &ext, args, &[]).unwrap();
+                let y = [l0, l1, e].map(|inst| inst.result_at(0).unwrap());
+                outputs.insert("y".to_string(), comb::XorOp::build(builder, y).unwrap().result());
+            },
+        )?;
+
+        let costs = CostModel::unit();
+        let report = design_stats(&module, &costs)?;
+        assert_eq!(report.top_level, ["top"]);
+        assert!(report.modules["ext"].is_extern);
+        let leaf = &report.modules["leaf"].local;
+        assert_eq!(leaf.comb["comb.mux"].count, 5);
+        assert_eq!(leaf.comb["comb.mux"].bits, 40);
+        assert_eq!(leaf.comb["comb.extract"].area, 0.0);
+        // m2 feeds two muxes, so it roots its own tree of m2 and m1 and m0.
+        assert_eq!((leaf.mux_trees, leaf.max_mux_tree_depth), (2, 3));
+        assert_eq!(leaf.registers.bits, 8);
+        assert_eq!(leaf.area, 8.0 + 40.0 + 8.0);
+
+        let top = &report.modules["top"].total;
+        assert_eq!(top.instances, [("ext".to_string(), 1), ("leaf".to_string(), 2)].into());
+        assert_eq!(top.comb["comb.mux"].count, 10);
+        assert_eq!(top.registers.count, 2);
+        assert_eq!(top.mux_trees, 4);
+        assert_eq!(top.area, 2.0 * leaf.area + 8.0);
+        assert_eq!(StatsReport::from_json(&report.to_json())?, report);
+        Ok(())
+    }
+}
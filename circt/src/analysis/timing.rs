@@ -0,0 +1,245 @@
+// Copyright (c) 2022-2023 Kamyar Mohajerani
+
+//! Static estimation of combinational depth: the longest register-to-register and
+//!  port-to-register paths of each module under the delays of a `CostModel`.
+//! Arrival times are measured from the clock edge, without clock-to-output delays. Instances are
+//!  opaque: their outputs start paths like input ports.
+
+use super::cost::CostModel;
+use super::loops::topological_order;
+use super::{op_width, source_string, string_array, string_attr, REGISTER_OPS};
+use crate::crate_prelude::*;
+use hw::{HwModuleLike, HwModuleOp, InstanceGraph};
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathKind {
+    RegToReg,
+    PortToReg,
+}
+
+/// An operation on a path, with its delay and the arrival time at its result.
+#[derive(Debug, Clone)]
+pub struct PathStep {
+    pub op: Operation,
+    pub delay: f64,
+    pub arrival: f64,
+}
+
+impl PathStep {
+    pub fn loc(&self) -> Location {
+        self.op.loc()
+    }
+
+    /// Filename, line and column of the operation, if it has a file location.
+    pub fn source(&self) -> Option<(String, usize, usize)> {
+        self.loc().file_line_col()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CriticalPath {
+    pub kind: PathKind,
+    pub delay: f64,
+    /// The input port, instance output as `instance.port`, or register the path starts at.
+    pub start: String,
+    /// The register the path ends at.
+    pub end: String,
+    pub steps: Vec<PathStep>,
+}
+
+impl fmt::Display for CriticalPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} -> {}: {}", self.start, self.end, self.delay)?;
+        for step in &self.steps {
+            let (name, source) = (step.op.name().to_string(), source_string(&step.loc()));
+            writeln!(f, "  {:>8} {:>8} {} at {}", step.arrival, step.delay, name, source)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ModuleTiming {
+    pub module: String,
+    pub reg_to_reg: Option<CriticalPath>,
+    pub port_to_reg: Option<CriticalPath>,
+}
+
+/// Latest arrival at a value from a start point of each kind, with the operand it arrives
+///  through, or `None` at the start point itself.
+type Arrivals = [Option<(f64, Option<Value>)>; 2];
+
+/// The critical paths of `hw_module`, with delays of operations from `model`.
+pub fn module_timing(hw_module: &HwModuleOp, model: &CostModel) -> Result<ModuleTiming, Error> {
+    let body = hw_module.first_block().ok_or(Error::IsNone)?;
+    let mut arrivals: HashMap<Value, Arrivals> = HashMap::new();
+    let mut starts: HashMap<Value, String> = HashMap::new();
+    for (port, arg) in hw_module.port_info().inputs.iter().zip(body.arguments()) {
+        arrivals.insert(arg, [Some((0.0, None)), None]);
+        starts.insert(arg, port.name.clone());
+    }
+
+    let mut registers = vec![];
+    let mut comb = vec![];
+    for op in body.operations() {
+        let name = op.name().to_string();
+        if REGISTER_OPS.contains(&name.as_str()) {
+            let result = op.result_at(0).ok_or(Error::IsNone)?;
+            let reg_name =
+                string_attr(&op, "name").unwrap_or_else(|| format!("_reg{}", registers.len()));
+            arrivals.insert(result, [None, Some((0.0, None))]);
+            starts.insert(result, reg_name.clone());
+            registers.push((op, reg_name));
+        } else if let Some(inst) = op.try_into_op::<hw::InstanceOp>() {
+            for (port, result) in string_array(&op, "resultNames").iter().zip(inst.results()) {
+                arrivals.insert(result, [Some((0.0, None)), None]);
+                starts.insert(result, format!("{}.{}", inst.instance_name(), port));
+            }
+        } else if name != "hw.output" && !op.operands().is_empty() {
+            comb.push(op);
+        }
+    }
+
+    // Visit the combinational operations in topological order.
+    let index: HashMap<Value, usize> = (comb.iter().enumerate())
+        .flat_map(|(i, op)| op.results().into_iter().map(move |result| (result, i)))
+        .collect();
+    let preds: Vec<Vec<usize>> = (comb.iter())
+        .map(|op| op.operands().iter().filter_map(|operand| index.get(operand).copied()).collect())
+        .collect();
+    let order = topological_order(&preds).map_err(|blocked| {
+        Error::simple(format!(
+            "Combinational loop through or into `{}` in `{}`",
+            comb[blocked[0]].name().to_string(),
+            hw_module.module_name()
+        ))
+    })?;
+    for i in order {
+        let op = &comb[i];
+        let delay = model.delay(&op.name().to_string(), op_width(op));
+        let mut arrival: Arrivals = [None, None];
+        for operand in op.operands() {
+            for (kind, latest) in arrivals.get(&operand).into_iter().flatten().enumerate() {
+                if let Some((time, _)) = latest {
+                    if arrival[kind].map_or(true, |(current, _)| *time + delay > current) {
+                        arrival[kind] = Some((*time + delay, Some(operand)));
+                    }
+                }
+            }
+        }
+        for result in op.results() {
+            arrivals.insert(result, arrival);
+        }
+    }
+
+    let path = |kind: usize, end: &str, endpoint: Value| -> Option<CriticalPath> {
+        let (delay, _) = arrivals.get(&endpoint)?[kind]?;
+        let mut steps = vec![];
+        let mut value = endpoint;
+        while let Some((arrival, Some(prev))) = arrivals[&value][kind] {
+            let op = comb[index[&value]];
+            let (prev_arrival, _) = arrivals[&prev][kind]?;
+            steps.push(PathStep {
+                op,
+                delay: arrival - prev_arrival,
+                arrival,
+            });
+            value = prev;
+        }
+        steps.reverse();
+        Some(CriticalPath {
+            kind: [PathKind::PortToReg, PathKind::RegToReg][kind],
+            delay,
+            start: starts.get(&value)?.clone(),
+            end: end.to_string(),
+            steps,
+        })
+    };
+    let mut timing = ModuleTiming {
+        module: hw_module.module_name(),
+        reg_to_reg: None,
+        port_to_reg: None,
+    };
+    for (op, reg_name) in &registers {
+        // The clock, operand 1, is not a timing path.
+        for (_, endpoint) in op.operands().into_iter().enumerate().filter(|(i, _)| *i != 1) {
+            for (kind, critical) in [(1, &mut timing.reg_to_reg), (0, &mut timing.port_to_reg)] {
+                if let Some(candidate) = path(kind, reg_name, endpoint) {
+                    if critical.as_ref().map_or(true, |path| candidate.delay > path.delay) {
+                        *critical = Some(candidate);
+                    }
+                }
+            }
+        }
+    }
+    Ok(timing)
+}
+
+/// The critical paths of every `hw.module` at the top level of `module`.
+pub fn design_timing(module: &Module, model: &CostModel) -> Result<Vec<ModuleTiming>, Error> {
+    let graph = InstanceGraph::new(module)?;
+    (graph.nodes().iter())
+        .filter_map(|node| node.op.try_into_op::<HwModuleOp>())
+        .map(|hw_module| module_timing(&hw_module, model))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hw::ModulePortInfo;
+
+    #[test]
+    fn critical_paths() -> miette::Result<()> {
+        let ctx = OwnedContext::default();
+        hw::dialect().load(&ctx).unwrap();
+        comb::dialect().load(&ctx).unwrap();
+        seq::dialect().load(&ctx).unwrap();
+
+        let mut builder = OpBuilder::new(&ctx);
+        let module = Module::create(builder.loc());
+        let i1 = IntegerType::new(&ctx, 1);
+        let i8 = IntegerType::new(&ctx, 8);
+        let mut ports = ModulePortInfo::default();
+        ports.add_input("clk", &i1);
+        ports.add_input("a", &i8);
+        ports.add_output("y", &i8);
+        let mac = HwModuleOp::build_with(
+            &mut builder,
+            &module,
+            "mac",
+            &ports,
+            &[],
+            "",
+            |builder, _, inputs, outputs| {
+                let (clk, a) = (inputs["clk"], inputs["a"]);
+                let r1 = seq::CompRegOp::build(builder, "r1", &a, &clk, None, None).unwrap();
+                let loc = *builder.loc();
+                builder.set_loc(Location::new(builder.context(), "gen.py", 12, 3));
+                let product = comb::MulOp::build(builder, &r1.output(), &r1.output()).unwrap();
+                builder.set_loc(loc);
+                let sum = comb::AddOp::build(builder, &product.result(), &a).unwrap();
+                let r2 = seq::CompRegOp::build(builder, "r2", &sum.result(), &clk, None, None);
+                outputs.insert("y".to_string(), r2.unwrap().output());
+            },
+        )?;
+
+        let timing = module_timing(&mac, &CostModel::unit())?;
+        let reg_to_reg = timing.reg_to_reg.unwrap();
+        assert_eq!((reg_to_reg.start.as_str(), reg_to_reg.end.as_str()), ("r1", "r2"));
+        assert_eq!(reg_to_reg.delay, 2.0);
+        let names: Vec<String> = reg_to_reg.steps.iter().map(|s| s.op.name().to_string()).collect();
+        assert_eq!(names, ["comb.mul", "comb.add"]);
+        assert_eq!(reg_to_reg.steps[0].source(), Some(("gen.py".to_string(), 12, 3)));
+        assert_eq!(reg_to_reg.steps[1].arrival, 2.0);
+        let port_to_reg = timing.port_to_reg.unwrap();
+        assert_eq!((port_to_reg.start.as_str(), port_to_reg.delay), ("a", 1.0));
+
+        // 1 + 4 log2(8) for the multiplier and 1 + 2 log2(8) for the adder.
+        let timing = design_timing(&module, &CostModel::default())?;
+        assert_eq!(timing[0].reg_to_reg.as_ref().unwrap().delay, 20.0);
+        Ok(())
+    }
+}
@@ -0,0 +1,323 @@
+// Copyright (c) 2022-2023 Kamyar Mohajerani
+
+//! The Arc dialect is the basis of `arcilator`, representing designs as state and pure
+//!  combinational arcs between state, suitable for fast cycle-based simulation.
+//! `Simulator` runs the arcilator lowering on an HW module and JIT-compiles the resulting model
+//!  with MLIR's execution engine.
+//! See https://circt.llvm.org/docs/Dialects/Arc/ for more details.
+
+use crate::crate_prelude::*;
+use circt_sys::*;
+use num::{BigUint, One};
+use std::collections::HashMap;
+use std::ffi::c_void;
+
+define_dialect!(arc);
+
+/// Register the Arc passes, along with `convert-to-arcs` and `lower-arc-to-llvm`.
+pub fn register_passes() {
+    unsafe {
+        arcRegisterPasses();
+        arcRegisterConversionPasses();
+    }
+}
+
+/// Add the passes turning HW modules into Arc models with allocated state to `pm`.
+pub fn add_lower_to_models_passes(pm: &PassManager) -> Result<(), Error> {
+    #[rustfmt::skip]
+    pm
+        .parse_pass("arc-strip-sv")?
+        .parse_pass("convert-to-arcs")?
+        .parse_pass("arc-dedup")?
+        .parse_pass("arc-inline-modules")?
+        .parse_pass("canonicalize, cse")?
+        .parse_pass("arc-lower-state")?
+        .parse_pass("arc-legalize-state-update")?
+        .parse_pass("cse, canonicalize")?
+        .parse_pass("arc.model(arc-allocate-state)")?
+        .parse_pass("arc-lower-clocks-to-funcs")?
+        .parse_pass("canonicalize")?;
+    Ok(())
+}
+
+/// The location of a port or register in the state storage of a model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StateInfo {
+    /// Byte offset into the storage.
+    pub offset: usize,
+    pub num_bits: u32,
+}
+
+impl StateInfo {
+    pub fn num_bytes(&self) -> usize {
+        (self.num_bits as usize + 7) / 8
+    }
+}
+
+/// The layout of the state storage of an Arc model.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModelInfo {
+    pub name: String,
+    pub num_state_bytes: usize,
+    pub inputs: HashMap<String, StateInfo>,
+    pub outputs: HashMap<String, StateInfo>,
+}
+
+impl ModelInfo {
+    /// Collect the layout of the model `name` in `module`, which must have been lowered with
+    ///  `add_lower_to_models_passes`.
+    pub fn collect(module: &Module, name: &str) -> Result<Self, Error> {
+        let model = module
+            .body()
+            .operations()
+            .filter(|op| op.name().to_string() == "arc.model")
+            .find(|op| {
+                op.attribute(SymbolTable::symbol_attr_name())
+                    .and_then(|attr| StringAttr::try_from(attr).ok())
+                    .map_or(false, |attr| attr.get_value() == name)
+            })
+            .ok_or(Error::simple(format!("No Arc model `{}`", name)))?;
+        let storage = model
+            .first_block()
+            .and_then(|block| block.argument(0))
+            .map(|arg| arg.ty())
+            .filter(|ty| unsafe { arcTypeIsAStorageType(ty.raw()) })
+            .ok_or(Error::simple(format!("Model `{}` has no storage", name)))?;
+
+        let mut info = Self {
+            name: name.to_string(),
+            num_state_bytes: unsafe { arcStorageTypeGetSize(storage.raw()) } as usize,
+            ..Default::default()
+        };
+        let mut result = Ok(());
+        model.walk(&mut |op| {
+            let ports = match op.name().to_string().as_str() {
+                "arc.root_input" => &mut info.inputs,
+                "arc.root_output" => &mut info.outputs,
+                _ => return,
+            };
+            let port_name = op
+                .attribute("name")
+                .and_then(|attr| StringAttr::try_from(attr).ok())
+                .map(|attr| attr.get_value());
+            let offset = op
+                .attribute("offset")
+                .and_then(|attr| IntegerAttr::try_from(attr).ok())
+                .map(|attr| attr.value() as usize);
+            let ty = op.result_at(0).map(|result| result.ty());
+            match (port_name, offset, ty) {
+                (Some(port_name), Some(offset), Some(ty))
+                    if unsafe { arcTypeIsAStateType(ty.raw()) } =>
+                {
+                    let num_bits = unsafe { arcStateTypeGetBitWidth(ty.raw()) };
+                    ports.insert(port_name, StateInfo { offset, num_bits });
+                }
+                _ => result = Err(Error::simple(format!("Unallocated port in model `{}`", name))),
+            }
+        });
+        result.map(|_| info)
+    }
+}
+
+/// A cycle-based simulator of an HW module, compiled through Arc to native code.
+pub struct Simulator {
+    engine: MlirExecutionEngine,
+    eval: unsafe extern "C" fn(*mut u8),
+    model: ModelInfo,
+    storage: Vec<u64>,
+    clock: String,
+}
+
+impl Simulator {
+    /// Compile the top-level HW module `top` of `module`. `module` is left untouched; its
+    ///  dialects must be loaded in `ctx`, and Arc passes registered with `register_passes`.
+    /// `clock` is the input toggled by `step_clock`.
+    pub fn new(ctx: &Context, module: &Module, top: &str, clock: &str) -> Result<Self, Error> {
+        dialect().load(ctx).ok_or(Error::simple("Failed to load the Arc dialect"))?;
+        let copy = module.op().deep_copy();
+        let sim_module = Module::from_op(&copy).ok_or(Error::IsNone)?;
+        let simulator = Self::compile(ctx, &sim_module, top, clock);
+        copy.erase();
+        simulator
+    }
+
+    fn compile(ctx: &Context, module: &Module, top: &str, clock: &str) -> Result<Self, Error> {
+        let pm = OwnedPassManager::new(ctx);
+        add_lower_to_models_passes(&pm)?;
+        pm.run(module)?;
+        let model = ModelInfo::collect(module, top)?;
+        if !model.inputs.contains_key(clock) {
+            return Err(Error::simple(format!("No clock input `{}` in `{}`", clock, top)));
+        }
+
+        let pm = OwnedPassManager::new(ctx);
+        pm.parse_pass("lower-arc-to-llvm")?.parse_pass("canonicalize, cse")?;
+        pm.run(module)?;
+
+        unsafe { arcRegisterLLVMTranslations(ctx.raw()) };
+        let engine =
+            unsafe { mlirExecutionEngineCreate(module.raw(), 2, 0, std::ptr::null(), false) };
+        if engine.ptr.is_null() {
+            return Err(Error::simple("Failed to create the execution engine"));
+        }
+        let eval_name = format!("{}_eval", top);
+        let eval =
+            unsafe { mlirExecutionEngineLookup(engine, StringRef::from_str(&eval_name).raw()) };
+        if eval.is_null() {
+            unsafe { mlirExecutionEngineDestroy(engine) };
+            return Err(Error::simple(format!(
+                "No function `{}` in the compiled model",
+                eval_name
+            )));
+        }
+        // SAFETY: `mlirExecutionEngineLookup` returns the address of the raw `{top}_eval` symbol,
+        //  not of an `_mlir_ciface_` wrapper, and `lower-arc-to-llvm` emits it as `void(ptr)`,
+        //  taking the state storage.
+        let eval =
+            unsafe { std::mem::transmute::<*mut c_void, unsafe extern "C" fn(*mut u8)>(eval) };
+        Ok(Self {
+            engine,
+            eval,
+            storage: vec![0; (model.num_state_bytes + 7) / 8],
+            model,
+            clock: clock.to_string(),
+        })
+    }
+
+    pub fn model(&self) -> &ModelInfo {
+        &self.model
+    }
+
+    fn bytes(&self, state: &StateInfo) -> &[u8] {
+        let storage = unsafe {
+            std::slice::from_raw_parts(self.storage.as_ptr() as *const u8, self.storage.len() * 8)
+        };
+        &storage[state.offset..state.offset + state.num_bytes()]
+    }
+
+    fn bytes_mut(&mut self, state: &StateInfo) -> &mut [u8] {
+        let storage = unsafe {
+            std::slice::from_raw_parts_mut(
+                self.storage.as_mut_ptr() as *mut u8,
+                self.storage.len() * 8,
+            )
+        };
+        &mut storage[state.offset..state.offset + state.num_bytes()]
+    }
+
+    /// Set the input `port` to `value`, truncated to the width of the port. Takes effect with the
+    ///  next `eval` or `step_clock`.
+    pub fn set_input(&mut self, port: &str, value: BigUint) -> Result<(), Error> {
+        let state = *self
+            .model
+            .inputs
+            .get(port)
+            .ok_or(Error::simple(format!("No input port `{}`", port)))?;
+        let value = value & ((BigUint::one() << state.num_bits) - BigUint::one());
+        let value = value.to_bytes_le();
+        let bytes = self.bytes_mut(&state);
+        bytes.fill(0);
+        bytes[..value.len()].copy_from_slice(&value);
+        Ok(())
+    }
+
+    /// The current value of the output `port`.
+    pub fn get_output(&self, port: &str) -> Result<BigUint, Error> {
+        let state = self
+            .model
+            .outputs
+            .get(port)
+            .ok_or(Error::simple(format!("No output port `{}`", port)))?;
+        let value = BigUint::from_bytes_le(self.bytes(state));
+        Ok(value & ((BigUint::one() << state.num_bits) - BigUint::one()))
+    }
+
+    /// Propagate the inputs through the combinational logic and update registers whose clock
+    ///  has risen since the last evaluation.
+    pub fn eval(&mut self) {
+        unsafe { (self.eval)(self.storage.as_mut_ptr() as *mut u8) }
+    }
+
+    /// Run one clock cycle: a rising and a falling edge of the clock.
+    /// The clock is an input of the model, as `new` fails otherwise.
+    pub fn step_clock(&mut self) {
+        let clock = self.clock.clone();
+        self.set_input(&clock, BigUint::one()).expect("the clock is checked by `new`");
+        self.eval();
+        self.set_input(&clock, BigUint::default())
+            .expect("the clock is checked by `new`");
+        self.eval();
+    }
+}
+
+impl Drop for Simulator {
+    fn drop(&mut self) {
+        unsafe { mlirExecutionEngineDestroy(self.engine) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hw::{HwModuleOp, ModulePortInfo};
+
+    #[test]
+    fn simulate_accumulator() -> miette::Result<()> {
+        let ctx = OwnedContext::default();
+        hw::dialect().load(&ctx).unwrap();
+        comb::dialect().load(&ctx).unwrap();
+        seq::dialect().load(&ctx).unwrap();
+        mlir::register_cse();
+        mlir::register_canonicalize();
+        register_passes();
+
+        let mut builder = OpBuilder::new(&ctx);
+        let module = Module::create(builder.loc());
+
+        let i1 = IntegerType::new(&ctx, 1);
+        let i8 = IntegerType::new(&ctx, 8);
+        let mut ports = ModulePortInfo::default();
+        ports.add_input("clk", &i1);
+        ports.add_input("a", &i8);
+        ports.add_output("acc", &i8);
+        ports.add_output("next", &i8);
+
+        HwModuleOp::build_with(
+            &mut builder,
+            &module,
+            "accumulator",
+            &ports,
+            &[],
+            "",
+            |builder, _, inputs, outputs| {
+                let acc =
+                    seq::CompRegOp::build(builder, "acc", &inputs["a"], &inputs["clk"], None, None)
+                        .unwrap();
+                let next = comb::AddOp::build(builder, &acc.output(), &inputs["a"]).unwrap();
+                acc.set_input(&next.result());
+                outputs.insert("acc".to_string(), acc.output());
+                outputs.insert("next".to_string(), next.result());
+            },
+        )?;
+
+        let mut sim = Simulator::new(&ctx, &module, "accumulator", "clk")?;
+        assert!(sim.model().inputs.contains_key("a"));
+        sim.set_input("a", BigUint::from(5u32))?;
+        sim.eval();
+        assert_eq!(sim.get_output("acc")?, BigUint::from(0u32));
+        assert_eq!(sim.get_output("next")?, BigUint::from(5u32));
+        for _ in 0..3 {
+            sim.step_clock();
+        }
+        assert_eq!(sim.get_output("acc")?, BigUint::from(15u32));
+        sim.set_input("a", BigUint::from(250u32))?;
+        sim.step_clock();
+        assert_eq!(sim.get_output("acc")?, BigUint::from(9u32));
+        assert!(sim.get_output("a").is_err());
+        assert!(Simulator::new(&ctx, &module, "accumulator", "clock").is_err());
+
+        // The simulated module is left as built.
+        assert!(module.body().operations().any(|op| op.name().to_string() == "hw.module"));
+        Ok(())
+    }
+}
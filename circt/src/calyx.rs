@@ -0,0 +1,644 @@
+// Copyright (c) 2022-2023 Kamyar Mohajerani
+
+//! The Calyx dialect separates the structure of a circuit (cells and wires) from its control
+//!  program (sequencing, parallelism and loops over groups of assignments).
+//! A component holds cells, a `calyx.wires` section with groups of assignments, and a
+//!  `calyx.control` section scheduling the groups. `add_lower_to_hw_passes` compiles the
+//!  control to FSMs and lowers the result to HW.
+//! See https://circt.llvm.org/docs/Dialects/Calyx/ for more details.
+
+use crate::crate_prelude::*;
+use circt_sys::*;
+use hw::{port_names, port_types, ModulePortInfo, PortInfo};
+use num::{BigUint, One};
+use std::collections::HashMap;
+
+define_dialect!(calyx);
+
+pub fn register_passes() {
+    unsafe { calyxRegisterPasses() }
+}
+
+pub mod passes {
+    use crate::*;
+
+    /// Lowers Calyx components, whose groups and control have been removed, to HW modules.
+    pub fn lower_calyx_to_hw() -> Pass {
+        Pass::try_from_raw(unsafe { calyxCreateCalyxToHWPass() }).unwrap()
+    }
+
+    pub fn register_lower_calyx_to_hw() {
+        unsafe { calyxRegisterCalyxToHWPass() }
+    }
+}
+
+/// Add the passes compiling the control of Calyx components and lowering them to HW to `pm`.
+/// Calyx passes must have been registered with `register_passes`.
+pub fn add_lower_to_hw_passes(pm: &PassManager) -> Result<(), Error> {
+    #[rustfmt::skip]
+    pm
+        .parse_pass("calyx-remove-comb-groups")?
+        .parse_pass("canonicalize")?
+        .parse_pass("calyx-go-insertion")?
+        .parse_pass("canonicalize")?
+        .parse_pass("calyx-compile-control")?
+        .parse_pass("calyx-remove-groups")?
+        .add_pass(&passes::lower_calyx_to_hw());
+    Ok(())
+}
+
+/// Names of the interface ports every component has, in addition to its own ports.
+pub const GO_PORT: &str = "go";
+pub const CLK_PORT: &str = "clk";
+pub const RESET_PORT: &str = "reset";
+pub const DONE_PORT: &str = "done";
+
+/// Run `with_fn` with the insertion point at the end of `block`, restoring it afterwards.
+fn build_in(builder: &mut OpBuilder, block: Block, with_fn: impl FnOnce(&mut OpBuilder)) {
+    let insert_point = builder.insert_point.clone();
+    builder.set_insertion_point(Some(InsertPoint::BlockEnd(block)));
+    with_fn(builder);
+    builder.set_insertion_point(insert_point);
+}
+
+fn sym_name_of(op: &impl Op) -> String {
+    op.attribute(SymbolTable::symbol_attr_name())
+        .and_then(|attr| StringAttr::try_from(attr).ok())
+        .map(|attr| attr.get_value())
+        .unwrap()
+}
+
+def_operation!(ComponentOp, "calyx.component"; doc = "A Calyx component, made of cells, wires and control.");
+
+impl SingleRegionOp for ComponentOp {}
+
+impl SingleBlockOp for ComponentOp {}
+
+impl ComponentOp {
+    /// Create a component at the end of `module`. The `go`, `clk` and `reset` inputs and the `done`
+    ///  output are added to `ports`. `with_fn` is called with the insertion point in the body of the
+    ///  component, to add cells, and with all ports by name; use `with_wires` and `with_control` to
+    ///  populate the other sections.
+    pub fn build_with(
+        builder: &mut OpBuilder,
+        module: &Module,
+        name: &str,
+        ports: &ModulePortInfo,
+        with_fn: impl FnOnce(&mut OpBuilder, &Self, &HashMap<String, Value>),
+    ) -> Result<Self, Error> {
+        builder.set_insertion_point(Some(InsertPoint::BlockEnd(module.body())));
+        let ctx = builder.context();
+        let i1 = IntegerType::new(ctx, 1);
+        let mut ports = ports.clone();
+        for name in [GO_PORT, CLK_PORT, RESET_PORT] {
+            ports.add_input(name, &i1);
+        }
+        ports.add_output(DONE_PORT, &i1);
+        let all_ports: Vec<&PortInfo> = ports.inputs.iter().chain(ports.outputs.iter()).collect();
+        let port_attrs: Vec<Attribute> = all_ports
+            .iter()
+            .map(|pi| {
+                let attrs = match pi.name.as_str() {
+                    GO_PORT | CLK_PORT | RESET_PORT | DONE_PORT => {
+                        vec![UnitAttr::new(ctx).to_named(&pi.name)]
+                    }
+                    _ => vec![],
+                };
+                DictionaryAttr::new(ctx, attrs).into()
+            })
+            .collect();
+        // One bit per port, set for outputs.
+        let directions =
+            ((BigUint::one() << ports.outputs.len()) - BigUint::one()) << ports.inputs.len();
+        let directions = IntegerAttr::from_str(
+            &IntegerType::new(ctx, all_ports.len() as u32),
+            &directions.to_string(),
+        );
+
+        let mut body = None;
+        let component: Self = builder
+            .build_with(|builder, state| {
+                let block = state.add_body();
+                for pi in all_ports.iter() {
+                    block.add_argument(&pi.ty, pi.loc.as_ref().unwrap_or(builder.loc()));
+                }
+                body = Some(block);
+                state.add_attribute(SymbolTable::symbol_attr_name(), &StringAttr::new(ctx, name));
+                state.add_attribute(
+                    "function_type",
+                    &TypeAttr::new(&FunctionType::new(
+                        ctx,
+                        port_types(ports.inputs.iter()),
+                        port_types(ports.outputs.iter()),
+                    )),
+                );
+                state.add_attribute(
+                    "portNames",
+                    &ArrayAttr::new(ctx, port_names(ctx, all_ports.iter().copied())),
+                );
+                state
+                    .add_attribute("portAttributes", &ArrayAttr::new::<Attribute>(ctx, port_attrs));
+                state.add_attribute("portDirections", &directions);
+            })
+            .ok_or(Error::simple("OpBuilder failed"))?;
+
+        let body = body.ok_or(Error::IsNone)?;
+        builder.set_insertion_point(Some(InsertPoint::BlockEnd(body.clone())));
+        let wires = WiresOp::build(builder).ok_or(Error::IsNone)?;
+        ControlOp::build(builder).ok_or(Error::IsNone)?;
+
+        let port_values: HashMap<String, Value> =
+            all_ports.iter().map(|pi| pi.name.clone()).zip(body.arguments()).collect();
+        builder.set_insertion_point(Some(InsertPoint::BeforeOp(
+            body,
+            Operation::from_raw(wires.raw()),
+        )));
+        with_fn(builder, &component, &port_values);
+        builder.set_insertion_point(Some(InsertPoint::BlockEnd(module.body())));
+        Ok(component)
+    }
+
+    pub fn wires(&self) -> WiresOp {
+        self.first_block().unwrap().operations().find_map(|op| op.try_into_op()).unwrap()
+    }
+
+    pub fn control(&self) -> ControlOp {
+        self.first_block().unwrap().operations().find_map(|op| op.try_into_op()).unwrap()
+    }
+
+    /// Call `with_fn` with the insertion point in the wires section, to add groups and
+    ///  continuous assignments.
+    pub fn with_wires(&self, builder: &mut OpBuilder, with_fn: impl FnOnce(&mut OpBuilder)) {
+        build_in(builder, self.wires().first_block().unwrap(), with_fn)
+    }
+
+    /// Call `with_fn` with the insertion point in the control section. It should add a single
+    ///  control operation, e.g. a `SeqOp`.
+    pub fn with_control(&self, builder: &mut OpBuilder, with_fn: impl FnOnce(&mut OpBuilder)) {
+        build_in(builder, self.control().first_block().unwrap(), with_fn)
+    }
+}
+
+/// Define an operation holding a single block, built at the current insertion point.
+macro_rules! def_calyx_section {
+    ($name:ident, $operation_name:expr; doc = $doc:tt) => {
+        def_operation!($name, $operation_name; doc = $doc);
+
+        impl SingleRegionOp for $name {}
+
+        impl SingleBlockOp for $name {}
+
+        impl $name {
+            pub fn build(builder: &mut OpBuilder) -> Option<Self> {
+                builder.build_with(|_, state| {
+                    state.add_body();
+                })
+            }
+
+            /// Create the operation and call `with_fn` with the insertion point in its body.
+            pub fn build_with(
+                builder: &mut OpBuilder,
+                with_fn: impl FnOnce(&mut OpBuilder),
+            ) -> Option<Self> {
+                let op = Self::build(builder)?;
+                build_in(builder, op.first_block()?, with_fn);
+                Some(op)
+            }
+        }
+    };
+}
+
+def_calyx_section!(WiresOp, "calyx.wires"; doc = "The section of a component holding its groups and continuous assignments.");
+def_calyx_section!(ControlOp, "calyx.control"; doc = "The section of a component holding its control program.");
+def_calyx_section!(SeqOp, "calyx.seq"; doc = "Runs the nested control operations one after the other.");
+def_calyx_section!(ParOp, "calyx.par"; doc = "Runs the nested control operations in parallel.");
+
+/// Define a group-like operation, named by a symbol and holding assignments.
+macro_rules! def_calyx_group {
+    ($name:ident, $operation_name:expr; doc = $doc:tt) => {
+        def_operation!($name, $operation_name; doc = $doc);
+
+        impl SingleRegionOp for $name {}
+
+        impl SingleBlockOp for $name {}
+
+        impl $name {
+            /// Create the group `name` in the wires section of `component`, calling `with_fn` with
+            ///  the insertion point in the group.
+            pub fn build_with(
+                builder: &mut OpBuilder,
+                component: &ComponentOp,
+                name: &str,
+                with_fn: impl FnOnce(&mut OpBuilder),
+            ) -> Option<Self> {
+                let mut group = None;
+                component.with_wires(builder, |builder| {
+                    group = builder.build_with(|builder, state| {
+                        state.add_body();
+                        state.add_attribute(
+                            SymbolTable::symbol_attr_name(),
+                            &StringAttr::new(builder.context(), name),
+                        );
+                    });
+                });
+                let group: Self = group?;
+                build_in(builder, group.first_block()?, with_fn);
+                Some(group)
+            }
+
+            pub fn sym_name(&self) -> String {
+                sym_name_of(self)
+            }
+        }
+    };
+}
+
+def_calyx_group!(GroupOp, "calyx.group"; doc = "A group of assignments, enabled by the control program. Signals completion with `GroupDoneOp`.");
+def_calyx_group!(CombGroupOp, "calyx.comb_group"; doc = "A group of combinational assignments, computing the conditions of `IfOp` and `WhileOp`.");
+
+def_operation!(AssignOp, "calyx.assign"; doc = "Drives `dest` with `src`, if `guard` is given while it is true.");
+
+impl AssignOp {
+    pub fn build(
+        builder: &mut OpBuilder,
+        dest: &Value,
+        src: &Value,
+        guard: Option<&Value>,
+    ) -> Option<Self> {
+        builder.build_with(|_, state| {
+            state.add_operand(dest);
+            state.add_operand(src);
+            state.add_operands::<Value>(guard);
+        })
+    }
+}
+
+def_operation!(GroupDoneOp, "calyx.group_done"; doc = "Signals that the enclosing group has completed once `src` is true.");
+
+impl GroupDoneOp {
+    pub fn build(builder: &mut OpBuilder, src: &Value, guard: Option<&Value>) -> Option<Self> {
+        builder.build_with(|_, state| {
+            state.add_operand(src);
+            state.add_operands::<Value>(guard);
+        })
+    }
+}
+
+def_operation!(EnableOp, "calyx.enable"; doc = "Runs a group until it is done.");
+
+impl EnableOp {
+    pub fn build(builder: &mut OpBuilder, group: &GroupOp) -> Option<Self> {
+        let name = group.sym_name();
+        builder.build_with(|builder, state| {
+            state.add_attribute("groupName", &SymbolRefAttr::new(builder.context(), &name));
+        })
+    }
+}
+
+/// Add the condition of a `WhileOp` or `IfOp`, computed by `comb_group` if given, to `state`.
+fn add_condition(
+    builder: &OpBuilder,
+    state: &mut OperationState,
+    cond: &Value,
+    comb_group: Option<&CombGroupOp>,
+) {
+    state.add_operand(cond);
+    if let Some(group) = comb_group {
+        state.add_attribute("groupName", &SymbolRefAttr::new(builder.context(), &group.sym_name()));
+    }
+}
+
+def_operation!(WhileOp, "calyx.while"; doc = "Runs its body as long as a condition port is true.");
+
+impl SingleRegionOp for WhileOp {}
+
+impl SingleBlockOp for WhileOp {}
+
+impl WhileOp {
+    pub fn build_with(
+        builder: &mut OpBuilder,
+        cond: &Value,
+        comb_group: Option<&CombGroupOp>,
+        with_fn: impl FnOnce(&mut OpBuilder),
+    ) -> Option<Self> {
+        let mut body = None;
+        let op = builder.build_with(|builder, state| {
+            add_condition(builder, state, cond, comb_group);
+            body = Some(state.add_body());
+        })?;
+        build_in(builder, body?, with_fn);
+        Some(op)
+    }
+}
+
+def_operation!(IfOp, "calyx.if"; doc = "Runs one of two branches depending on a condition port.");
+
+impl IfOp {
+    /// Create the conditional without an else branch, calling `then_fn` with the insertion point in
+    ///  the then branch.
+    pub fn build_with(
+        builder: &mut OpBuilder,
+        cond: &Value,
+        comb_group: Option<&CombGroupOp>,
+        then_fn: impl FnOnce(&mut OpBuilder),
+    ) -> Option<Self> {
+        Self::build_branches(builder, cond, comb_group, then_fn, None::<fn(&mut OpBuilder)>)
+    }
+
+    /// Create the conditional, calling `then_fn` and `else_fn` with the insertion point in the
+    ///  respective branch.
+    pub fn build_with_else(
+        builder: &mut OpBuilder,
+        cond: &Value,
+        comb_group: Option<&CombGroupOp>,
+        then_fn: impl FnOnce(&mut OpBuilder),
+        else_fn: impl FnOnce(&mut OpBuilder),
+    ) -> Option<Self> {
+        Self::build_branches(builder, cond, comb_group, then_fn, Some(else_fn))
+    }
+
+    fn build_branches(
+        builder: &mut OpBuilder,
+        cond: &Value,
+        comb_group: Option<&CombGroupOp>,
+        then_fn: impl FnOnce(&mut OpBuilder),
+        else_fn: Option<impl FnOnce(&mut OpBuilder)>,
+    ) -> Option<Self> {
+        let mut then_block = None;
+        let mut else_block = None;
+        let op = builder.build_with(|builder, state| {
+            add_condition(builder, state, cond, comb_group);
+            then_block = Some(state.add_body());
+            if else_fn.is_some() {
+                else_block = Some(state.add_body());
+            } else {
+                state.add_region(&Region::default());
+            }
+        })?;
+        build_in(builder, then_block?, then_fn);
+        if let (Some(block), Some(else_fn)) = (else_block, else_fn) {
+            build_in(builder, block, else_fn);
+        }
+        Some(op)
+    }
+}
+
+def_operation!(RegisterOp, "calyx.register"; doc = "A register cell, written when `write_en` is set and signalling `done` a cycle later.");
+
+impl RegisterOp {
+    pub fn build(builder: &mut OpBuilder, name: &str, ty: &impl Ty) -> Option<Self> {
+        builder.build_with(|builder, state| {
+            let i1 = IntegerType::new(builder.context(), 1);
+            state.add_attribute(
+                SymbolTable::symbol_attr_name(),
+                &StringAttr::new(builder.context(), name),
+            );
+            state.add_result(ty);
+            state.add_results([i1, i1, i1]);
+            state.add_result(ty);
+            state.add_result(&i1);
+        })
+    }
+
+    pub fn in_(&self) -> Value {
+        self.result_at(0).unwrap()
+    }
+
+    pub fn write_en(&self) -> Value {
+        self.result_at(1).unwrap()
+    }
+
+    pub fn clk(&self) -> Value {
+        self.result_at(2).unwrap()
+    }
+
+    pub fn reset(&self) -> Value {
+        self.result_at(3).unwrap()
+    }
+
+    pub fn out(&self) -> Value {
+        self.result_at(4).unwrap()
+    }
+
+    pub fn done(&self) -> Value {
+        self.result_at(5).unwrap()
+    }
+}
+
+/// Define a combinational primitive of the standard library with ports `left`, `right` and `out`.
+macro_rules! def_calyx_binary_primitive {
+    ($name:ident, $operation_name:expr, $out_width:expr) => {
+        def_operation!($name, $operation_name);
+
+        impl $name {
+            pub fn build(builder: &mut OpBuilder, name: &str, width: u32) -> Option<Self> {
+                builder.build_with(|builder, state| {
+                    let ctx = builder.context();
+                    let ty = IntegerType::new(ctx, width);
+                    state.add_attribute(
+                        SymbolTable::symbol_attr_name(),
+                        &StringAttr::new(ctx, name),
+                    );
+                    state.add_results([ty, ty]);
+                    state.add_result(&IntegerType::new(ctx, $out_width.unwrap_or(width)));
+                })
+            }
+
+            pub fn left(&self) -> Value {
+                self.result_at(0).unwrap()
+            }
+
+            pub fn right(&self) -> Value {
+                self.result_at(1).unwrap()
+            }
+
+            pub fn out(&self) -> Value {
+                self.result_at(2).unwrap()
+            }
+        }
+    };
+}
+
+def_calyx_binary_primitive!(AddLibOp, "calyx.std_add", None);
+def_calyx_binary_primitive!(SubLibOp, "calyx.std_sub", None);
+def_calyx_binary_primitive!(AndLibOp, "calyx.std_and", None);
+def_calyx_binary_primitive!(OrLibOp, "calyx.std_or", None);
+def_calyx_binary_primitive!(EqLibOp, "calyx.std_eq", Some(1));
+def_calyx_binary_primitive!(LtLibOp, "calyx.std_lt", Some(1));
+def_calyx_binary_primitive!(GtLibOp, "calyx.std_gt", Some(1));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accumulator_to_verilog() -> miette::Result<()> {
+        let ctx = OwnedContext::default();
+        hw::dialect().load(&ctx).unwrap();
+        comb::dialect().load(&ctx).unwrap();
+        seq::dialect().load(&ctx).unwrap();
+        sv::dialect().load(&ctx).unwrap();
+        dialect().load(&ctx).unwrap();
+        mlir::register_canonicalize();
+        register_passes();
+
+        let mut builder = OpBuilder::new(&ctx);
+        let module = Module::create(builder.loc());
+
+        let i32 = IntegerType::new(&ctx, 32);
+        let mut ports = ModulePortInfo::default();
+        ports.add_input("in", &i32);
+        ports.add_output("out", &i32);
+
+        ComponentOp::build_with(&mut builder, &module, "main", &ports, |builder, comp, ports| {
+            let acc = RegisterOp::build(builder, "acc", &i32).unwrap();
+            let add = AddLibOp::build(builder, "add", 32).unwrap();
+            let one = hw::ConstantOp::build(builder, 1, 1).result();
+
+            let update = GroupOp::build_with(builder, comp, "update", |builder| {
+                AssignOp::build(builder, &add.left(), &acc.out(), None).unwrap();
+                AssignOp::build(builder, &add.right(), &ports["in"], None).unwrap();
+                AssignOp::build(builder, &acc.in_(), &add.out(), None).unwrap();
+                AssignOp::build(builder, &acc.write_en(), &one, None).unwrap();
+                GroupDoneOp::build(builder, &acc.done(), None).unwrap();
+            })
+            .unwrap();
+            comp.with_wires(builder, |builder| {
+                AssignOp::build(builder, &ports["out"], &acc.out(), None).unwrap();
+                AssignOp::build(builder, &acc.clk(), &ports[CLK_PORT], None).unwrap();
+                AssignOp::build(builder, &acc.reset(), &ports[RESET_PORT], None).unwrap();
+            });
+            comp.with_control(builder, |builder| {
+                SeqOp::build_with(builder, |builder| {
+                    EnableOp::build(builder, &update).unwrap();
+                    EnableOp::build(builder, &update).unwrap();
+                })
+                .unwrap();
+            });
+        })?;
+        assert!(module.op().verify());
+
+        let pm = OwnedPassManager::new(&ctx);
+        add_lower_to_hw_passes(&pm)?;
+        pm.run(&module)?;
+
+        let mut verilog = String::new();
+        assert!(sv::export_verilog(&module, &mut verilog).is_success());
+        assert!(verilog.contains("module main"));
+        Ok(())
+    }
+    #[test]
+    fn control_flow_to_hw() -> miette::Result<()> {
+        let ctx = OwnedContext::default();
+        hw::dialect().load(&ctx).unwrap();
+        comb::dialect().load(&ctx).unwrap();
+        seq::dialect().load(&ctx).unwrap();
+        sv::dialect().load(&ctx).unwrap();
+        dialect().load(&ctx).unwrap();
+        mlir::register_canonicalize();
+        register_passes();
+
+        let mut builder = OpBuilder::new(&ctx);
+        let module = Module::create(builder.loc());
+
+        let i8 = IntegerType::new(&ctx, 8);
+        let mut ports = ModulePortInfo::default();
+        ports.add_input("n", &i8);
+        ports.add_output("out", &i8);
+
+        // Sum the odd numbers below `n`, clearing the sum on even ones.
+        ComponentOp::build_with(&mut builder, &module, "main", &ports, |builder, comp, ports| {
+            let i = RegisterOp::build(builder, "i", &i8).unwrap();
+            let acc = RegisterOp::build(builder, "acc", &i8).unwrap();
+            let incr = AddLibOp::build(builder, "incr", 8).unwrap();
+            let sum = AddLibOp::build(builder, "sum", 8).unwrap();
+            let odd = AndLibOp::build(builder, "odd", 8).unwrap();
+            let lt = LtLibOp::build(builder, "lt", 8).unwrap();
+            let eq = EqLibOp::build(builder, "eq", 8).unwrap();
+            let zero = hw::ConstantOp::build(builder, 8, 0).result();
+            let one = hw::ConstantOp::build(builder, 8, 1).result();
+            let high = hw::ConstantOp::build(builder, 1, 1).result();
+
+            let write = |builder: &mut OpBuilder, name: &str, reg: &RegisterOp, value: &Value| {
+                GroupOp::build_with(builder, comp, name, |builder| {
+                    AssignOp::build(builder, &reg.in_(), value, None).unwrap();
+                    AssignOp::build(builder, &reg.write_en(), &high, None).unwrap();
+                    GroupDoneOp::build(builder, &reg.done(), None).unwrap();
+                })
+                .unwrap()
+            };
+            let init_i = write(builder, "init_i", &i, &zero);
+            let init_acc = write(builder, "init_acc", &acc, &zero);
+            let clear = write(builder, "clear", &acc, &zero);
+            let step = write(builder, "step", &i, &incr.out());
+            let add = write(builder, "add", &acc, &sum.out());
+            let below = CombGroupOp::build_with(builder, comp, "below", |builder| {
+                AssignOp::build(builder, &lt.left(), &i.out(), None).unwrap();
+                AssignOp::build(builder, &lt.right(), &ports["n"], None).unwrap();
+            })
+            .unwrap();
+            let is_odd = CombGroupOp::build_with(builder, comp, "is_odd", |builder| {
+                AssignOp::build(builder, &odd.left(), &i.out(), None).unwrap();
+                AssignOp::build(builder, &odd.right(), &one, None).unwrap();
+                AssignOp::build(builder, &eq.left(), &odd.out(), None).unwrap();
+                AssignOp::build(builder, &eq.right(), &one, None).unwrap();
+            })
+            .unwrap();
+            comp.with_wires(builder, |builder| {
+                AssignOp::build(builder, &incr.left(), &i.out(), None).unwrap();
+                AssignOp::build(builder, &incr.right(), &one, None).unwrap();
+                AssignOp::build(builder, &sum.left(), &acc.out(), None).unwrap();
+                AssignOp::build(builder, &sum.right(), &i.out(), None).unwrap();
+                AssignOp::build(builder, &ports["out"], &acc.out(), None).unwrap();
+                for reg in [&i, &acc] {
+                    AssignOp::build(builder, &reg.clk(), &ports[CLK_PORT], None).unwrap();
+                    AssignOp::build(builder, &reg.reset(), &ports[RESET_PORT], None).unwrap();
+                }
+            });
+            comp.with_control(builder, |builder| {
+                SeqOp::build_with(builder, |builder| {
+                    ParOp::build_with(builder, |builder| {
+                        EnableOp::build(builder, &init_i).unwrap();
+                        EnableOp::build(builder, &init_acc).unwrap();
+                    })
+                    .unwrap();
+                    WhileOp::build_with(builder, &lt.out(), Some(&below), |builder| {
+                        SeqOp::build_with(builder, |builder| {
+                            IfOp::build_with_else(
+                                builder,
+                                &eq.out(),
+                                Some(&is_odd),
+                                |builder| {
+                                    EnableOp::build(builder, &add).unwrap();
+                                },
+                                |builder| {
+                                    EnableOp::build(builder, &clear).unwrap();
+                                },
+                            )
+                            .unwrap();
+                            EnableOp::build(builder, &step).unwrap();
+                        })
+                        .unwrap();
+                    })
+                    .unwrap();
+                    // Without an else branch.
+                    IfOp::build_with(builder, &eq.out(), Some(&is_odd), |builder| {
+                        EnableOp::build(builder, &clear).unwrap();
+                    })
+                    .unwrap();
+                })
+                .unwrap();
+            });
+        })?;
+        assert!(module.op().verify());
+
+        let pm = OwnedPassManager::new(&ctx);
+        add_lower_to_hw_passes(&pm)?;
+        pm.run(&module)?;
+
+        let mut verilog = String::new();
+        assert!(sv::export_verilog(&module, &mut verilog).is_success());
+        assert!(verilog.contains("module main"));
+        Ok(())
+    }
+}
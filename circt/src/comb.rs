@@ -5,13 +5,16 @@
 //! This dialect is designed to allow easy analysis and transformation.
 
 use crate::crate_prelude::*;
+use num_derive::FromPrimitive;
 use simple_error::SimpleError;
 use std::borrow::Borrow;
 
+pub mod fold;
+
 define_dialect!(comb);
 
 /// Predicate for a comparison operation.
-#[derive(PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive)]
 pub enum CmpPred {
     Eq,
     Neq,
@@ -25,6 +28,18 @@ pub enum CmpPred {
     Uge,
 }
 
+impl CmpPred {
+    /// The predicate of a `comb.icmp` `predicate` attribute value. The case and wildcard
+    ///  equalities only differ from `eq` and `ne` on X and Z bits, so they map to those.
+    pub fn from_attr_value(value: i64) -> Option<Self> {
+        match value {
+            10 | 12 => Some(CmpPred::Eq),
+            11 | 13 => Some(CmpPred::Neq),
+            value => num::FromPrimitive::from_i64(value),
+        }
+    }
+}
+
 def_operation_many_to_one!(AndOp, "comb.and");
 def_operation_many_to_one!(OrOp, "comb.or");
 def_operation_many_to_one!(XorOp, "comb.xor");
@@ -43,6 +58,7 @@ def_operation_single_result!(ICmpOp, "comb.icmp");
 def_operation_single_result!(MuxOp, "comb.mux");
 def_operation_single_result!(ExtractOp, "comb.extract");
 def_operation_single_result!(ConcatOp, "comb.concat");
+def_operation_single_result!(ParityOp, "comb.parity");
 
 impl ICmpOp {
     /// Create a new comparison operation.
@@ -124,6 +140,16 @@ impl ConcatOp {
     }
 }
 
+impl ParityOp {
+    /// Create the XOR of all bits of `value`.
+    pub fn build(builder: &mut OpBuilder, value: &Value) -> Option<Self> {
+        builder.build_with(|builder, state| {
+            state.add_operand(value);
+            state.add_result(&IntegerType::new(builder.context(), 1));
+        })
+    }
+}
+
 impl ShrUOp {
     pub fn with_sizes(builder: &mut OpBuilder, value: &Value, amount: &Value) -> Option<Self> {
         let amount = trunc_or_zext(builder, amount, &value.ty())?;
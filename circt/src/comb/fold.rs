@@ -0,0 +1,318 @@
+// Copyright (c) 2022-2023 Kamyar Mohajerani
+
+//! Constant folding of comb operations, with exact semantics at any width.
+//! `Bits` is a constant bit vector; `eval` computes the result of a comb operation on `Bits`
+//!  operands, and `fold` does the same on `IntegerAttr`s.
+
+use super::CmpPred;
+use crate::crate_prelude::*;
+use num::{BigInt, BigUint, One, Zero};
+
+/// A constant bit vector.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Bits {
+    width: u32,
+    value: BigUint,
+}
+
+impl Bits {
+    /// The `width` lowest bits of `value`.
+    pub fn new(width: u32, value: BigUint) -> Self {
+        Self {
+            value: value & mask(width),
+            width,
+        }
+    }
+
+    pub fn zero(width: u32) -> Self {
+        Self::new(width, BigUint::zero())
+    }
+
+    /// The two's complement representation of `value` in `width` bits, truncated if needed.
+    pub fn from_signed(width: u32, value: &BigInt) -> Self {
+        let modulus = BigInt::one() << width;
+        let value = ((value % &modulus) + &modulus) % &modulus;
+        Self::new(width, value.to_biguint().unwrap())
+    }
+
+    /// The value of an attribute of integer type.
+    pub fn from_attr(attr: &IntegerAttr) -> Option<Self> {
+        let ty = IntegerType::try_from(attr.ty()).ok()?;
+        Some(Self::new(ty.width(), attr.to_biguint()))
+    }
+
+    /// An attribute of the signless integer type of the same width.
+    pub fn to_attr(&self, ctx: &Context) -> IntegerAttr {
+        IntegerAttr::from_str(&IntegerType::new(ctx, self.width), &self.value.to_string())
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The value as an unsigned integer.
+    pub fn value(&self) -> &BigUint {
+        &self.value
+    }
+
+    /// The value as a two's complement signed integer.
+    pub fn to_signed(&self) -> BigInt {
+        if self.sign_bit() {
+            BigInt::from(self.value.clone()) - (BigInt::one() << self.width)
+        } else {
+            BigInt::from(self.value.clone())
+        }
+    }
+
+    /// The most significant bit; false if the width is zero.
+    pub fn sign_bit(&self) -> bool {
+        self.width > 0 && self.value.bit(self.width as u64 - 1)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.value.is_zero()
+    }
+}
+
+impl From<bool> for Bits {
+    fn from(value: bool) -> Self {
+        Self::new(1, BigUint::from(value as u32))
+    }
+}
+
+pub(crate) fn mask(width: u32) -> BigUint {
+    (BigUint::one() << width) - BigUint::one()
+}
+
+/// Evaluate the two-operand comb operation `name`, e.g. `comb.divs`, on operands of equal width.
+/// Returns `None` for other operations, for operands of different widths, and for division by
+///  zero, which comb leaves undefined.
+/// Shifts by the width or more yield zero, or all sign bits for `comb.shrs`.
+pub fn binary(name: &str, lhs: &Bits, rhs: &Bits) -> Option<Bits> {
+    if lhs.width != rhs.width {
+        return None;
+    }
+    let width = lhs.width;
+    let (l, r) = (&lhs.value, &rhs.value);
+    let amount = u32::try_from(r).ok().filter(|amount| *amount < width);
+    let value = match name {
+        "comb.add" => l + r,
+        "comb.sub" => l + (BigUint::one() << width) - r,
+        "comb.mul" => l * r,
+        "comb.and" => l & r,
+        "comb.or" => l | r,
+        "comb.xor" => l ^ r,
+        "comb.divu" | "comb.modu" | "comb.divs" | "comb.mods" if r.is_zero() => return None,
+        "comb.divu" => l / r,
+        "comb.modu" => l % r,
+        // `BigInt` division truncates towards zero and the remainder takes the sign of the
+        //  dividend, as in comb.
+        "comb.divs" | "comb.mods" => {
+            let (l, r) = (lhs.to_signed(), rhs.to_signed());
+            let value = if name == "comb.divs" { l / r } else { l % r };
+            return Some(Bits::from_signed(width, &value));
+        }
+        "comb.shl" => amount.map_or(BigUint::zero(), |amount| l << amount),
+        "comb.shru" => amount.map_or(BigUint::zero(), |amount| l >> amount),
+        "comb.shrs" => {
+            let amount = amount.unwrap_or(width);
+            if lhs.sign_bit() {
+                (l >> amount) | (mask(width) ^ mask(width - amount))
+            } else {
+                l >> amount
+            }
+        }
+        _ => return None,
+    };
+    Some(Bits::new(width, value))
+}
+
+/// Compare operands of equal width, or return `None` if their widths differ.
+pub fn icmp(pred: CmpPred, lhs: &Bits, rhs: &Bits) -> Option<bool> {
+    if lhs.width != rhs.width {
+        return None;
+    }
+    Some(match pred {
+        CmpPred::Eq => lhs.value == rhs.value,
+        CmpPred::Neq => lhs.value != rhs.value,
+        CmpPred::Slt => lhs.to_signed() < rhs.to_signed(),
+        CmpPred::Sle => lhs.to_signed() <= rhs.to_signed(),
+        CmpPred::Sgt => lhs.to_signed() > rhs.to_signed(),
+        CmpPred::Sge => lhs.to_signed() >= rhs.to_signed(),
+        CmpPred::Ult => lhs.value < rhs.value,
+        CmpPred::Ule => lhs.value <= rhs.value,
+        CmpPred::Ugt => lhs.value > rhs.value,
+        CmpPred::Uge => lhs.value >= rhs.value,
+    })
+}
+
+/// Concatenate `operands`, the first being the most significant.
+pub fn concat<'a>(operands: impl IntoIterator<Item = &'a Bits>) -> Bits {
+    operands.into_iter().fold(Bits::zero(0), |acc, operand| Bits {
+        value: (acc.value << operand.width) | &operand.value,
+        width: acc.width + operand.width,
+    })
+}
+
+/// The `width` bits of `input` starting at `low_bit`.
+pub fn extract(input: &Bits, low_bit: u32, width: u32) -> Bits {
+    Bits::new(width, &input.value >> low_bit)
+}
+
+/// `count` copies of `input`, concatenated.
+pub fn replicate(input: &Bits, count: u32) -> Bits {
+    concat(std::iter::repeat(input).take(count as usize))
+}
+
+/// The XOR of all bits of `input`.
+pub fn parity(input: &Bits) -> Bits {
+    Bits::from(input.value.count_ones() % 2 == 1)
+}
+
+/// Evaluate the comb operation `op` on constant `operands` of the types of its operands.
+/// Returns `None` if `op` is not a comb operation with an integer result, or if the result is
+///  undefined.
+pub fn eval(op: &impl Op, operands: &[Bits]) -> Option<Bits> {
+    let width = IntegerType::try_from(op.result_at(0)?.ty()).ok()?.width();
+    let int_attr = |attr_name: &str| {
+        op.attribute(attr_name)
+            .and_then(|attr| IntegerAttr::try_from(attr).ok())
+            .map(|attr| attr.value())
+    };
+    let name = op.name().to_string();
+    match name.as_str() {
+        "comb.add" | "comb.mul" | "comb.and" | "comb.or" | "comb.xor" => {
+            let (first, rest) = operands.split_first()?;
+            rest.iter().try_fold(first.clone(), |acc, operand| binary(&name, &acc, operand))
+        }
+        "comb.sub" | "comb.divu" | "comb.divs" | "comb.modu" | "comb.mods" | "comb.shl"
+        | "comb.shru" | "comb.shrs" => binary(&name, operands.first()?, operands.get(1)?),
+        "comb.icmp" => {
+            let pred = CmpPred::from_attr_value(int_attr("predicate")?)?;
+            icmp(pred, operands.first()?, operands.get(1)?).map(Bits::from)
+        }
+        "comb.concat" => Some(concat(operands)),
+        "comb.extract" => Some(extract(operands.first()?, int_attr("lowBit")? as u32, width)),
+        "comb.replicate" => {
+            let input = operands.first()?;
+            Some(replicate(input, width / input.width.max(1)))
+        }
+        "comb.mux" => {
+            let (cond, true_value, false_value) =
+                (operands.first()?, operands.get(1)?, operands.get(2)?);
+            Some(
+                if cond.is_zero() {
+                    false_value
+                } else {
+                    true_value
+                }
+                .clone(),
+            )
+        }
+        "comb.parity" => Some(parity(operands.first()?)),
+        _ => None,
+    }
+}
+
+/// Fold `op` with constant `operands`, one of the type of each operand of `op`, into an attribute
+///  of its result type.
+pub fn fold(op: &impl Op, operands: &[IntegerAttr]) -> Option<IntegerAttr> {
+    if operands.len() != op.num_operands()
+        || (operands.iter().zip(op.operands())).any(|(attr, operand)| attr.ty() != operand.ty())
+    {
+        return None;
+    }
+    let operands: Vec<Bits> = operands.iter().map(Bits::from_attr).collect::<Option<_>>()?;
+    eval(op, &operands).map(|result| result.to_attr(&op.context()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hw::{HwModuleOp, ModulePortInfo};
+
+    fn bits(width: u32, value: u32) -> Bits {
+        Bits::new(width, BigUint::from(value))
+    }
+
+    #[test]
+    fn bit_vector_semantics() {
+        let (a, b) = (bits(8, 0xF0), bits(8, 3));
+        assert_eq!(binary("comb.sub", &b, &a), Some(bits(8, 0x13)));
+        assert_eq!(binary("comb.divs", &a, &b), Some(bits(8, 0xFB)));
+        assert_eq!(binary("comb.mods", &a, &b), Some(bits(8, 0xFF)));
+        assert_eq!(binary("comb.mods", &b, &a), Some(bits(8, 3)));
+        assert_eq!(binary("comb.shrs", &a, &b), Some(bits(8, 0xFE)));
+        assert_eq!(binary("comb.shrs", &a, &bits(8, 9)), Some(bits(8, 0xFF)));
+        assert_eq!(binary("comb.shl", &a, &b), Some(bits(8, 0x80)));
+        assert_eq!(binary("comb.divu", &a, &bits(8, 0)), None);
+        assert_eq!(icmp(CmpPred::Slt, &a, &b), Some(true));
+        assert_eq!(icmp(CmpPred::Ult, &a, &b), Some(false));
+        assert_eq!(binary("comb.sub", &bits(4, 3), &a), None);
+        assert_eq!(icmp(CmpPred::Eq, &a, &bits(4, 3)), None);
+        assert_eq!(concat([&b, &a]), bits(16, 0x03F0));
+        assert_eq!(extract(&a, 3, 3), bits(3, 0b110));
+        assert_eq!(replicate(&bits(2, 0b10), 3), bits(6, 0b101010));
+        assert_eq!(parity(&a), Bits::from(false));
+
+        // -2^99 / -1 overflows back to -2^99.
+        let min = Bits::from_signed(100, &-(BigInt::one() << 99u32));
+        let minus_one = Bits::from_signed(100, &-BigInt::one());
+        assert_eq!(binary("comb.divs", &min, &minus_one), Some(min.clone()));
+        assert_eq!(minus_one.value(), &mask(100));
+    }
+
+    #[test]
+    fn fold_ops() -> miette::Result<()> {
+        let ctx = OwnedContext::default();
+        hw::dialect().load(&ctx).unwrap();
+        dialect().load(&ctx).unwrap();
+
+        let mut builder = OpBuilder::new(&ctx);
+        let module = Module::create(builder.loc());
+        let i70 = IntegerType::new(&ctx, 70);
+        let mut ports = ModulePortInfo::default();
+        ports.add_input("a", &i70);
+        ports.add_input("b", &i70);
+        ports.add_output("q", &i70);
+        ports.add_output("lt", &IntegerType::new(&ctx, 1));
+        HwModuleOp::build_with(
+            &mut builder,
+            &module,
+            "wide",
+            &ports,
+            &[],
+            "",
+            |builder, _, inputs, outputs| {
+                let (a, b) = (&inputs["a"], &inputs["b"]);
+                let minus_seven = IntegerAttr::from_str(&i70, &(mask(70) - 6u32).to_string());
+                let two = IntegerAttr::new(&i70, 2);
+
+                let div = DivSOp::build(builder, a, b).unwrap();
+                let q = fold(&div, &[minus_seven.clone(), two.clone()]).unwrap();
+                assert_eq!(Bits::from_attr(&q), Some(Bits::from_signed(70, &BigInt::from(-3))));
+                let rem = ModSOp::build(builder, a, b).unwrap();
+                let r = fold(&rem, &[minus_seven.clone(), two.clone()]).unwrap();
+                assert_eq!(r.to_biguint(), mask(70));
+                assert!(fold(&div, &[two.clone()]).is_none());
+                assert!(fold(&div, &[two.clone(), IntegerAttr::new(&i70, 0)]).is_none());
+                // Attributes must have the types of the operands.
+                let sub = SubOp::build(builder, a, b).unwrap();
+                let i8 = IntegerType::new(builder.context(), 8);
+                assert!(fold(&sub, &[IntegerAttr::new(&i8, 1), two.clone()]).is_none());
+
+                let lt = ICmpOp::build(builder, CmpPred::Slt, a, b).unwrap();
+                let folded = fold(&lt, &[minus_seven.clone(), two.clone()]).unwrap();
+                assert_eq!(folded.to_biguint(), BigUint::one());
+
+                let ext = ExtractOp::with_sizes(builder, a, 66, 4).unwrap();
+                let ext = fold(&ext, &[minus_seven]).unwrap();
+                assert_eq!(Bits::from_attr(&ext), Some(bits(4, 0xF)));
+
+                outputs.insert("q".to_string(), div.result());
+                outputs.insert("lt".to_string(), lt.result());
+            },
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,7 @@
+// Copyright (c) 2022-2023 Kamyar Mohajerani
+
+//! The Debug dialect tracks source-level variables and scopes through lowering, for debug
+//!  information emission.
+//! See https://circt.llvm.org/docs/Dialects/Debug/ for more details.
+
+define_dialect!(debug);
@@ -0,0 +1,373 @@
+// Copyright (c) 2022-2023 Kamyar Mohajerani
+
+//! Combinational equivalence checking of HW modules, using a built-in BDD package rather than an
+//!  external SAT or SMT solver. Both modules are lowered by `aig::bitblast`, and their outputs
+//!  are compared for all values of their inputs. Registers are cut points matched by name: their
+//!  states are free variables, and their next states are compared like outputs.
+
+use crate::crate_prelude::*;
+use aig::{Aig, Lit, Node, Port, Register};
+use num::{BigUint, One, Zero};
+use std::collections::{BTreeMap, HashMap};
+
+/// The largest number of BDD nodes before giving up.
+const MAX_BDD_NODES: usize = 1 << 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum BddOp {
+    And,
+    Xor,
+}
+
+/// A reduced ordered BDD manager. Nodes 0 and 1 are the constants false and true.
+struct Bdd {
+    /// The variable and the low and high children of each node.
+    nodes: Vec<(u32, usize, usize)>,
+    unique: HashMap<(u32, usize, usize), usize>,
+    cache: HashMap<(BddOp, usize, usize), usize>,
+}
+
+impl Bdd {
+    const FALSE: usize = 0;
+    const TRUE: usize = 1;
+
+    fn new() -> Self {
+        Self {
+            nodes: vec![(u32::MAX, 0, 0), (u32::MAX, 1, 1)],
+            unique: HashMap::new(),
+            cache: HashMap::new(),
+        }
+    }
+
+    fn node(&mut self, var: u32, low: usize, high: usize) -> Result<usize, Error> {
+        if low == high {
+            return Ok(low);
+        }
+        if let Some(node) = self.unique.get(&(var, low, high)) {
+            return Ok(*node);
+        }
+        if self.nodes.len() >= MAX_BDD_NODES {
+            return Err(Error::simple(
+                "The BDDs grew too large for equivalence checking".to_string(),
+            ));
+        }
+        self.nodes.push((var, low, high));
+        self.unique.insert((var, low, high), self.nodes.len() - 1);
+        Ok(self.nodes.len() - 1)
+    }
+
+    fn var(&mut self, var: u32) -> Result<usize, Error> {
+        self.node(var, Self::FALSE, Self::TRUE)
+    }
+
+    fn apply(&mut self, op: BddOp, a: usize, b: usize) -> Result<usize, Error> {
+        let (a, b) = (a.min(b), a.max(b));
+        match op {
+            BddOp::And if a == Self::FALSE => return Ok(Self::FALSE),
+            BddOp::And if a == Self::TRUE || a == b => return Ok(b),
+            BddOp::Xor if a == b => return Ok(Self::FALSE),
+            BddOp::Xor if a == Self::FALSE => return Ok(b),
+            _ => {}
+        }
+        if let Some(node) = self.cache.get(&(op, a, b)) {
+            return Ok(*node);
+        }
+        let ((var_a, low_a, high_a), (var_b, low_b, high_b)) = (self.nodes[a], self.nodes[b]);
+        let var = var_a.min(var_b);
+        let (low_a, high_a) = if var_a == var {
+            (low_a, high_a)
+        } else {
+            (a, a)
+        };
+        let (low_b, high_b) = if var_b == var {
+            (low_b, high_b)
+        } else {
+            (b, b)
+        };
+        let low = self.apply(op, low_a, low_b)?;
+        let high = self.apply(op, high_a, high_b)?;
+        let node = self.node(var, low, high)?;
+        self.cache.insert((op, a, b), node);
+        Ok(node)
+    }
+
+    fn not(&mut self, a: usize) -> Result<usize, Error> {
+        self.apply(BddOp::Xor, a, Self::TRUE)
+    }
+
+    /// A satisfying assignment of `a` as the values of the variables on one path to true, or
+    ///  `None` if `a` is false.
+    fn satisfy(&self, mut a: usize) -> Option<Vec<(u32, bool)>> {
+        if a == Self::FALSE {
+            return None;
+        }
+        let mut assignment = vec![];
+        while a != Self::TRUE {
+            let (var, low, high) = self.nodes[a];
+            let value = low == Self::FALSE;
+            assignment.push((var, value));
+            a = if value { high } else { low };
+        }
+        Some(assignment)
+    }
+
+    /// The BDD of every node of `aig`, given the variables of its input and register bits.
+    fn build<'a>(
+        &mut self,
+        aig: &'a Aig,
+        vars: &HashMap<(bool, &'a str, usize), u32>,
+    ) -> Result<Vec<usize>, Error> {
+        let mut bdds = vec![Self::FALSE; aig.nodes().len()];
+        let inputs = aig.inputs().iter().map(|port| (false, &port.name, &port.bits));
+        let registers = aig.registers().iter().map(|reg| (true, &reg.name, &reg.bits));
+        for (is_register, name, bits) in inputs.chain(registers) {
+            for (i, bit) in bits.iter().enumerate() {
+                bdds[bit.node()] = self.var(vars[&(is_register, name.as_str(), i)])?;
+            }
+        }
+        for (index, node) in aig.nodes().iter().enumerate() {
+            if let Node::And(a, b) = node {
+                let (a, b) = (self.lit(&bdds, *a)?, self.lit(&bdds, *b)?);
+                bdds[index] = self.apply(BddOp::And, a, b)?;
+            }
+        }
+        Ok(bdds)
+    }
+
+    fn lit(&mut self, bdds: &[usize], lit: Lit) -> Result<usize, Error> {
+        if lit.is_negated() {
+            self.not(bdds[lit.node()])
+        } else {
+            Ok(bdds[lit.node()])
+        }
+    }
+}
+
+/// Input values for which two modules differ.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Counterexample {
+    /// The values of the input ports, by name.
+    pub inputs: BTreeMap<String, BigUint>,
+    /// The states of the registers, by name.
+    pub registers: BTreeMap<String, BigUint>,
+    /// The output ports, and the registers whose next states, that differ for these values.
+    pub mismatches: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Equivalence {
+    Equivalent,
+    Different(Counterexample),
+}
+
+fn port_words(ports: &[Port]) -> impl Iterator<Item = (&String, &Vec<Lit>)> {
+    ports.iter().map(|port| (&port.name, &port.bits))
+}
+
+fn register_words(registers: &[Register]) -> impl Iterator<Item = (&String, &Vec<Lit>)> {
+    registers.iter().map(|reg| (&reg.name, &reg.bits))
+}
+
+/// The widths of the words `ports`, by name, which must be unique.
+fn widths<'a>(
+    kind: &str,
+    ports: impl Iterator<Item = (&'a String, &'a Vec<Lit>)>,
+) -> Result<BTreeMap<&'a str, usize>, Error> {
+    let mut widths = BTreeMap::new();
+    for (name, bits) in ports {
+        if widths.insert(name.as_str(), bits.len()).is_some() {
+            return Err(Error::simple(format!("Duplicate {} `{}`", kind, name)));
+        }
+    }
+    Ok(widths)
+}
+
+fn match_words<'a>(
+    kind: &str,
+    a: impl Iterator<Item = (&'a String, &'a Vec<Lit>)>,
+    b: impl Iterator<Item = (&'a String, &'a Vec<Lit>)>,
+) -> Result<BTreeMap<&'a str, usize>, Error> {
+    let (a, b) = (widths(kind, a)?, widths(kind, b)?);
+    match a.iter().zip(&b).find(|(a, b)| a != b) {
+        None if a.len() == b.len() => Ok(a),
+        _ => Err(Error::simple(format!(
+            "The modules have different {}s: {:?} and {:?}",
+            kind, a, b
+        ))),
+    }
+}
+
+/// Check whether the modules `a` and `b` compute the same outputs, matched by name, for all
+///  values of their inputs, also matched by name. Registers are matched by their `name` attribute.
+pub fn check_combinational(a: &hw::HwModuleOp, b: &hw::HwModuleOp) -> Result<Equivalence, Error> {
+    let aigs = [aig::bitblast(a)?, aig::bitblast(b)?];
+    let [inputs_a, inputs_b] = [0, 1].map(|i| port_words(aigs[i].inputs()));
+    let input_widths = match_words("input", inputs_a, inputs_b)?;
+    let [outputs_a, outputs_b] = [0, 1].map(|i| port_words(aigs[i].outputs()));
+    match_words("output", outputs_a, outputs_b)?;
+    let [registers_a, registers_b] = [0, 1].map(|i| register_words(aigs[i].registers()));
+    let register_widths = match_words("register", registers_a, registers_b)?;
+
+    // Interleaving the bits of all words, least significant first, keeps the BDDs of e.g.
+    //  adders and comparators small.
+    let sources: Vec<(bool, &str, usize)> = input_widths
+        .iter()
+        .map(|(name, width)| (false, *name, *width))
+        .chain(register_widths.iter().map(|(name, width)| (true, *name, *width)))
+        .collect();
+    let max_width = sources.iter().map(|(_, _, width)| *width).max().unwrap_or(0);
+    let mut vars = HashMap::new();
+    for bit in 0..max_width {
+        for (is_register, name, width) in &sources {
+            if bit < *width {
+                vars.insert((*is_register, *name, bit), vars.len() as u32);
+            }
+        }
+    }
+
+    let mut bdd = Bdd::new();
+    let bdds = [bdd.build(&aigs[0], &vars)?, bdd.build(&aigs[1], &vars)?];
+    // The outputs, then the next states of the registers, each sorted by name.
+    let compared = |aig: &Aig| -> Vec<Vec<Lit>> {
+        let outputs: BTreeMap<_, _> = port_words(aig.outputs()).collect();
+        let next: BTreeMap<_, _> =
+            aig.registers().iter().map(|reg| (&reg.name, &reg.next)).collect();
+        outputs.into_values().chain(next.into_values()).cloned().collect()
+    };
+    let (words_a, words_b) = (compared(&aigs[0]), compared(&aigs[1]));
+    let mut assignment = None;
+    'words: for (bits_a, bits_b) in words_a.iter().zip(&words_b) {
+        for (bit_a, bit_b) in bits_a.iter().zip(bits_b) {
+            let (x, y) = (bdd.lit(&bdds[0], *bit_a)?, bdd.lit(&bdds[1], *bit_b)?);
+            let miter = bdd.apply(BddOp::Xor, x, y)?;
+            if let Some(values) = bdd.satisfy(miter) {
+                assignment = Some(values);
+                break 'words;
+            }
+        }
+    }
+    let Some(assignment) = assignment else {
+        return Ok(Equivalence::Equivalent);
+    };
+
+    let values: HashMap<u32, bool> = assignment.into_iter().collect();
+    let mut counterexample = Counterexample::default();
+    for (is_register, name, _) in &sources {
+        let words = if *is_register {
+            &mut counterexample.registers
+        } else {
+            &mut counterexample.inputs
+        };
+        words.insert(name.to_string(), BigUint::zero());
+    }
+    for ((is_register, name, bit), var) in &vars {
+        if values.get(var).copied().unwrap_or(false) {
+            let words = if *is_register {
+                &mut counterexample.registers
+            } else {
+                &mut counterexample.inputs
+            };
+            *words.get_mut(*name).unwrap() |= BigUint::one() << *bit;
+        }
+    }
+    let results = aigs.iter().map(|aig| -> Vec<(String, BigUint)> {
+        let inputs: Vec<BigUint> =
+            aig.inputs().iter().map(|port| counterexample.inputs[&port.name].clone()).collect();
+        let states: Vec<BigUint> =
+            aig.registers().iter().map(|reg| counterexample.registers[&reg.name].clone()).collect();
+        let (outputs, next) = aig.eval(&inputs, &states);
+        let outputs: BTreeMap<_, _> =
+            aig.outputs().iter().map(|port| &port.name).zip(outputs).collect();
+        let next: BTreeMap<_, _> = aig.registers().iter().map(|reg| &reg.name).zip(next).collect();
+        outputs.into_iter().chain(next).map(|(name, value)| (name.clone(), value)).collect()
+    });
+    let results: Vec<_> = results.collect();
+    counterexample.mismatches = results[0]
+        .iter()
+        .zip(&results[1])
+        .filter(|(a, b)| a != b)
+        .map(|((name, _), _)| name.clone())
+        .collect();
+    Ok(Equivalence::Different(counterexample))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hw::{HwModuleOp, ModulePortInfo};
+
+    #[test]
+    fn bdd_operations() -> Result<(), Error> {
+        let mut bdd = Bdd::new();
+        let (x, y) = (bdd.var(0)?, bdd.var(1)?);
+        let (not_x, not_y) = (bdd.not(x)?, bdd.not(y)?);
+        // x | y == !(!x & !y) == x ^ y ^ (x & y)
+        let and = bdd.apply(BddOp::And, x, y)?;
+        let neither = bdd.apply(BddOp::And, not_x, not_y)?;
+        let or = bdd.not(neither)?;
+        let xor = bdd.apply(BddOp::Xor, x, y)?;
+        assert_eq!(bdd.apply(BddOp::Xor, xor, and)?, or);
+        assert_eq!(bdd.apply(BddOp::Xor, x, x)?, Bdd::FALSE);
+        assert_eq!(bdd.satisfy(Bdd::FALSE), None);
+        assert_eq!(bdd.satisfy(and), Some(vec![(0, true), (1, true)]));
+        assert_eq!(bdd.satisfy(xor), Some(vec![(0, false), (1, true)]));
+        Ok(())
+    }
+
+    #[test]
+    fn adders() -> miette::Result<()> {
+        let ctx = OwnedContext::default();
+        hw::dialect().load(&ctx).unwrap();
+        comb::dialect().load(&ctx).unwrap();
+        seq::dialect().load(&ctx).unwrap();
+
+        let mut builder = OpBuilder::new(&ctx);
+        let module = Module::create(builder.loc());
+        let i1 = IntegerType::new(&ctx, 1);
+        let i8 = IntegerType::new(&ctx, 8);
+        let mut ports = ModulePortInfo::default();
+        ports.add_input("clk", &i1);
+        ports.add_input("a", &i8);
+        ports.add_input("b", &i8);
+        ports.add_output("sum", &i8);
+
+        // `sum = acc + a + b`, where `acc` is registered, with the additions in either order or
+        //  off by one.
+        let mut build = |name: &str, swap: bool, offset: u64| {
+            HwModuleOp::build_with(
+                &mut builder,
+                &module,
+                name,
+                &ports,
+                &[],
+                "",
+                |builder, _, inputs, outputs| {
+                    let (a, b) = (inputs["a"], inputs["b"]);
+                    let zero = hw::ConstantOp::build(builder, 8, 0).result();
+                    let acc =
+                        seq::CompRegOp::build(builder, "acc", &zero, &inputs["clk"], None, None)
+                            .unwrap();
+                    let (x, y) = if swap { (b, a) } else { (a, b) };
+                    let ab = comb::AddOp::build(builder, &x, &y).unwrap().result();
+                    let offset = hw::ConstantOp::build(builder, 8, offset as i64).result();
+                    let ab = comb::AddOp::build(builder, &ab, &offset).unwrap().result();
+                    let sum = comb::AddOp::build(builder, &acc.output(), &ab).unwrap().result();
+                    acc.set_input(&sum);
+                    outputs.insert("sum".to_string(), sum);
+                },
+            )
+        };
+        let a = build("a", false, 0)?;
+        let b = build("b", true, 0)?;
+        let c = build("c", true, 1)?;
+
+        assert_eq!(check_combinational(&a, &b)?, Equivalence::Equivalent);
+        let Equivalence::Different(counterexample) = check_combinational(&a, &c)? else {
+            panic!("`a` and `c` differ");
+        };
+        assert_eq!(counterexample.mismatches, ["sum", "acc"]);
+        assert_eq!(counterexample.inputs.len(), 3);
+        assert_eq!(counterexample.registers.len(), 1);
+        Ok(())
+    }
+}
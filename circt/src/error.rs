@@ -10,6 +10,8 @@ pub enum Error {
     SimpleError(#[from] SimpleError),
     #[error("Option was none!")]
     IsNone,
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
 }
 
 impl Error {
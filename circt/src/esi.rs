@@ -1,4 +1,15 @@
+// Copyright (c) 2022-2023 Kamyar Mohajerani
+
+//! The Elastic Silicon Interconnect (ESI) dialect provides latency-insensitive channels and
+//!  services connecting them across the design hierarchy.
+//! See https://circt.llvm.org/docs/Dialects/ESI/ for more details.
+
+use crate::crate_prelude::*;
 use circt_sys::*;
+use hw::InnerRefAttr;
+
+pub mod manifest;
+pub use manifest::{Manifest, MANIFEST_FILE_NAME};
 
 define_dialect!(esi);
 
@@ -9,3 +20,484 @@ pub fn register_passes() {
 pub fn register_translations() {
     unsafe { registerESITranslations() }
 }
+
+/// Add the passes lowering ESI channels, ports and services to HW to `pm`.
+/// ESI passes must have been registered with `register_passes`.
+pub fn add_lower_to_hw_passes(pm: &PassManager) -> Result<(), Error> {
+    #[rustfmt::skip]
+    pm
+        .parse_pass("esi-connect-services")?
+        .parse_pass("lower-esi-to-physical")?
+        .parse_pass("lower-esi-ports")?
+        .parse_pass("lower-esi-to-hw")?;
+    Ok(())
+}
+
+/// Lower all ESI constructs in `module` to HW.
+pub fn lower_to_hw(ctx: &Context, module: &Module) -> Result<(), Error> {
+    let pm = OwnedPassManager::new(ctx);
+    add_lower_to_hw_passes(&pm)?;
+    pm.run(module)
+}
+
+def_type!(ChannelType; doc = "A latency-insensitive channel carrying values of an inner type.");
+
+impl ChannelType {
+    pub fn new(inner: &impl Ty) -> Self {
+        Self::try_from_raw(unsafe { circtESIChannelTypeGet(inner.raw()) }).unwrap()
+    }
+
+    /// Type of the values carried by the channel.
+    pub fn inner(&self) -> Type {
+        Type::try_from_raw(unsafe { circtESIChannelGetInner(self.raw()) }).unwrap()
+    }
+}
+
+impl TyIsa for ChannelType {
+    fn isa(ty: &impl HasRaw<RawType = MlirType>) -> bool {
+        unsafe { circtESITypeIsAChannelType(ty.raw()) }
+    }
+}
+
+def_operation!(WrapValidReadyOp, "esi.wrap.vr"; doc = "Wrap a value and a valid signal into a channel.");
+
+impl WrapValidReadyOp {
+    pub fn build(builder: &mut OpBuilder, data: &Value, valid: &Value) -> Option<Self> {
+        builder.build_with(|builder, state| {
+            state.add_operand(data);
+            state.add_operand(valid);
+            state.add_result(&ChannelType::new(&data.ty()));
+            state.add_result(&IntegerType::new(builder.context(), 1));
+        })
+    }
+
+    pub fn channel(&self) -> Value {
+        self.result_at(0).unwrap()
+    }
+
+    pub fn ready(&self) -> Value {
+        self.result_at(1).unwrap()
+    }
+}
+
+def_operation!(UnwrapValidReadyOp, "esi.unwrap.vr"; doc = "Unwrap a channel into its value and valid signal.");
+
+impl UnwrapValidReadyOp {
+    pub fn build(builder: &mut OpBuilder, channel: &Value, ready: &Value) -> Result<Self, Error> {
+        let ty = ChannelType::try_from(channel.ty())?;
+        builder
+            .build_with(|builder, state| {
+                state.add_operand(channel);
+                state.add_operand(ready);
+                state.add_result(&ty.inner());
+                state.add_result(&IntegerType::new(builder.context(), 1));
+            })
+            .ok_or(Error::simple("OpBuilder failed"))
+    }
+
+    pub fn data(&self) -> Value {
+        self.result_at(0).unwrap()
+    }
+
+    pub fn valid(&self) -> Value {
+        self.result_at(1).unwrap()
+    }
+}
+
+def_operation_single_result!(ChannelBufferOp, "esi.buffer");
+
+impl ChannelBufferOp {
+    /// Buffer `input` with `stages` pipeline stages, or as many as the lowering decides if `None`.
+    pub fn build(
+        builder: &mut OpBuilder,
+        clk: &Value,
+        rst: &Value,
+        input: &Value,
+        stages: Option<usize>,
+        name: Option<&str>,
+    ) -> Option<Self> {
+        builder.build_with(|builder, state| {
+            let ctx = builder.context();
+            state.add_operand(clk);
+            state.add_operand(rst);
+            state.add_operand(input);
+            state.add_result(&input.ty());
+            if let Some(stages) = stages {
+                state.add_attribute(
+                    "stages",
+                    &IntegerAttr::new(&IntegerType::new(ctx, 64), stages as i64),
+                );
+            }
+            if let Some(name) = name {
+                state.add_attribute("name", &StringAttr::new(ctx, name));
+            }
+        })
+    }
+}
+
+def_operation_single_result!(PipelineStageOp, "esi.stage");
+
+impl PipelineStageOp {
+    /// A single register stage on a channel, which the lowering retimes freely.
+    pub fn build(builder: &mut OpBuilder, clk: &Value, rst: &Value, input: &Value) -> Option<Self> {
+        builder.build_with(|_, state| {
+            state.add_operand(clk);
+            state.add_operand(rst);
+            state.add_operand(input);
+            state.add_result(&input.ty());
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ServicePortDirection {
+    /// Clients send to the service.
+    ToServer,
+    /// The service sends to clients.
+    ToClient,
+}
+
+impl ServicePortDirection {
+    pub fn op_name(&self) -> &'static str {
+        match self {
+            ServicePortDirection::ToServer => "esi.service.to_server",
+            ServicePortDirection::ToClient => "esi.service.to_client",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ServicePort {
+    pub name: String,
+    pub direction: ServicePortDirection,
+    pub ty: ChannelType,
+}
+
+impl ServicePort {
+    pub fn to_server(name: &str, ty: ChannelType) -> Self {
+        Self {
+            name: name.to_string(),
+            direction: ServicePortDirection::ToServer,
+            ty,
+        }
+    }
+
+    pub fn to_client(name: &str, ty: ChannelType) -> Self {
+        Self {
+            name: name.to_string(),
+            direction: ServicePortDirection::ToClient,
+            ty,
+        }
+    }
+}
+
+def_operation!(ServiceDeclOp, "esi.service.decl"; doc = "Declares a service and the channel ports it offers to clients.");
+
+impl SingleRegionOp for ServiceDeclOp {}
+
+impl SingleBlockOp for ServiceDeclOp {}
+
+impl ServiceDeclOp {
+    /// Declare the service `name` with `ports` at the end of `module`.
+    pub fn build(
+        builder: &mut OpBuilder,
+        module: &Module,
+        name: &str,
+        ports: &[ServicePort],
+    ) -> Result<Self, Error> {
+        builder.set_insertion_point(Some(InsertPoint::BlockEnd(module.body())));
+        let region = Region::default();
+        let block = Block::default();
+        let ctx = builder.context();
+        let decl: Self = builder
+            .build_with(|_, state| {
+                region.append_block(&block);
+                state.add_region(&region);
+                state.add_attribute(SymbolTable::symbol_attr_name(), &StringAttr::new(ctx, name));
+            })
+            .ok_or(Error::simple("OpBuilder failed"))?;
+        let body = decl.first_block().ok_or(Error::IsNone)?;
+        for port in ports {
+            let mut state = OperationState::new(port.direction.op_name(), builder.loc());
+            state.add_attribute("inner_sym", &StringAttr::new(ctx, &port.name));
+            state.add_attribute("type", &TypeAttr::new(&port.ty));
+            let op = Operation::create(&mut state).ok_or(Error::simple("OpBuilder failed"))?;
+            body.append_op(&op);
+        }
+        Ok(decl)
+    }
+
+    pub fn sym_name(&self) -> String {
+        self.attribute(SymbolTable::symbol_attr_name())
+            .and_then(|attr| StringAttr::try_from(attr).ok())
+            .map(|attr| attr.get_value())
+            .unwrap()
+    }
+
+    /// The ports declared by the service.
+    pub fn ports(&self) -> Vec<ServicePort> {
+        let Some(body) = self.first_block() else {
+            return vec![];
+        };
+        body.operations()
+            .filter_map(|op| {
+                let direction = match op.name().to_string().as_str() {
+                    "esi.service.to_server" => ServicePortDirection::ToServer,
+                    "esi.service.to_client" => ServicePortDirection::ToClient,
+                    _ => return None,
+                };
+                let name = StringAttr::try_from(op.attribute("inner_sym")?).ok()?.get_value();
+                let ty = TypeAttr::try_from(op.attribute("type")?).ok()?.ty();
+                Some(ServicePort {
+                    name,
+                    direction,
+                    ty: ChannelType::try_from(ty).ok()?,
+                })
+            })
+            .collect()
+    }
+
+    /// The port `name` of the service.
+    pub fn port(&self, name: &str) -> Option<ServicePort> {
+        self.ports().into_iter().find(|p| p.name == name)
+    }
+
+    fn port_ref(&self, port: &str) -> InnerRefAttr {
+        let ctx = self.context();
+        InnerRefAttr::new(&StringAttr::new(&ctx, &self.sym_name()), &StringAttr::new(&ctx, port))
+            .unwrap()
+    }
+}
+
+fn client_name_path(ctx: &Context, client_name: &[&str]) -> ArrayAttr {
+    ArrayAttr::new::<StringAttr>(ctx, client_name.iter().map(|n| StringAttr::new(ctx, n)))
+}
+
+def_operation!(RequestToServerConnectionOp, "esi.service.req.to_server"; doc = "Connect a channel to a `to_server` port of a service.");
+
+impl RequestToServerConnectionOp {
+    /// Send `channel` to the `to_server` port `port` of `service`. The client is identified by
+    ///  `client_name` in the service implementation.
+    pub fn build(
+        builder: &mut OpBuilder,
+        service: &ServiceDeclOp,
+        port: &str,
+        client_name: &[&str],
+        channel: &Value,
+    ) -> Result<Self, Error> {
+        match service.port(port) {
+            Some(p) if p.direction == ServicePortDirection::ToServer => {}
+            _ => {
+                return Err(Error::simple(format!(
+                    "Service `{}` has no to_server port `{}`",
+                    service.sym_name(),
+                    port
+                )))
+            }
+        }
+        builder
+            .build_with(|builder, state| {
+                state.add_operand(channel);
+                state.add_attribute("servicePort", &service.port_ref(port));
+                state.add_attribute(
+                    "clientNamePath",
+                    &client_name_path(builder.context(), client_name),
+                );
+            })
+            .ok_or(Error::simple("OpBuilder failed"))
+    }
+}
+
+def_operation_single_result!(RequestToClientConnectionOp, "esi.service.req.to_client");
+
+impl RequestToClientConnectionOp {
+    /// Request a channel from the `to_client` port `port` of `service`.
+    pub fn build(
+        builder: &mut OpBuilder,
+        service: &ServiceDeclOp,
+        port: &str,
+        client_name: &[&str],
+    ) -> Result<Self, Error> {
+        let ty = match service.port(port) {
+            Some(p) if p.direction == ServicePortDirection::ToClient => p.ty,
+            _ => {
+                return Err(Error::simple(format!(
+                    "Service `{}` has no to_client port `{}`",
+                    service.sym_name(),
+                    port
+                )))
+            }
+        };
+        builder
+            .build_with(|builder, state| {
+                state.add_attribute("servicePort", &service.port_ref(port));
+                state.add_attribute(
+                    "clientNamePath",
+                    &client_name_path(builder.context(), client_name),
+                );
+                state.add_result(&ty);
+            })
+            .ok_or(Error::simple("OpBuilder failed"))
+    }
+}
+
+def_operation!(ServiceInstanceOp, "esi.service.instance"; doc = "Instantiates an implementation of a service, which serves all requests below it in the hierarchy.");
+
+impl ServiceInstanceOp {
+    /// Instantiate the implementation `impl_type`, e.g. `"cosim"`, of `service`, or of all
+    ///  services if `None`.
+    pub fn build(
+        builder: &mut OpBuilder,
+        service: Option<&ServiceDeclOp>,
+        impl_type: &str,
+        inputs: impl IntoIterator<Item = impl std::borrow::Borrow<Value>>,
+    ) -> Option<Self> {
+        builder.build_with(|builder, state| {
+            let ctx = builder.context();
+            if let Some(service) = service {
+                state.add_attribute("serviceSymbol", &SymbolRefAttr::new(ctx, &service.sym_name()));
+            }
+            state.add_attribute("impl_type", &StringAttr::new(ctx, impl_type));
+            state.add_operands::<Value>(inputs);
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hw::{HwModuleOp, ModulePortInfo};
+
+    fn load_dialects(ctx: &Context) {
+        hw::dialect().load(ctx).unwrap();
+        comb::dialect().load(ctx).unwrap();
+        seq::dialect().load(ctx).unwrap();
+        sv::dialect().load(ctx).unwrap();
+        dialect().load(ctx).unwrap();
+        register_passes();
+    }
+
+    #[test]
+    fn channels_lower_to_hw() -> miette::Result<()> {
+        let ctx = OwnedContext::default();
+        load_dialects(&ctx);
+        let mut builder = OpBuilder::new(&ctx);
+        let module = Module::create(builder.loc());
+
+        let i1 = IntegerType::new(&ctx, 1);
+        let i8 = IntegerType::new(&ctx, 8);
+        let mut ports = ModulePortInfo::default();
+        ports.add_input("clk", &i1);
+        ports.add_input("rst", &i1);
+        ports.add_input("data", &i8);
+        ports.add_input("valid", &i1);
+        ports.add_input("ready", &i1);
+        ports.add_output("data_out", &i8);
+        ports.add_output("valid_out", &i1);
+        ports.add_output("ready_out", &i1);
+
+        HwModuleOp::build_with(
+            &mut builder,
+            &module,
+            "passthrough",
+            &ports,
+            &[],
+            "",
+            |builder, _, inputs, outputs| {
+                let wrap =
+                    WrapValidReadyOp::build(builder, &inputs["data"], &inputs["valid"]).unwrap();
+                assert_eq!(
+                    ChannelType::try_from(wrap.channel().ty()).unwrap().inner(),
+                    i8.as_type()
+                );
+                let buffered = ChannelBufferOp::build(
+                    builder,
+                    &inputs["clk"],
+                    &inputs["rst"],
+                    &wrap.channel(),
+                    Some(2),
+                    Some("buf"),
+                )
+                .unwrap();
+                let staged = PipelineStageOp::build(
+                    builder,
+                    &inputs["clk"],
+                    &inputs["rst"],
+                    &buffered.result(),
+                )
+                .unwrap();
+                let unwrap =
+                    UnwrapValidReadyOp::build(builder, &staged.result(), &inputs["ready"]).unwrap();
+                outputs.insert("data_out".to_string(), unwrap.data());
+                outputs.insert("valid_out".to_string(), unwrap.valid());
+                outputs.insert("ready_out".to_string(), wrap.ready());
+            },
+        )?;
+        assert!(module.op().verify());
+
+        lower_to_hw(&ctx, &module)?;
+        let mut verilog = String::new();
+        assert!(sv::export_verilog(&module, &mut verilog).is_success());
+        assert!(verilog.contains("module passthrough"));
+        Ok(())
+    }
+
+    #[test]
+    fn service_requests() -> miette::Result<()> {
+        let ctx = OwnedContext::default();
+        load_dialects(&ctx);
+        let mut builder = OpBuilder::new(&ctx);
+        let module = Module::create(builder.loc());
+
+        let i1 = IntegerType::new(&ctx, 1);
+        let i8 = IntegerType::new(&ctx, 8);
+        let chan = ChannelType::new(&i8);
+        let service = ServiceDeclOp::build(
+            &mut builder,
+            &module,
+            "HostComms",
+            &[
+                ServicePort::to_server("Send", chan),
+                ServicePort::to_client("Recv", chan),
+            ],
+        )?;
+        assert_eq!(service.ports().len(), 2);
+
+        let mut ports = ModulePortInfo::default();
+        ports.add_input("clk", &i1);
+        ports.add_input("rst", &i1);
+        HwModuleOp::build_with(
+            &mut builder,
+            &module,
+            "top",
+            &ports,
+            &[],
+            "",
+            |builder, _, inputs, _| {
+                let recv =
+                    RequestToClientConnectionOp::build(builder, &service, "Recv", &["loopback"])
+                        .unwrap();
+                RequestToServerConnectionOp::build(
+                    builder,
+                    &service,
+                    "Send",
+                    &["loopback"],
+                    &recv.result(),
+                )
+                .unwrap();
+                assert!(
+                    RequestToClientConnectionOp::build(builder, &service, "Send", &["x"]).is_err()
+                );
+                ServiceInstanceOp::build(
+                    builder,
+                    Some(&service),
+                    "cosim",
+                    [inputs["clk"], inputs["rst"]],
+                )
+                .unwrap();
+            },
+        )?;
+        assert!(module.op().verify());
+        Ok(())
+    }
+}
@@ -0,0 +1,328 @@
+// Copyright (c) 2022-2023 Kamyar Mohajerani
+
+//! The system manifest emitted by ESI, describing the services of a design, their ports and
+//!  clients, and the types of the channels between them.
+
+use crate::crate_prelude::*;
+use hw::OutputFileAttr;
+use serde::{Deserialize, Serialize};
+
+use super::ServicePortDirection;
+
+/// Name of the file ESI emits the manifest to.
+pub const MANIFEST_FILE_NAME: &str = "services.json";
+
+/// Run ESI's collateral emission on `module` and return the JSON manifest.
+/// `tops` are the names of the top-level modules to describe. Service requests are connected
+///  first, which is a no-op if `module` has already been lowered. ESI passes must have been
+///  registered with `esi::register_passes`.
+/// ESI only emits the manifest as collateral, in an `sv.verbatim` bound to `MANIFEST_FILE_NAME`;
+///  the translations registered by `esi::register_translations` export Cap'n Proto schemas, not
+///  the manifest, so the verbatim is read back from `module`.
+pub fn emit_manifest(ctx: &Context, module: &Module, tops: &[&str]) -> Result<String, Error> {
+    let pm = OwnedPassManager::new(ctx);
+    pm.parse_pass("esi-connect-services")?
+        .parse_pass(&format!("esi-emit-collateral{{tops={}}}", tops.join(",")))?;
+    pm.run(module)?;
+
+    module
+        .body()
+        .operations()
+        .filter(|op| op.name().to_string() == "sv.verbatim")
+        .filter(|op| {
+            op.attribute("output_file")
+                .and_then(|attr| OutputFileAttr::try_from(attr).ok())
+                .map_or(false, |file| file.file_name().ends_with(MANIFEST_FILE_NAME))
+        })
+        .filter_map(|op| StringAttr::try_from(op.attribute("format_string")?).ok())
+        .last()
+        .map(|text| text.get_value())
+        .ok_or(Error::simple("ESI did not emit a manifest"))
+}
+
+/// Emit the manifest of `module` and parse it.
+pub fn manifest(ctx: &Context, module: &Module, tops: &[&str]) -> Result<Manifest, Error> {
+    Manifest::from_json(&emit_manifest(ctx, module, tops)?)
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// The services declared in the design.
+    #[serde(default)]
+    pub declarations: Vec<ServiceDeclDesc>,
+    /// The service instances found below each top-level module.
+    #[serde(default)]
+    pub top_levels: Vec<TopLevelDesc>,
+}
+
+impl Manifest {
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        serde_json::from_str(json)
+            .map_err(|e| Error::simple(format!("Failed to parse ESI manifest: {}", e)))
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    /// The declaration of the service `name`.
+    pub fn service(&self, name: &str) -> Option<&ServiceDeclDesc> {
+        self.declarations.iter().find(|decl| decl.name == name)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ServiceDeclDesc {
+    pub name: String,
+    #[serde(default)]
+    pub ports: Vec<ServicePortDesc>,
+}
+
+impl ServiceDeclDesc {
+    pub fn port(&self, name: &str) -> Option<&ServicePortDesc> {
+        self.ports.iter().find(|port| port.name == name)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ServicePortDesc {
+    pub name: String,
+    #[serde(
+        default,
+        rename = "to-server-type",
+        alias = "to_server_type",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub to_server_type: Option<TypeDesc>,
+    #[serde(
+        default,
+        rename = "to-client-type",
+        alias = "to_client_type",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub to_client_type: Option<TypeDesc>,
+}
+
+impl ServicePortDesc {
+    pub fn direction(&self) -> Option<ServicePortDirection> {
+        match (&self.to_server_type, &self.to_client_type) {
+            (Some(_), None) => Some(ServicePortDirection::ToServer),
+            (None, Some(_)) => Some(ServicePortDirection::ToClient),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TopLevelDesc {
+    /// Symbol of the top-level module, e.g. `@top`.
+    pub module: String,
+    #[serde(default)]
+    pub services: Vec<ServiceInstanceDesc>,
+}
+
+impl TopLevelDesc {
+    /// Name of the top-level module, without the leading `@`.
+    pub fn module_name(&self) -> &str {
+        self.module.trim_start_matches('@')
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ServiceInstanceDesc {
+    /// The implemented service, or `None` if the instance implements all services.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub impl_type: Option<String>,
+    /// Implementation specific details, e.g. cosimulation endpoint ids.
+    #[serde(default, skip_serializing_if = "serde_json::Value::is_null")]
+    pub impl_details: serde_json::Value,
+    #[serde(default)]
+    pub instance_path: Vec<InstancePathDesc>,
+    #[serde(default)]
+    pub clients: Vec<ServiceClientDesc>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct InstancePathDesc {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inner: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outer_sym: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ServiceClientDesc {
+    #[serde(default)]
+    pub client_name: Vec<String>,
+    /// The service port, e.g. `#hw.innerNameRef<@HostComms::@Send>`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<String>,
+    #[serde(
+        default,
+        rename = "to-server-type",
+        alias = "to_server_type",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub to_server_type: Option<TypeDesc>,
+    #[serde(
+        default,
+        rename = "to-client-type",
+        alias = "to_client_type",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub to_client_type: Option<TypeDesc>,
+}
+
+/// A type in the manifest, e.g. `{"dialect": "esi", "mnemonic": "channel", "inner": ...}`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TypeDesc {
+    #[serde(default)]
+    pub dialect: String,
+    #[serde(default)]
+    pub mnemonic: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signedness: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hw_bitwidth: Option<usize>,
+    /// Inner type of channels.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inner: Option<Box<TypeDesc>>,
+    /// Element type of arrays and lists.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub element: Option<Box<TypeDesc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<usize>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldDesc>,
+}
+
+impl TypeDesc {
+    pub fn is_channel(&self) -> bool {
+        self.dialect == "esi" && self.mnemonic == "channel"
+    }
+
+    /// Number of bits of the type, looking through channels.
+    pub fn bitwidth(&self) -> Option<usize> {
+        if self.is_channel() {
+            return self.inner.as_ref()?.bitwidth();
+        }
+        self.hw_bitwidth.or(self.width)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FieldDesc {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: TypeDesc,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::esi::*;
+    use hw::{HwModuleOp, ModulePortInfo};
+
+    #[test]
+    fn parse_manifest() -> miette::Result<()> {
+        let json = r##"{
+          "declarations": [{
+            "name": "HostComms",
+            "ports": [
+              { "name": "Send",
+                "to-server-type": { "dialect": "esi", "mnemonic": "channel",
+                  "inner": { "dialect": "builtin", "mnemonic": "int", "width": 8,
+                             "signedness": "signless", "hw_bitwidth": 8 } } },
+              { "name": "Recv",
+                "to-client-type": { "dialect": "esi", "mnemonic": "channel",
+                  "inner": { "dialect": "builtin", "mnemonic": "int", "width": 16 } } }
+            ]
+          }],
+          "top_levels": [{
+            "module": "@top",
+            "services": [{
+              "service": "HostComms",
+              "impl_type": "cosim",
+              "instance_path": [],
+              "clients": [{ "client_name": ["loopback"],
+                            "port": "#hw.innerNameRef<@HostComms::@Send>" }]
+            }]
+          }]
+        }"##;
+        let manifest = Manifest::from_json(json)?;
+        let decl = manifest.service("HostComms").unwrap();
+        let send = decl.port("Send").unwrap();
+        assert_eq!(send.direction(), Some(ServicePortDirection::ToServer));
+        assert!(send.to_server_type.as_ref().unwrap().is_channel());
+        assert_eq!(send.to_server_type.as_ref().unwrap().bitwidth(), Some(8));
+        assert_eq!(decl.port("Recv").unwrap().to_client_type.as_ref().unwrap().bitwidth(), Some(16));
+        assert_eq!(manifest.top_levels[0].module_name(), "top");
+        assert_eq!(manifest.top_levels[0].services[0].clients[0].client_name, ["loopback"]);
+
+        assert_eq!(Manifest::from_json(&manifest.to_json())?, manifest);
+        assert!(Manifest::from_json("{").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn emit_and_parse_manifest() -> miette::Result<()> {
+        let ctx = OwnedContext::default();
+        hw::dialect().load(&ctx).unwrap();
+        sv::dialect().load(&ctx).unwrap();
+        dialect().load(&ctx).unwrap();
+        register_passes();
+        let mut builder = OpBuilder::new(&ctx);
+        let module = Module::create(builder.loc());
+
+        let i1 = IntegerType::new(&ctx, 1);
+        let chan = ChannelType::new(&IntegerType::new(&ctx, 8));
+        let service = ServiceDeclOp::build(
+            &mut builder,
+            &module,
+            "HostComms",
+            &[ServicePort::to_server("Send", chan), ServicePort::to_client("Recv", chan)],
+        )?;
+        let mut ports = ModulePortInfo::default();
+        ports.add_input("clk", &i1);
+        ports.add_input("rst", &i1);
+        HwModuleOp::build_with(
+            &mut builder,
+            &module,
+            "top",
+            &ports,
+            &[],
+            "",
+            |builder, _, inputs, _| {
+                let recv =
+                    RequestToClientConnectionOp::build(builder, &service, "Recv", &["loopback"])
+                        .unwrap();
+                RequestToServerConnectionOp::build(
+                    builder,
+                    &service,
+                    "Send",
+                    &["loopback"],
+                    &recv.result(),
+                )
+                .unwrap();
+                ServiceInstanceOp::build(
+                    builder,
+                    Some(&service),
+                    "cosim",
+                    [inputs["clk"], inputs["rst"]],
+                )
+                .unwrap();
+            },
+        )?;
+
+        let manifest = manifest(&ctx, &module, &["top"])?;
+        let decl = manifest.service("HostComms").unwrap();
+        assert_eq!(decl.port("Send").unwrap().direction(), Some(ServicePortDirection::ToServer));
+        assert_eq!(decl.port("Recv").unwrap().direction(), Some(ServicePortDirection::ToClient));
+        assert_eq!(manifest.top_levels[0].module_name(), "top");
+        Ok(())
+    }
+}
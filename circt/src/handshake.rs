@@ -0,0 +1,328 @@
+// Copyright (c) 2022-2023 Kamyar Mohajerani
+
+//! The handshake dialect describes dataflow circuits, in which every value is a latency-insensitive
+//!  stream of tokens and every operation fires once all its inputs are available.
+//! See https://circt.llvm.org/docs/Dialects/Handshake/ for more details.
+
+use crate::crate_prelude::*;
+use circt_sys::*;
+use hw::{port_names, port_types, ModulePortInfo};
+use std::{borrow::Borrow, collections::HashMap};
+
+define_dialect!(handshake);
+
+pub fn register_passes() {
+    unsafe { registerHandshakePasses() }
+}
+
+pub mod passes {
+    use crate::*;
+
+    /// Lowers handshake functions to HW modules with valid/ready handshaking.
+    pub fn lower_handshake_to_hw() -> Pass {
+        Pass::try_from_raw(unsafe { handshakeCreateHandshakeToHWPass() }).unwrap()
+    }
+
+    pub fn register_lower_handshake_to_hw() {
+        unsafe { handshakeRegisterHandshakeToHWPass() }
+    }
+}
+
+def_operation!(FuncOp, "handshake.func"; doc = "A dataflow function, lowered to a module with handshaked ports.");
+
+impl SingleRegionOp for FuncOp {}
+
+impl SingleBlockOp for FuncOp {}
+
+impl FuncOp {
+    /// Create a function at the end of `module`. Every value must be used exactly once in the
+    ///  body, i.e. values used more than once must be forked and unused ones sunk.
+    pub fn build_with(
+        builder: &mut OpBuilder,
+        module: &Module,
+        name: &str,
+        ports: &ModulePortInfo,
+        with_fn: impl FnOnce(
+            &mut OpBuilder,
+            &Block,
+            &HashMap<String, Value>,
+            &mut HashMap<String, Value>,
+        ),
+    ) -> Result<Self, Error> {
+        builder.set_insertion_point(Some(InsertPoint::BlockEnd(module.body())));
+        let region = Region::default();
+        let block = Block::default();
+        let func: Self = builder
+            .build_with(|builder, state| {
+                let ctx = builder.context();
+                region.append_block(&block);
+                state.add_region(&region);
+                for pi in ports.inputs.iter() {
+                    block.add_argument(&pi.ty, pi.loc.as_ref().unwrap_or(builder.loc()));
+                }
+                state.add_attribute(SymbolTable::symbol_attr_name(), &StringAttr::new(ctx, name));
+                state.add_attribute(
+                    "function_type",
+                    &TypeAttr::new(&FunctionType::new(
+                        ctx,
+                        port_types(ports.inputs.iter()),
+                        port_types(ports.outputs.iter()),
+                    )),
+                );
+                state.add_attribute(
+                    "argNames",
+                    &ArrayAttr::new(ctx, port_names(ctx, ports.inputs.iter())),
+                );
+                state.add_attribute(
+                    "resNames",
+                    &ArrayAttr::new(ctx, port_names(ctx, ports.outputs.iter())),
+                );
+            })
+            .ok_or(Error::simple("OpBuilder failed"))?;
+
+        let body = func.first_block().ok_or(Error::IsNone)?;
+        let inputs: HashMap<String, Value> =
+            ports.inputs.iter().map(|pi| pi.name.clone()).zip(body.arguments()).collect();
+        let mut output_val_map = HashMap::default();
+        builder.set_insertion_point(Some(InsertPoint::BlockEnd(body.clone())));
+        with_fn(builder, &body, &inputs, &mut output_val_map);
+
+        let mut output_vals = Vec::default();
+        for out_port in ports.outputs.iter() {
+            output_vals.push(output_val_map.remove(&out_port.name).ok_or(Error::simple(
+                format!("Value for output port: {} is missing!", &out_port.name),
+            ))?);
+        }
+        builder.set_insertion_point(Some(InsertPoint::BlockEnd(body)));
+        ReturnOp::build::<Value>(builder, output_vals.iter()).ok_or(Error::IsNone)?;
+        func.verify()
+            .then_some(func)
+            .ok_or(Error::simple(format!("handshake::FuncOp verification failed. {:?}", &func)))
+    }
+}
+
+def_operation!(ReturnOp, "handshake.return");
+
+impl ReturnOp {
+    pub fn build<V: Borrow<Value>>(
+        builder: &mut OpBuilder,
+        values: impl IntoIterator<Item = V>,
+    ) -> Option<Self> {
+        builder.build_with(|_, state| {
+            state.add_operands::<Value>(values);
+        })
+    }
+}
+
+def_operation!(ForkOp, "handshake.fork"; doc = "Replicates a token to `n` consumers.");
+
+impl ForkOp {
+    pub fn build(builder: &mut OpBuilder, input: &Value, n: usize) -> Option<Self> {
+        builder.build_with(|_, state| {
+            state.add_operand(input);
+            state.add_results(std::iter::repeat(input.ty()).take(n));
+        })
+    }
+
+    pub fn outputs(&self) -> Vec<Value> {
+        self.results()
+    }
+}
+
+def_operation_single_result!(JoinOp, "handshake.join");
+
+impl JoinOp {
+    /// Produce a control token once a token is available on each of `inputs`.
+    pub fn build(
+        builder: &mut OpBuilder,
+        inputs: impl IntoIterator<Item = impl Borrow<Value>>,
+    ) -> Option<Self> {
+        builder.build_with(|builder, state| {
+            state.add_operands::<Value>(inputs);
+            state.add_result(&NoneType::new(builder.context()));
+        })
+    }
+}
+
+def_operation_many_to_one!(MergeOp, "handshake.merge");
+
+def_operation_single_result!(MuxOp, "handshake.mux");
+
+impl MuxOp {
+    /// Forward the token of the data input selected by `select`.
+    pub fn build(
+        builder: &mut OpBuilder,
+        select: &Value,
+        data: impl IntoIterator<Item = impl Borrow<Value>>,
+    ) -> Option<Self> {
+        let data: Vec<Value> = data.into_iter().map(|v| *v.borrow()).collect();
+        let ty = data.first()?.ty();
+        builder.build_with(|_, state| {
+            state.add_operand(select);
+            state.add_operands::<Value>(data.iter());
+            state.add_result(&ty);
+        })
+    }
+}
+
+/// Kind of a `handshake.buffer`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BufferType {
+    /// A chain of registers, which may be initialized.
+    Seq,
+    /// A FIFO, which adds no latency when empty.
+    Fifo,
+}
+
+impl BufferType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BufferType::Seq => "seq",
+            BufferType::Fifo => "fifo",
+        }
+    }
+
+    pub fn attr(&self, ctx: &Context) -> Attribute {
+        Attribute::parse(ctx, &format!("#handshake<buffer_type {}>", self.name())).unwrap()
+    }
+}
+
+def_operation_single_result!(BufferOp, "handshake.buffer");
+
+impl BufferOp {
+    pub fn build(
+        builder: &mut OpBuilder,
+        input: &Value,
+        slots: usize,
+        buffer_type: BufferType,
+    ) -> Option<Self> {
+        builder.build_with(|builder, state| {
+            let ctx = builder.context();
+            state.add_operand(input);
+            state.add_result(&input.ty());
+            state.add_attribute(
+                "slots",
+                &IntegerAttr::new(&IntegerType::new(ctx, 32), slots as i64),
+            );
+            state.add_attribute("bufferType", &buffer_type.attr(ctx));
+        })
+    }
+}
+
+def_operation!(SinkOp, "handshake.sink"; doc = "Discards all tokens it receives.");
+
+impl SinkOp {
+    pub fn build(builder: &mut OpBuilder, input: &Value) -> Option<Self> {
+        builder.build_with(|_, state| {
+            state.add_operand(input);
+        })
+    }
+}
+
+def_operation_single_result!(SourceOp, "handshake.source");
+
+impl SourceOp {
+    /// Produce an unbounded stream of control tokens.
+    pub fn build(builder: &mut OpBuilder) -> Option<Self> {
+        builder.build_with(|builder, state| {
+            state.add_result(&NoneType::new(builder.context()));
+        })
+    }
+}
+
+def_operation_single_result!(ConstantOp, "handshake.constant");
+
+impl ConstantOp {
+    /// Produce `value` for every token received on `ctrl`.
+    pub fn build(builder: &mut OpBuilder, ctrl: &Value, value: &IntegerAttr) -> Option<Self> {
+        builder.build_with(|_, state| {
+            state.add_operand(ctrl);
+            state.add_attribute("value", value);
+            state.add_result(&value.ty());
+        })
+    }
+}
+
+def_operation!(ConditionalBranchOp, "handshake.cond_br"; doc = "Routes a data token to one of two outputs depending on a condition token.");
+
+impl ConditionalBranchOp {
+    pub fn build(builder: &mut OpBuilder, condition: &Value, data: &Value) -> Option<Self> {
+        builder.build_with(|_, state| {
+            state.add_operand(condition);
+            state.add_operand(data);
+            state.add_result(&data.ty());
+            state.add_result(&data.ty());
+        })
+    }
+
+    pub fn true_result(&self) -> Value {
+        self.result_at(0).unwrap()
+    }
+
+    pub fn false_result(&self) -> Value {
+        self.result_at(1).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dataflow_to_hw() -> miette::Result<()> {
+        let ctx = OwnedContext::default();
+        hw::dialect().load(&ctx).unwrap();
+        comb::dialect().load(&ctx).unwrap();
+        seq::dialect().load(&ctx).unwrap();
+        sv::dialect().load(&ctx).unwrap();
+        dialect().load(&ctx).unwrap();
+
+        let mut builder = OpBuilder::new(&ctx);
+        let module = Module::create(builder.loc());
+
+        let i1 = IntegerType::new(&ctx, 1);
+        let i32 = IntegerType::new(&ctx, 32);
+        let none = NoneType::new(&ctx);
+        let mut ports = ModulePortInfo::default();
+        ports.add_input("a", &i32);
+        ports.add_input("b", &i32);
+        ports.add_input("sel", &i1);
+        ports.add_input("ctrl", &none);
+        ports.add_output("out", &i32);
+        ports.add_output("k", &i32);
+        ports.add_output("done", &none);
+
+        FuncOp::build_with(
+            &mut builder,
+            &module,
+            "select",
+            &ports,
+            |builder, _, inputs, outputs| {
+                let sel = ForkOp::build(builder, &inputs["sel"], 2).unwrap().outputs();
+                let br = ConditionalBranchOp::build(builder, &sel[0], &inputs["a"]).unwrap();
+                SinkOp::build(builder, &br.false_result()).unwrap();
+                let mux = MuxOp::build(builder, &sel[1], [br.true_result(), inputs["b"]]).unwrap();
+                let buf = BufferOp::build(builder, &mux.result(), 2, BufferType::Seq).unwrap();
+                outputs.insert("out".to_string(), buf.result());
+
+                let ctrl = ForkOp::build(builder, &inputs["ctrl"], 2).unwrap().outputs();
+                let k = ConstantOp::build(builder, &ctrl[0], &IntegerAttr::new(&i32, 42)).unwrap();
+                outputs.insert("k".to_string(), k.result());
+                let src = SourceOp::build(builder).unwrap();
+                let merged = MergeOp::build(builder, [ctrl[1], src.result()]).unwrap();
+                let done = JoinOp::build(builder, [merged.result()]).unwrap();
+                outputs.insert("done".to_string(), done.result());
+            },
+        )?;
+        assert!(module.op().verify());
+
+        let pm = OwnedPassManager::new(&ctx);
+        pm.add_pass(&passes::lower_handshake_to_hw());
+        pm.run(&module)?;
+
+        let mut verilog = String::new();
+        assert!(sv::export_verilog(&module, &mut verilog).is_success());
+        assert!(verilog.contains("module select"));
+        Ok(())
+    }
+}
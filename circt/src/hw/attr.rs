@@ -1,7 +1,9 @@
 // Copyright (c) 2022-2023 Kamyar Mohajerani
 
+use super::EnumType;
 use crate::crate_prelude::*;
 use circt_sys::*;
+use std::borrow::Borrow;
 
 def_attr!(hw::GlobalRefAttr);
 
@@ -31,7 +33,36 @@ impl InnerRefAttr {
 }
 
 
-def_attr!(hw::ParamDeclAttr);
+def_attr!(hw::OutputFileAttr, Clone, Copy);
+
+/// Specifies the file an operation is emitted to by `sv::export_split_verilog`.
+impl OutputFileAttr {
+    /// `exclude_from_file_list`: do not list the file in the generated filelist.
+    /// `include_replicated_ops`: also emit the operations that are replicated in every file.
+    pub fn new(
+        file_name: StringAttr,
+        exclude_from_file_list: bool,
+        include_replicated_ops: bool,
+    ) -> Self {
+        Self::try_from_raw(unsafe {
+            hwOutputFileGetFromFileName(
+                file_name.raw(),
+                exclude_from_file_list,
+                include_replicated_ops,
+            )
+        })
+        .unwrap()
+    }
+
+    /// Name of the file the operation is emitted to.
+    pub fn file_name(&self) -> String {
+        StringRef::try_from_raw(unsafe { hwOutputFileAttrGetFilename(self.raw()) })
+            .map(|s| s.to_string())
+            .unwrap()
+    }
+}
+
+def_attr!(hw::ParamDeclAttr, Clone, Copy);
 
 impl ParamDeclAttr {
     pub fn new(name: &str, ty: impl Ty, value: impl Attr) -> Self {
@@ -54,7 +85,7 @@ impl ParamDeclAttr {
     }
 }
 
-def_attr!(hw::ParamDeclRefAttr);
+def_attr!(hw::ParamDeclRefAttr, Clone, Copy);
 
 impl ParamDeclRefAttr {
     pub fn new(ctx: &Context, name: &str) -> Self {
@@ -68,6 +99,15 @@ impl ParamDeclRefAttr {
         StringRef::try_from_raw(unsafe { hwParamDeclRefAttrGetName(self.0) })
     }
 
+    /// Create a reference to the parameter `name` of type `ty`, e.g. `#hw.param.decl.ref<"WIDTH"> : i32`.
+    /// Integer typed references can be used as widths of `hw::IntType`.
+    pub fn new_typed(ctx: &Context, name: &str, ty: &impl Ty) -> Self {
+        Self::try_from_raw(unsafe {
+            hwParamDeclRefAttrGetWithType(ctx.raw(), StringRef::from_str(name).raw(), ty.raw())
+        })
+        .unwrap()
+    }
+
     pub fn ty(&self) -> Option<Type> {
         Type::try_from_raw(unsafe { hwParamDeclRefAttrGetType(self.0) })
     }
@@ -80,3 +120,95 @@ impl ParamVerbatimAttr {
         Self::try_from_raw(unsafe { hwParamVerbatimAttrGet(text.raw()) }).unwrap()
     }
 }
+
+/// Opcodes of the `#hw.param.expr.*` parameter expression attributes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamExprOpcode {
+    Add,
+    Mul,
+    And,
+    Or,
+    Xor,
+    Shl,
+    ShrU,
+    ShrS,
+    DivU,
+    DivS,
+    ModU,
+    ModS,
+    CLog2,
+    StrConcat,
+}
+
+impl ParamExprOpcode {
+    /// Name of the opcode as known to CIRCT's `PEO` enum.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ParamExprOpcode::Add => "Add",
+            ParamExprOpcode::Mul => "Mul",
+            ParamExprOpcode::And => "And",
+            ParamExprOpcode::Or => "Or",
+            ParamExprOpcode::Xor => "Xor",
+            ParamExprOpcode::Shl => "Shl",
+            ParamExprOpcode::ShrU => "ShrU",
+            ParamExprOpcode::ShrS => "ShrS",
+            ParamExprOpcode::DivU => "DivU",
+            ParamExprOpcode::DivS => "DivS",
+            ParamExprOpcode::ModU => "ModU",
+            ParamExprOpcode::ModS => "ModS",
+            ParamExprOpcode::CLog2 => "CLog2",
+            ParamExprOpcode::StrConcat => "StrConcat",
+        }
+    }
+}
+
+def_attr!(hw::ParamExprAttr, Clone, Copy);
+
+/// Parameter expressions, e.g. `#hw.param.expr.add<#hw.param.decl.ref<"WIDTH">, 1>`.
+/// Operands are typed attributes: integer or string literals, decl refs or other expressions.
+impl ParamExprAttr {
+    /// Build a parameter expression. Constant operands are folded by CIRCT, so the returned
+    /// attribute is not necessarily a `ParamExprAttr`, e.g. `add(1, 2)` yields `3 : i32`.
+    pub fn new(
+        ctx: &Context,
+        opcode: ParamExprOpcode,
+        operands: impl IntoIterator<Item = impl Borrow<Attribute>>,
+    ) -> Option<Attribute> {
+        let operands: Vec<_> = operands.into_iter().map(|a| a.borrow().raw()).collect();
+        Attribute::try_from_raw(unsafe {
+            hwParamExprAttrGet(
+                ctx.raw(),
+                StringRef::from_str(opcode.name()).raw(),
+                operands.len() as _,
+                operands.as_ptr(),
+            )
+        })
+    }
+
+    pub fn add(ctx: &Context, operands: &[Attribute]) -> Option<Attribute> {
+        Self::new(ctx, ParamExprOpcode::Add, operands)
+    }
+
+    pub fn mul(ctx: &Context, operands: &[Attribute]) -> Option<Attribute> {
+        Self::new(ctx, ParamExprOpcode::Mul, operands)
+    }
+
+    pub fn clog2(ctx: &Context, operand: &Attribute) -> Option<Attribute> {
+        Self::new(ctx, ParamExprOpcode::CLog2, [operand])
+    }
+
+    pub fn str_concat(ctx: &Context, operands: &[Attribute]) -> Option<Attribute> {
+        Self::new(ctx, ParamExprOpcode::StrConcat, operands)
+    }
+}
+
+def_attr!(hw::EnumFieldAttr, Clone, Copy);
+
+/// A field of an enum type, e.g. `#hw.enum.field<Busy, !hw.enum<Idle, Busy>>`.
+impl EnumFieldAttr {
+    /// Returns `None` if `field` is not a field of `ty`.
+    pub fn new(ty: &EnumType, field: &str) -> Option<Self> {
+        ty.field_index(field)?;
+        Self::try_from_raw(unsafe { hwEnumFieldAttrGet(ty.raw(), StringRef::from_str(field).raw()) })
+    }
+}
@@ -0,0 +1,326 @@
+// Copyright (c) 2022-2023 Kamyar Mohajerani
+
+//! Inner symbols name operations inside a module, so that they can be referred to from outside
+//!  the module, e.g. by hierarchical paths.
+//! See https://circt.llvm.org/docs/RationaleSymbols/
+
+use crate::crate_prelude::*;
+use hw::{HwModuleLike, HwModuleOp, InnerRefAttr, InstanceOp};
+use std::collections::HashMap;
+
+def_attr!(hw::InnerSymAttr, Clone, Copy);
+
+impl InnerSymAttr {
+    pub fn new(ctx: &Context, sym_name: &str) -> Self {
+        Self::try_from_raw(unsafe { hwInnerSymAttrGet(StringAttr::new(ctx, sym_name).raw()) })
+            .unwrap()
+    }
+
+    pub fn sym_name(&self) -> StringAttr {
+        StringAttr::try_from_raw(unsafe { hwInnerSymAttrGetSymName(self.raw()) }).unwrap()
+    }
+}
+
+/// Name of the attribute holding the inner symbol of an operation.
+pub const INNER_SYM_ATTR_NAME: &str = "inner_sym";
+
+/// Accessors for the inner symbol of an operation.
+pub trait InnerSymbolOp: Op {
+    /// Set the inner symbol of the operation, replacing the existing one.
+    fn set_inner_sym(&self, name: &str) {
+        self.set_attribute(INNER_SYM_ATTR_NAME, InnerSymAttr::new(&self.context(), name));
+    }
+
+    /// The inner symbol of the operation, if it has one.
+    fn inner_sym(&self) -> Option<String> {
+        let attr = self.attribute(INNER_SYM_ATTR_NAME)?;
+        match InnerSymAttr::try_from(attr) {
+            Ok(sym) => Some(sym.sym_name().get_value()),
+            Err(_) => StringAttr::try_from(attr).ok().map(|s| s.get_value()),
+        }
+    }
+}
+
+impl<T: Op> InnerSymbolOp for T {}
+
+/// The inner symbols defined in a single module.
+#[derive(Debug, Clone)]
+pub struct InnerSymbolTable {
+    module_name: String,
+    symbols: HashMap<String, Operation>,
+}
+
+impl InnerSymbolTable {
+    /// Collect the inner symbols of all operations in `module`. Fails if a symbol is defined more
+    ///  than once.
+    pub fn new(module: &impl HwModuleLike) -> Result<Self, Error> {
+        let module_name = module.module_name();
+        let mut symbols = HashMap::new();
+        let mut duplicate = None;
+        module.walk(&mut |op| {
+            if let Some(sym) = op.inner_sym() {
+                if symbols.insert(sym.clone(), op).is_some() {
+                    duplicate.get_or_insert(sym);
+                }
+            }
+        });
+        match duplicate {
+            Some(sym) => Err(Error::simple(format!(
+                "Duplicate inner symbol `{}` in module `{}`",
+                sym, module_name
+            ))),
+            None => Ok(Self {
+                module_name,
+                symbols,
+            }),
+        }
+    }
+
+    pub fn module_name(&self) -> &str {
+        &self.module_name
+    }
+
+    /// The operation with the inner symbol `name`.
+    pub fn lookup(&self, name: &str) -> Option<Operation> {
+        self.symbols.get(name).copied()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.symbols.contains_key(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Operation)> {
+        self.symbols.iter()
+    }
+
+    /// Return the inner symbol of `op`, giving it a fresh one based on `hint` if it has none.
+    pub fn get_or_create(&mut self, op: &impl Op, hint: &str) -> String {
+        let op = Operation::from_raw(op.raw());
+        if let Some(sym) = op.inner_sym() {
+            return sym;
+        }
+        let mut name = hint.to_string();
+        let mut suffix = 0;
+        while self.contains(&name) {
+            name = format!("{}_{}", hint, suffix);
+            suffix += 1;
+        }
+        op.set_inner_sym(&name);
+        self.symbols.insert(name.clone(), op);
+        name
+    }
+
+    /// A reference to the inner symbol `name` of this module.
+    pub fn inner_ref(&self, ctx: &Context, name: &str) -> InnerRefAttr {
+        InnerRefAttr::new(&StringAttr::new(ctx, &self.module_name), &StringAttr::new(ctx, name))
+            .unwrap()
+    }
+}
+
+def_operation!(HierPathOp, "hw.hierpath"; doc = "A named path through the instance hierarchy, usable in cross-module references.");
+
+impl HierPathOp {
+    /// Create a path `sym_name` at the end of `module`.
+    /// `path` lists the `(module, instance)` pairs from the root of the path downwards; the
+    ///  instances are given inner symbols as needed. If `target` is given, the path ends at the
+    ///  operation with that inner symbol inside the module instantiated last, otherwise at the
+    ///  module itself.
+    pub fn build(
+        builder: &mut OpBuilder,
+        module: &Module,
+        sym_name: &str,
+        path: &[(&str, &str)],
+        target: Option<&str>,
+    ) -> Result<Self, Error> {
+        builder.set_insertion_point(Some(InsertPoint::BlockEnd(module.body())));
+        let ctx = builder.context();
+        let modules: HashMap<String, HwModuleOp> = module
+            .body()
+            .operations()
+            .filter_map(|op| op.try_into_op::<HwModuleOp>())
+            .map(|m| (m.module_name(), m))
+            .collect();
+        // Resolve the whole path before giving any instance an inner symbol.
+        let mut instances = Vec::with_capacity(path.len());
+        let mut leaf_module: Option<String> = None;
+        for (i, &(module_name, instance_name)) in path.iter().enumerate() {
+            let expected = leaf_module.as_deref().unwrap_or(module_name);
+            if expected != module_name {
+                let (parent_name, parent_instance) = path[i - 1];
+                return Err(Error::simple(format!(
+                    "Instance `{}` of `{}` instantiates `{}`, not `{}`",
+                    parent_instance, parent_name, expected, module_name
+                )));
+            }
+            let parent = modules
+                .get(module_name)
+                .ok_or(Error::simple(format!("Unknown module `{}`", module_name)))?;
+            let mut instance = None;
+            parent.walk(&mut |op| {
+                if let Some(inst) = op.try_into_op::<InstanceOp>() {
+                    if inst.instance_name() == instance_name {
+                        instance = Some(inst);
+                    }
+                }
+            });
+            let instance = instance.ok_or(Error::simple(format!(
+                "No instance `{}` in module `{}`",
+                instance_name, module_name
+            )))?;
+            leaf_module = Some(instance.module_name());
+            instances.push((InnerSymbolTable::new(parent)?, instance, instance_name));
+        }
+        let leaf_module = leaf_module.ok_or(Error::simple("Empty hierarchical path"))?;
+        let leaf = match target {
+            Some(target) => {
+                let symbols = modules
+                    .get(&leaf_module)
+                    .ok_or(Error::simple(format!("Unknown module `{}`", leaf_module)))
+                    .and_then(InnerSymbolTable::new)?;
+                if !symbols.contains(target) {
+                    return Err(Error::simple(format!(
+                        "No inner symbol `{}` in module `{}`",
+                        target, leaf_module
+                    )));
+                }
+                symbols.inner_ref(ctx, target).into()
+            }
+            None => SymbolRefAttr::new(ctx, &leaf_module).into(),
+        };
+
+        let mut namepath: Vec<Attribute> = Vec::with_capacity(path.len() + 1);
+        for (mut symbols, instance, instance_name) in instances {
+            let sym = symbols.get_or_create(&instance, instance_name);
+            namepath.push(symbols.inner_ref(ctx, &sym).into());
+        }
+        namepath.push(leaf);
+
+        builder
+            .build_with(|_, state| {
+                state.add_attribute(
+                    SymbolTable::symbol_attr_name(),
+                    &StringAttr::new(ctx, sym_name),
+                );
+                state.add_attribute("namepath", &ArrayAttr::new::<Attribute>(ctx, namepath));
+            })
+            .ok_or(Error::simple("OpBuilder failed"))
+    }
+
+    pub fn sym_name(&self) -> String {
+        self.attribute(SymbolTable::symbol_attr_name())
+            .and_then(|attr| StringAttr::try_from(attr).ok())
+            .map(|attr| attr.get_value())
+            .unwrap()
+    }
+
+    /// The elements of the path: `hw::InnerRefAttr`s, possibly ending in a `SymbolRefAttr`.
+    pub fn namepath(&self) -> Vec<Attribute> {
+        self.attribute("namepath")
+            .and_then(|attr| ArrayAttr::try_from(attr).ok())
+            .map(|path| path.elements().collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hierpath_to_register() -> miette::Result<()> {
+        let ctx = OwnedContext::default();
+        hw::dialect().load(&ctx).unwrap();
+        seq::dialect().load(&ctx).unwrap();
+        sv::dialect().load(&ctx).unwrap();
+        let mut builder = OpBuilder::new(&ctx);
+        let module = Module::create(builder.loc());
+
+        let i1 = IntegerType::new(&ctx, 1);
+        let i8 = IntegerType::new(&ctx, 8);
+        let mut ports = ModulePortInfo::default();
+        ports.add_input("clk", &i1);
+        ports.add_input("a", &i8);
+        ports.add_output("b", &i8);
+
+        let leaf = HwModuleOp::build_with(
+            &mut builder,
+            &module,
+            "leaf",
+            &ports,
+            &[],
+            "",
+            |builder, _, inputs, outputs| {
+                let r =
+                    seq::CompRegOp::build(builder, "r", &inputs["a"], &inputs["clk"], None, None)
+                        .unwrap();
+                r.set_inner_sym("r_sym");
+                outputs.insert("b".to_string(), r.output());
+            },
+        )?;
+        HwModuleOp::build_with(
+            &mut builder,
+            &module,
+            "top",
+            &ports,
+            &[],
+            "",
+            |builder, _, inputs, outputs| {
+                let l = InstanceOp::build(builder, "l", &leaf, [inputs["clk"], inputs["a"]], &[])
+                    .unwrap();
+                outputs.insert("b".to_string(), l.result_at(0).unwrap());
+            },
+        )?;
+
+        let path =
+            HierPathOp::build(&mut builder, &module, "probe", &[("top", "l")], Some("r_sym"))?;
+        assert_eq!(path.sym_name(), "probe");
+        assert_eq!(path.namepath().len(), 2);
+        assert!(module.op().verify());
+        assert!(HierPathOp::build(&mut builder, &module, "bad", &[("top", "x")], None).is_err());
+        // `l` instantiates `leaf`, not `top`.
+        let unrelated = [("top", "l"), ("top", "l")];
+        assert!(HierPathOp::build(&mut builder, &module, "bad", &unrelated, None).is_err());
+
+        // A module outside of the path reads the register through it.
+        let mut monitor_ports = ModulePortInfo::default();
+        monitor_ports.add_output("r", &i8);
+        HwModuleOp::build_with(
+            &mut builder,
+            &module,
+            "monitor",
+            &monitor_ports,
+            &[],
+            "",
+            |builder, _, _, outputs| {
+                let xmr = sv::XMRRefOp::build(builder, "probe", &i8).unwrap();
+                let r = sv::ReadInOutOp::build(builder, &xmr.result()).unwrap();
+                outputs.insert("r".to_string(), r.result());
+            },
+        )?;
+        assert!(module.op().verify());
+
+        let pm = OwnedPassManager::new(&ctx);
+        pm.nest("hw.module").parse_pass("prettify-verilog")?;
+        pm.run(&module)?;
+
+        let mut verilog = String::new();
+        assert!(sv::export_verilog(&module, &mut verilog).is_success());
+        assert!(verilog.contains("top.l.r"));
+
+        let symbols = InnerSymbolTable::new(&leaf)?;
+        assert!(symbols.lookup("r_sym").is_some());
+        let top = module
+            .body()
+            .operations()
+            .filter_map(|op| op.try_into_op::<HwModuleOp>())
+            .find(|m| m.module_name() == "top")
+            .unwrap();
+        assert!(InnerSymbolTable::new(&top)?.contains("l"));
+
+        // Inner symbols must be unique within a module.
+        let output = top.first_block().unwrap().terminator().unwrap();
+        output.set_inner_sym("l");
+        assert!(InnerSymbolTable::new(&top).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,389 @@
+// Copyright (c) 2022-2023 Kamyar Mohajerani
+
+//! The module hierarchy of a design: modules are nodes and `hw.instance`s are edges.
+
+use crate::crate_prelude::*;
+use hw::{HwModuleExternOp, HwModuleLike, HwModuleOp, InstanceOp};
+use itertools::Itertools;
+use std::collections::{HashMap, HashSet};
+
+/// A module in the instance graph, either a `hw.module` or a `hw.module.extern`.
+#[derive(Debug, Clone)]
+pub struct InstanceGraphNode {
+    /// Symbol name of the module.
+    pub name: String,
+    /// The `hw.module` or `hw.module.extern` operation.
+    pub op: Operation,
+    /// Indices of the instances inside this module.
+    pub instances: Vec<usize>,
+    /// Indices of the instances of this module.
+    pub uses: Vec<usize>,
+}
+
+impl InstanceGraphNode {
+    pub fn is_extern(&self) -> bool {
+        !HwModuleOp::isa(&self.op)
+    }
+}
+
+/// An edge of the instance graph: an `hw.instance` of `target` inside `parent`.
+#[derive(Debug, Clone)]
+pub struct InstanceRecord {
+    pub op: InstanceOp,
+    /// Name of the instance.
+    pub name: String,
+    /// Index of the module containing the instance.
+    pub parent: usize,
+    /// Index of the instantiated module.
+    pub target: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct InstanceGraph {
+    nodes: Vec<InstanceGraphNode>,
+    records: Vec<InstanceRecord>,
+    index: HashMap<String, usize>,
+}
+
+impl InstanceGraph {
+    /// Build the instance graph of all modules at the top level of `module`.
+    pub fn new(module: &Module) -> Result<Self, Error> {
+        let mut nodes = Vec::new();
+        let mut index = HashMap::new();
+        for op in module.body().operations() {
+            let name = if let Some(m) = op.try_into_op::<HwModuleOp>() {
+                m.module_name()
+            } else if let Some(m) = op.try_into_op::<HwModuleExternOp>() {
+                m.module_name()
+            } else {
+                continue;
+            };
+            if index.insert(name.clone(), nodes.len()).is_some() {
+                return Err(Error::simple(format!("Duplicate module `{}`", name)));
+            }
+            nodes.push(InstanceGraphNode {
+                name,
+                op,
+                instances: Vec::new(),
+                uses: Vec::new(),
+            });
+        }
+
+        let mut records = Vec::new();
+        for parent in 0..nodes.len() {
+            let mut instances = Vec::new();
+            nodes[parent].op.walk(&mut |op| {
+                if let Some(inst) = op.try_into_op::<InstanceOp>() {
+                    instances.push(inst);
+                }
+            });
+            for op in instances {
+                let target = *index.get(&op.module_name()).ok_or(Error::simple(format!(
+                    "Instance `{}` in module `{}` refers to unknown module `{}`",
+                    op.instance_name(),
+                    nodes[parent].name,
+                    op.module_name()
+                )))?;
+                let id = records.len();
+                records.push(InstanceRecord {
+                    op,
+                    name: op.instance_name(),
+                    parent,
+                    target,
+                });
+                nodes[parent].instances.push(id);
+                nodes[target].uses.push(id);
+            }
+        }
+        Ok(Self {
+            nodes,
+            records,
+            index,
+        })
+    }
+
+    pub fn nodes(&self) -> &[InstanceGraphNode] {
+        &self.nodes
+    }
+
+    pub fn records(&self) -> &[InstanceRecord] {
+        &self.records
+    }
+
+    pub fn node(&self, id: usize) -> &InstanceGraphNode {
+        &self.nodes[id]
+    }
+
+    pub fn record(&self, id: usize) -> &InstanceRecord {
+        &self.records[id]
+    }
+
+    /// Index of the module named `name`.
+    pub fn lookup(&self, name: &str) -> Option<usize> {
+        self.index.get(name).copied()
+    }
+
+    /// Modules which are children of `id`, i.e. instantiated inside it, without duplicates.
+    pub fn children(&self, id: usize) -> Vec<usize> {
+        self.nodes[id].instances.iter().map(|&r| self.records[r].target).unique().collect()
+    }
+
+    /// Modules which are not instantiated anywhere. External modules are never top-level.
+    pub fn top_level_modules(&self) -> Vec<usize> {
+        (0..self.nodes.len())
+            .filter(|&id| self.nodes[id].uses.is_empty() && !self.nodes[id].is_extern())
+            .collect()
+    }
+
+    /// Find a cycle of instantiations, returned as the modules along the cycle.
+    pub fn find_cycle(&self) -> Option<Vec<usize>> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum Mark {
+            Unvisited,
+            OnStack,
+            Done,
+        }
+
+        fn visit(
+            graph: &InstanceGraph,
+            id: usize,
+            marks: &mut [Mark],
+            stack: &mut Vec<usize>,
+        ) -> Option<Vec<usize>> {
+            marks[id] = Mark::OnStack;
+            stack.push(id);
+            for child in graph.children(id) {
+                match marks[child] {
+                    Mark::OnStack => {
+                        let start = stack.iter().position(|&n| n == child).unwrap();
+                        return Some(stack[start..].to_vec());
+                    }
+                    Mark::Unvisited => {
+                        if let Some(cycle) = visit(graph, child, marks, stack) {
+                            return Some(cycle);
+                        }
+                    }
+                    Mark::Done => {}
+                }
+            }
+            stack.pop();
+            marks[id] = Mark::Done;
+            None
+        }
+
+        let mut marks = vec![Mark::Unvisited; self.nodes.len()];
+        (0..self.nodes.len()).find_map(|id| {
+            (marks[id] == Mark::Unvisited)
+                .then(|| visit(self, id, &mut marks, &mut Vec::new()))
+                .flatten()
+        })
+    }
+
+    /// All modules ordered such that every module comes after all the modules it instantiates.
+    /// Fails if the hierarchy contains a cycle.
+    pub fn post_order(&self) -> Result<Vec<usize>, Error> {
+        if let Some(cycle) = self.find_cycle() {
+            return Err(Error::simple(format!(
+                "Instance graph contains a cycle: {}",
+                cycle.iter().map(|&id| self.nodes[id].name.as_str()).join(" -> ")
+            )));
+        }
+        fn visit(graph: &InstanceGraph, id: usize, visited: &mut [bool], order: &mut Vec<usize>) {
+            visited[id] = true;
+            for child in graph.children(id) {
+                if !visited[child] {
+                    visit(graph, child, visited, order);
+                }
+            }
+            order.push(id);
+        }
+        let mut visited = vec![false; self.nodes.len()];
+        let mut order = Vec::with_capacity(self.nodes.len());
+        for id in self.top_level_modules().into_iter().chain(0..self.nodes.len()) {
+            if !visited[id] {
+                visit(self, id, &mut visited, &mut order);
+            }
+        }
+        Ok(order)
+    }
+
+    /// All paths of instances from a top-level module down to the module `id`.
+    /// Each path is a list of instance record indices, starting in the top-level module.
+    /// Fails if `id` is instantiated inside a cycle of the hierarchy.
+    pub fn instance_paths(&self, id: usize) -> Result<Vec<Vec<usize>>, Error> {
+        self.paths_to(id, &mut Vec::new())
+    }
+
+    /// `instance_paths` of `id`, which is instantiated by the modules on `stack`.
+    fn paths_to(&self, id: usize, stack: &mut Vec<usize>) -> Result<Vec<Vec<usize>>, Error> {
+        if let Some(start) = stack.iter().position(|&n| n == id) {
+            return Err(Error::simple(format!(
+                "Instance graph contains a cycle: {}",
+                stack[start..].iter().rev().map(|&n| self.nodes[n].name.as_str()).join(" -> ")
+            )));
+        }
+        if self.nodes[id].uses.is_empty() {
+            return Ok(vec![vec![]]);
+        }
+        stack.push(id);
+        let mut paths = Vec::new();
+        for &r in &self.nodes[id].uses {
+            for mut path in self.paths_to(self.records[r].parent, stack)? {
+                path.push(r);
+                paths.push(path);
+            }
+        }
+        stack.pop();
+        Ok(paths)
+    }
+
+    /// Render an instance path as `top/inst0/inst1`.
+    pub fn path_to_string(&self, path: &[usize]) -> String {
+        match path.first() {
+            Some(&first) => std::iter::once(self.nodes[self.records[first].parent].name.as_str())
+                .chain(path.iter().map(|&r| self.records[r].name.as_str()))
+                .join("/"),
+            None => String::new(),
+        }
+    }
+
+    /// Modules reachable from the modules named `roots`.
+    pub fn reachable(&self, roots: &[&str]) -> Result<HashSet<usize>, Error> {
+        let mut reachable = HashSet::new();
+        let mut worklist = roots
+            .iter()
+            .map(|&name| {
+                self.lookup(name).ok_or(Error::simple(format!("Unknown module `{}`", name)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        while let Some(id) = worklist.pop() {
+            if reachable.insert(id) {
+                worklist.extend(self.children(id));
+            }
+        }
+        Ok(reachable)
+    }
+
+    /// Modules not reachable from the modules named `roots`.
+    pub fn unused_modules(&self, roots: &[&str]) -> Result<Vec<usize>, Error> {
+        let reachable = self.reachable(roots)?;
+        Ok((0..self.nodes.len()).filter(|id| !reachable.contains(id)).collect())
+    }
+
+    /// Erase all modules not reachable from the modules named `roots` and return their names.
+    pub fn prune_unused(self, roots: &[&str]) -> Result<Vec<String>, Error> {
+        let unused = self.unused_modules(roots)?;
+        // Unused modules can only be instantiated by other unused modules, so erasing all of
+        // them leaves no dangling instances behind.
+        Ok(unused
+            .into_iter()
+            .map(|id| {
+                let node = &self.nodes[id];
+                node.op.erase();
+                node.name.clone()
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instance_graph() -> miette::Result<()> {
+        let ctx = OwnedContext::default();
+        hw::dialect().load(&ctx).unwrap();
+        let mut builder = OpBuilder::new(&ctx);
+        let module = Module::create(builder.loc());
+
+        let i8 = IntegerType::new(&ctx, 8);
+        let mut ports = ModulePortInfo::default();
+        ports.add_input("a", &i8);
+        ports.add_output("b", &i8);
+
+        let ext = HwModuleExternOp::build(&mut builder, &module, "ext", &ports, &[])?;
+        let leaf = HwModuleOp::build_with(
+            &mut builder,
+            &module,
+            "leaf",
+            &ports,
+            &[],
+            "",
+            |builder, _, inputs, outputs| {
+                let e = InstanceOp::build(builder, "e", &ext, [inputs["a"]], &[]).unwrap();
+                outputs.insert("b".to_string(), e.result_at(0).unwrap());
+            },
+        )?;
+        let mid = HwModuleOp::build_with(
+            &mut builder,
+            &module,
+            "mid",
+            &ports,
+            &[],
+            "",
+            |builder, _, inputs, outputs| {
+                let l0 = InstanceOp::build(builder, "l0", &leaf, [inputs["a"]], &[]).unwrap();
+                let l1 = InstanceOp::build(builder, "l1", &leaf, [l0.result_at(0).unwrap()], &[])
+                    .unwrap();
+                outputs.insert("b".to_string(), l1.result_at(0).unwrap());
+            },
+        )?;
+        for name in ["top", "unused"] {
+            HwModuleOp::build_with(
+                &mut builder,
+                &module,
+                name,
+                &ports,
+                &[],
+                "",
+                |builder, _, inputs, outputs| {
+                    let m = InstanceOp::build(builder, "m", &mid, [inputs["a"]], &[]).unwrap();
+                    outputs.insert("b".to_string(), m.result_at(0).unwrap());
+                },
+            )?;
+        }
+        assert!(module.op().verify());
+
+        let graph = InstanceGraph::new(&module)?;
+        let id = |name: &str| graph.lookup(name).unwrap();
+        let names = |ids: Vec<usize>| -> Vec<String> {
+            ids.into_iter().map(|i| graph.node(i).name.clone()).collect()
+        };
+
+        assert_eq!(names(graph.top_level_modules()), ["top", "unused"]);
+        assert!(graph.node(id("ext")).is_extern());
+        assert_eq!(graph.node(id("leaf")).uses.len(), 2);
+        assert_eq!(names(graph.children(id("mid"))), ["leaf"]);
+        assert!(graph.find_cycle().is_none());
+
+        let order = graph.post_order()?;
+        let pos = |name| order.iter().position(|&i| i == id(name)).unwrap();
+        assert!(pos("ext") < pos("leaf"));
+        assert!(pos("leaf") < pos("mid"));
+        assert!(pos("mid") < pos("top"));
+
+        let mut paths: Vec<String> =
+            graph.instance_paths(id("ext"))?.iter().map(|p| graph.path_to_string(p)).collect();
+        paths.sort();
+        assert_eq!(paths, ["top/m/l0/e", "top/m/l1/e", "unused/m/l0/e", "unused/m/l1/e"]);
+
+        assert_eq!(names(graph.unused_modules(&["mid"])?), ["top", "unused"]);
+        assert_eq!(graph.prune_unused(&["top"])?, ["unused"]);
+        assert!(module.op().verify());
+
+        let graph = InstanceGraph::new(&module)?;
+        assert_eq!(graph.top_level_modules(), [graph.lookup("top").unwrap()]);
+        assert!(graph.lookup("unused").is_none());
+
+        // Make `leaf` instantiate `mid`, which instantiates `leaf`.
+        let e = graph.records().iter().find(|r| r.name == "e").unwrap();
+        e.op.set_attribute("moduleName", SymbolRefAttr::new(&ctx, "mid"));
+        let graph = InstanceGraph::new(&module)?;
+        assert!(graph.find_cycle().is_some());
+        assert!(graph.post_order().is_err());
+        assert!(graph.instance_paths(graph.lookup("leaf").unwrap()).is_err());
+
+        Ok(())
+    }
+}
@@ -7,10 +7,14 @@
 //! See https://circt.llvm.org/docs/Dialects/HW/RationaleHW/ and https://circt.llvm.org/docs/Dialects/HW/ for more details.
 
 mod attr;
+mod inner_sym;
+mod instance_graph;
 mod ops;
 mod structure;
 mod ty;
 pub use attr::*;
+pub use inner_sym::*;
+pub use instance_graph::*;
 pub use ops::*;
 pub use structure::*;
 pub use ty::*;
@@ -42,6 +46,11 @@ pub mod passes {
     pub fn hw_specialize() -> Pass {
         Pass::try_from_raw(unsafe { hwCreateHWSpecializePass() }).unwrap()
     }
+
+    /// Registers the `hw-specialize` pass, so that it can be used in textual pipelines.
+    pub fn register_hw_specialize() {
+        unsafe { hwRegisterHWSpecializePass() }
+    }
 }
 
 /// Return true if the specified type can be used as an HW value type, that is the set of types
@@ -51,6 +60,17 @@ pub fn is_value_type(ty: Type) -> bool {
     unsafe { hwTypeIsAValueType(ty.raw()) }
 }
 
+/// Resolve the parameter references in a parametric type, e.g. `!hw.int<#hw.param.decl.ref<"W">>`,
+///  given an array of `ParamDeclAttr` assignments. Non-parametric types are returned as-is.
+/// Returns `None` if a referenced parameter is not assigned or the resulting type is invalid.
+pub fn evaluate_parametric_type(
+    loc: &Location,
+    parameters: &ArrayAttr,
+    ty: &impl Ty,
+) -> Option<Type> {
+    Type::try_from_raw(unsafe { hwEvaluateParametricType(loc.raw(), parameters.raw(), ty.raw()) })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -75,4 +95,211 @@ mod tests {
         hw::register_hw_passes();
         seq::register_passes();
     }
+
+    #[test]
+    fn parametric_module() -> miette::Result<()> {
+        let ctx = OwnedContext::default();
+        hw::dialect().load(&ctx).unwrap();
+        comb::dialect().load(&ctx).unwrap();
+        sv::dialect().load(&ctx).unwrap();
+
+        let mut builder = OpBuilder::new(&ctx);
+        let module = Module::create(builder.loc());
+
+        let i32 = IntegerType::new(&ctx, 32);
+        let width_ref = ParamDeclRefAttr::new_typed(&ctx, "WIDTH", &i32);
+        let width_ty = IntType::new(&width_ref).unwrap();
+        assert!(IntType::isa(&width_ty));
+        assert_eq!(width_ty.width(), width_ref.into());
+
+        let width_plus_one = ParamExprAttr::add(
+            &ctx,
+            &[width_ref.into(), IntegerAttr::new(&i32, 1).into()],
+        )
+        .unwrap();
+        assert!(ParamExprAttr::isa(&width_plus_one));
+        let folded = ParamExprAttr::add(
+            &ctx,
+            &[IntegerAttr::new(&i32, 2).into(), IntegerAttr::new(&i32, 3).into()],
+        )
+        .unwrap();
+        assert_eq!(folded.to_string(), "5 : i32");
+
+        let mut ports = ModulePortInfo::default();
+        ports.add_input("a", &width_ty);
+        ports.add_input("b", &width_ty);
+        ports.add_output("sum", &width_ty);
+        ports.add_output("width", &i32);
+
+        let adder = HwModuleOp::build_with(
+            &mut builder,
+            &module,
+            "adder",
+            &ports,
+            &[ParamDeclAttr::new("WIDTH", i32, IntegerAttr::new(&i32, 8))],
+            "",
+            |builder, _, inputs, outputs| {
+                let sum = comb::AddOp::build(builder, &inputs["a"], &inputs["b"]).unwrap();
+                outputs.insert("sum".to_string(), sum.result());
+                let width = ParamValueOp::build(builder, &i32, &width_ref).unwrap();
+                outputs.insert("width".to_string(), width.result());
+            },
+        )?;
+        assert_eq!(adder.module_name(), "adder");
+        assert_eq!(adder.parameters().len(), 1);
+        assert_eq!(adder.port_info().outputs[0].ty, width_ty.as_type());
+
+        let i4 = IntegerType::new(&ctx, 4);
+        let mut top_ports = ModulePortInfo::default();
+        top_ports.add_input("x", &i4);
+        top_ports.add_output("y", &i4);
+        HwModuleOp::build_with(
+            &mut builder,
+            &module,
+            "top",
+            &top_ports,
+            &[],
+            "",
+            |builder, _, inputs, outputs| {
+                let width4 = ParamDeclAttr::new("WIDTH", i32, IntegerAttr::new(&i32, 4));
+                let width8 = ParamDeclAttr::new("WIDTH", i32, IntegerAttr::new(&i32, 8));
+                let mismatched = InstanceOp::build(
+                    builder,
+                    "bad",
+                    &adder,
+                    [inputs["x"], inputs["x"]],
+                    &[width8],
+                );
+                assert!(mismatched.unwrap_err().to_string().contains("`a`"));
+                assert!(
+                    InstanceOp::build(builder, "bad", &adder, [inputs["x"]], &[width4]).is_err()
+                );
+                let inst = InstanceOp::build(
+                    builder,
+                    "adder4",
+                    &adder,
+                    [inputs["x"], inputs["x"]],
+                    &[width4],
+                )
+                .unwrap();
+                assert_eq!(inst.result_at(0).unwrap().ty(), i4.as_type());
+                outputs.insert("y".to_string(), inst.result_at(0).unwrap());
+            },
+        )?;
+        assert!(module.op().verify());
+
+        let mut verilog = String::new();
+        sv::export_verilog(&module, &mut verilog);
+        assert!(verilog.contains("parameter"));
+        assert!(verilog.contains("WIDTH"));
+
+        let pm = OwnedPassManager::new(&ctx);
+        pm.add_pass(&hw::passes::hw_specialize());
+        pm.run(&module)?;
+        assert!(module.op().verify());
+
+        // The instance now targets a non-parametric copy of `adder` with 4-bit ports.
+        let mut inst = None;
+        module.op().walk(&mut |op| {
+            if let Some(op) = op.try_into_op::<InstanceOp>() {
+                inst = Some(op);
+            }
+        });
+        let target = inst.unwrap().module_name();
+        assert_ne!(target, "adder");
+        let specialized = (module.body().operations())
+            .filter_map(|op| op.try_into_op::<HwModuleOp>())
+            .find(|op| op.module_name() == target)
+            .unwrap();
+        assert!(specialized.parameters().is_empty());
+        let ports = specialized.port_info();
+        assert_eq!(ports.inputs[0].ty, i4.as_type());
+        assert_eq!(ports.outputs[0].ty, i4.as_type());
+
+        Ok(())
+    }
+
+    #[test]
+    fn enum_and_union_ops() -> miette::Result<()> {
+        let ctx = OwnedContext::default();
+        hw::dialect().load(&ctx).unwrap();
+
+        let mut builder = OpBuilder::new(&ctx);
+        let module = Module::create(builder.loc());
+
+        let i8 = IntegerType::new(&ctx, 8);
+        let state_ty = EnumType::new(&ctx, ["Idle", "Busy"]).unwrap();
+        let msg_ty = UnionType::new(&ctx, [("data", i8.as_type()), ("tag", i8.as_type())]).unwrap();
+
+        let mut ports = ModulePortInfo::default();
+        ports.add_input("data", &i8);
+        ports.add_output("state", &state_ty);
+        ports.add_output("msg", &msg_ty);
+        ports.add_output("tag", &i8);
+
+        HwModuleOp::build_with(
+            &mut builder,
+            &module,
+            "tagged",
+            &ports,
+            &[],
+            "",
+            |builder, _, inputs, outputs| {
+                assert!(EnumConstantOp::build(builder, &state_ty, "Stopped").is_none());
+                let state = EnumConstantOp::build(builder, &state_ty, "Busy").unwrap();
+                outputs.insert("state".to_string(), state.result());
+                let msg = UnionCreateOp::build(builder, &msg_ty, "data", &inputs["data"]).unwrap();
+                outputs.insert("msg".to_string(), msg.result());
+                let tag = UnionExtractOp::build(builder, &msg.result(), "tag").unwrap();
+                outputs.insert("tag".to_string(), tag.result());
+            },
+        )?;
+        assert!(module.op().verify());
+        Ok(())
+    }
+
+    #[test]
+    fn aggregate_constant_and_explode() -> miette::Result<()> {
+        let ctx = OwnedContext::default();
+        hw::dialect().load(&ctx).unwrap();
+
+        let mut builder = OpBuilder::new(&ctx);
+        let module = Module::create(builder.loc());
+
+        let i4 = IntegerType::new(&ctx, 4);
+        let pair_ty = StructType::new(&ctx, [("lo", i4.as_type()), ("hi", i4.as_type())]).unwrap();
+        let pairs_ty = ArrayType::new(&pair_ty, 2);
+
+        let mut ports = ModulePortInfo::default();
+        ports.add_output("pairs", &pairs_ty);
+        ports.add_output("hi", &i4);
+
+        HwModuleOp::build_with(
+            &mut builder,
+            &module,
+            "constants",
+            &ports,
+            &[],
+            "",
+            |builder, _, _, outputs| {
+                let pair = |lo: i64, hi: i64| AggValue::Struct(vec![lo.into(), hi.into()]);
+                let bad = AggValue::Array(vec![pair(1, 2)]);
+                assert!(AggregateConstantOp::build(builder, &pairs_ty.as_type(), &bad).is_err());
+                let too_wide = AggValue::Array(vec![pair(1, 2), pair(3, 16)]);
+                assert!(AggregateConstantOp::build(builder, &pairs_ty.as_type(), &too_wide).is_err());
+
+                let value = AggValue::Array(vec![pair(1, 2), pair(-1, 7)]);
+                let pairs =
+                    AggregateConstantOp::build(builder, &pairs_ty.as_type(), &value).unwrap();
+                outputs.insert("pairs".to_string(), pairs.result());
+
+                let first = ArrayGetOp::with_const_offset(builder, &pairs.result(), 0);
+                let fields = StructExplodeOp::build(builder, &first.result()).unwrap();
+                assert_eq!(fields.fields().len(), 2);
+                outputs.insert("hi".to_string(), fields.field("hi").unwrap());
+            },
+        )?;
+        assert!(module.op().verify());
+        Ok(())
+    }
 }
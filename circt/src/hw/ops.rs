@@ -3,10 +3,13 @@
 
 use crate::{comb::trunc_or_zext_to_clog2, crate_prelude::*};
 
-use num::Num;
+use num::{BigInt, Num, One};
 use std::borrow::Borrow;
 
-use super::{ArrayType, StructType};
+use super::{
+    port_names, ArrayType, EnumFieldAttr, EnumType, HwModuleLike, ParamDeclAttr, StructType,
+    UnionType,
+};
 
 def_operation!(ConstantOp, "hw.constant");
 impl_op_single_result!(ConstantOp);
@@ -54,6 +57,100 @@ impl OutputOp {
 
 def_operation!(InstanceOp, "hw.instance"); // n-args, m-results
 
+impl InstanceOp {
+    /// Instantiate `module`, connecting `inputs` to its input ports in order.
+    /// Parameters of a parametric module are assigned by `parameters`, which are also used to
+    /// resolve the parametric result types of the instance.
+    pub fn build(
+        builder: &mut OpBuilder,
+        instance_name: &str,
+        module: &impl HwModuleLike,
+        inputs: impl IntoIterator<Item = impl Borrow<Value>>,
+        parameters: &[ParamDeclAttr],
+    ) -> Result<Self, Error> {
+        let ctx = builder.context();
+        let ports = module.port_info();
+        let params = ArrayAttr::new::<ParamDeclAttr>(ctx, parameters.iter());
+        let result_types = ports
+            .outputs
+            .iter()
+            .map(|pi| {
+                hw::evaluate_parametric_type(builder.loc(), &params, &pi.ty).ok_or(Error::simple(
+                    format!("Failed to resolve the type of output port `{}`", pi.name),
+                ))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let inputs: Vec<Value> = inputs.into_iter().map(|v| *v.borrow()).collect();
+        if inputs.len() != ports.inputs.len() {
+            return Err(Error::simple(format!(
+                "Expected {} inputs for module `{}` but got {}",
+                ports.inputs.len(),
+                module.module_name(),
+                inputs.len()
+            )));
+        }
+        for (input, pi) in inputs.iter().zip(ports.inputs.iter()) {
+            let ty = hw::evaluate_parametric_type(builder.loc(), &params, &pi.ty).ok_or(
+                Error::simple(format!("Failed to resolve the type of input port `{}`", pi.name)),
+            )?;
+            if input.ty() != ty {
+                return Err(Error::simple(format!(
+                    "Input port `{}` of module `{}` expects {} but got {}",
+                    pi.name,
+                    module.module_name(),
+                    ty,
+                    input.ty()
+                )));
+            }
+        }
+        builder
+            .build_with(|_, state| {
+                state.add_operands::<Value>(&inputs);
+                state.add_results(result_types);
+                state.add_attribute("instanceName", &StringAttr::new(ctx, instance_name));
+                state.add_attribute("moduleName", &SymbolRefAttr::new(ctx, &module.module_name()));
+                state.add_attribute(
+                    "argNames",
+                    &ArrayAttr::new(ctx, port_names(ctx, ports.inputs.iter())),
+                );
+                state.add_attribute(
+                    "resultNames",
+                    &ArrayAttr::new(ctx, port_names(ctx, ports.outputs.iter())),
+                );
+                state.add_attribute("parameters", &params);
+            })
+            .ok_or(Error::simple("OpBuilder failed"))
+    }
+
+    /// Name of this instance.
+    pub fn instance_name(&self) -> String {
+        self.attribute("instanceName")
+            .and_then(|attr| StringAttr::try_from(attr).ok())
+            .map(|attr| attr.get_value())
+            .unwrap()
+    }
+
+    /// Name of the instantiated module.
+    pub fn module_name(&self) -> String {
+        self.attribute("moduleName")
+            .and_then(|attr| SymbolRefAttr::try_from(attr).ok())
+            .map(|attr| attr.value())
+            .unwrap()
+    }
+}
+
+def_operation_single_result!(ParamValueOp, "hw.param.value");
+
+impl ParamValueOp {
+    /// Materialize the value of a parameter expression, e.g. `#hw.param.decl.ref<"WIDTH">`.
+    pub fn build(builder: &mut OpBuilder, ty: &impl Ty, value: &impl Attr) -> Option<Self> {
+        builder.build_with(|_, state| {
+            state.add_attribute("value", value);
+            state.add_result(ty);
+        })
+    }
+}
+
 impl ArrayCreateOp {
     /// Create a new array value.
     pub fn new(
@@ -191,3 +288,161 @@ impl BitcastOp {
             .unwrap()
     }
 }
+
+def_operation_single_result!(EnumConstantOp, "hw.enum.constant");
+
+impl EnumConstantOp {
+    /// Create a constant of an enum type, returns `None` if `field` is not part of the enum.
+    pub fn build(builder: &mut OpBuilder, ty: &EnumType, field: &str) -> Option<Self> {
+        let field = EnumFieldAttr::new(ty, field)?;
+        builder.build_with(|_, state| {
+            state.add_attribute("field", &field);
+            state.add_result(ty);
+        })
+    }
+}
+
+def_operation_single_result!(UnionCreateOp, "hw.union_create");
+
+impl UnionCreateOp {
+    /// Create a union value of type `ty` with `field` set to `value`.
+    pub fn build(
+        builder: &mut OpBuilder,
+        ty: &UnionType,
+        field: &str,
+        value: &Value,
+    ) -> Option<Self> {
+        ty.field(field)?;
+        builder.build_with(|builder, state| {
+            state.add_attribute("field", &StringAttr::new(builder.context(), field));
+            state.add_operand(value);
+            state.add_result(ty);
+        })
+    }
+}
+
+def_operation_single_result!(UnionExtractOp, "hw.union_extract");
+
+impl UnionExtractOp {
+    /// Extract `field` from a union value.
+    pub fn build(builder: &mut OpBuilder, value: &Value, field: &str) -> Option<Self> {
+        let field_ty = UnionType::try_from(value.ty()).ok()?.field(field)?;
+        builder.build_with(|builder, state| {
+            state.add_attribute("field", &StringAttr::new(builder.context(), field));
+            state.add_operand(value);
+            state.add_result(&field_ty);
+        })
+    }
+}
+
+/// A constant value of an integer, array or struct type, see `AggregateConstantOp`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AggValue {
+    Int(BigInt),
+    /// Array elements, in the same order as the operands of `hw.array_create`,
+    ///  i.e. the first element is the one with the highest index.
+    Array(Vec<AggValue>),
+    /// Struct fields, in declaration order.
+    Struct(Vec<AggValue>),
+}
+
+impl AggValue {
+    /// Convert the value into an attribute as used by `hw.aggregate_constant`,
+    ///  after checking that its shape matches `ty`.
+    pub fn to_attr(&self, ty: &Type) -> Result<Attribute, Error> {
+        match self {
+            AggValue::Int(value) => {
+                let int_ty = IntegerType::try_from(ty)?;
+                let bound = BigInt::one() << int_ty.width();
+                if *value >= bound || *value < -(bound >> 1) {
+                    return Err(Error::simple(format!("{} does not fit in type {}", value, ty)));
+                }
+                Ok(IntegerAttr::from_bigint(&int_ty, value.clone()).into())
+            }
+            AggValue::Array(elements) => {
+                let array_ty = ArrayType::try_from(ty)?;
+                if elements.len() != array_ty.size() {
+                    return Err(Error::simple(format!(
+                        "Expected {} elements for type {} but got {}",
+                        array_ty.size(),
+                        ty,
+                        elements.len()
+                    )));
+                }
+                let element_ty = array_ty.element_type().ok_or(Error::IsNone)?;
+                let elements = elements
+                    .iter()
+                    .map(|e| e.to_attr(&element_ty))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(ArrayAttr::new::<Attribute>(&ty.context(), elements).into())
+            }
+            AggValue::Struct(fields) => {
+                let struct_ty = StructType::try_from(ty)?;
+                let field_tys = struct_ty.fields();
+                if fields.len() != field_tys.len() {
+                    return Err(Error::simple(format!(
+                        "Expected {} fields for type {} but got {}",
+                        field_tys.len(),
+                        ty,
+                        fields.len()
+                    )));
+                }
+                let fields = fields
+                    .iter()
+                    .zip(field_tys.iter())
+                    .map(|(f, (_, field_ty))| f.to_attr(field_ty))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(ArrayAttr::new::<Attribute>(&ty.context(), fields).into())
+            }
+        }
+    }
+}
+
+impl From<i64> for AggValue {
+    fn from(value: i64) -> Self {
+        AggValue::Int(value.into())
+    }
+}
+
+def_operation_single_result!(AggregateConstantOp, "hw.aggregate_constant");
+
+impl AggregateConstantOp {
+    /// Create a constant of an array or struct type.
+    pub fn build(builder: &mut OpBuilder, ty: &Type, value: &AggValue) -> Result<Self, Error> {
+        if let AggValue::Int(_) = value {
+            return Err(Error::simple("Integer constants should be built using hw::ConstantOp"));
+        }
+        let fields = value.to_attr(ty)?;
+        builder
+            .build_with(|_, state| {
+                state.add_attribute("fields", &fields);
+                state.add_result(ty);
+            })
+            .ok_or(Error::simple("OpBuilder failed"))
+    }
+}
+
+def_operation!(StructExplodeOp, "hw.struct_explode");
+
+impl StructExplodeOp {
+    /// Explode a struct value into all of its fields.
+    pub fn build(builder: &mut OpBuilder, value: &Value) -> Option<Self> {
+        let ty = StructType::try_from(value.ty()).ok()?;
+        builder.build_with(|_, state| {
+            state.add_operand(value);
+            state.add_results(ty.fields().into_iter().map(|(_, field_ty)| field_ty));
+        })
+    }
+
+    /// The values of all fields, in declaration order.
+    pub fn fields(&self) -> Vec<Value> {
+        (0..self.num_results()).map(|i| self.result_at(i).unwrap()).collect()
+    }
+
+    /// The value of the field named `field_name`.
+    pub fn field(&self, field_name: &str) -> Option<Value> {
+        let ty = StructType::try_from(self.operand(0)?.ty()).ok()?;
+        let pos = ty.fields().iter().position(|(name, _)| name == field_name)?;
+        self.result_at(pos)
+    }
+}
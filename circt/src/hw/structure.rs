@@ -1,5 +1,5 @@
 use crate::crate_prelude::*;
-use hw::{OutputOp, ParamDeclAttr};
+use hw::{AliasType, OutputFileAttr, OutputOp, ParamDeclAttr};
 use itertools::{Either, Itertools};
 use std::{borrow::Borrow, collections::HashMap};
 
@@ -139,6 +139,106 @@ impl HwModuleOp {
     }
 }
 
+/// Accessors shared by `hw.module` and `hw.module.extern`.
+pub trait HwModuleLike: Op {
+    /// Name of the module, i.e. its symbol name.
+    fn module_name(&self) -> String {
+        self.attribute(SymbolTable::symbol_attr_name())
+            .and_then(|attr| StringAttr::try_from(attr).ok())
+            .map(|attr| attr.get_value())
+            .unwrap()
+    }
+
+    /// Type of the module, mapping input port types to output port types.
+    fn function_type(&self) -> FunctionType {
+        self.attribute("function_type")
+            .and_then(|attr| TypeAttr::try_from(attr).ok())
+            .and_then(|attr| FunctionType::try_from(attr.ty()).ok())
+            .unwrap()
+    }
+
+    /// Parameters declared by the module.
+    fn parameters(&self) -> Vec<ParamDeclAttr> {
+        self.attribute("parameters")
+            .and_then(|attr| ArrayAttr::try_from(attr).ok())
+            .map(|params| params.elements().filter_map(|p| p.try_into().ok()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Reconstruct the port information of the module from its attributes.
+    fn port_info(&self) -> ModulePortInfo {
+        let names = |attr_name: &str| -> Vec<String> {
+            self.attribute(attr_name)
+                .and_then(|attr| ArrayAttr::try_from(attr).ok())
+                .map(|names| {
+                    names
+                        .elements()
+                        .map(|name| StringAttr::try_from(name).unwrap().get_value())
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        let fn_ty = self.function_type();
+        let inputs = names("argNames")
+            .iter()
+            .zip(fn_ty.inputs())
+            .map(|(name, ty)| PortInfo::input(name, &ty))
+            .collect();
+        let outputs = names("resultNames")
+            .iter()
+            .zip(fn_ty.results())
+            .map(|(name, ty)| PortInfo::output(name, &ty))
+            .collect();
+        ModulePortInfo { inputs, outputs }
+    }
+}
+
+impl HwModuleLike for HwModuleOp {}
+
+impl HwModuleLike for HwModuleExternOp {}
+
+def_operation!(HwModuleExternOp, "hw.module.extern"; doc = "A module defined outside of the design, e.g. a vendor primitive.");
+
+impl HwModuleExternOp {
+    /// Declare an external module with the given ports at the end of `module`.
+    pub fn build(
+        builder: &mut OpBuilder,
+        module: &Module,
+        name: &str,
+        ports: &ModulePortInfo,
+        parameters: &[ParamDeclAttr],
+    ) -> Result<Self, Error> {
+        builder.set_insertion_point(Some(InsertPoint::BlockEnd(module.body())));
+        builder
+            .build_with(|builder, state| {
+                let ctx = builder.context();
+                state.add_region(&Region::default());
+                state.add_attribute(SymbolTable::symbol_attr_name(), &StringAttr::new(ctx, name));
+                state.add_attribute(
+                    "argNames",
+                    &ArrayAttr::new(ctx, port_names(ctx, ports.inputs.iter())),
+                );
+                state.add_attribute(
+                    "resultNames",
+                    &ArrayAttr::new(ctx, port_names(ctx, ports.outputs.iter())),
+                );
+                state.add_attribute(
+                    "parameters",
+                    &ArrayAttr::new::<ParamDeclAttr>(ctx, parameters.iter()),
+                );
+                state.add_attribute(
+                    "function_type",
+                    &TypeAttr::new(&FunctionType::new(
+                        ctx,
+                        port_types(ports.inputs.iter()),
+                        port_types(ports.outputs.iter()),
+                    )),
+                );
+            })
+            .ok_or(Error::simple("OpBuilder failed"))
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum PortDirection {
     Input = 1,
@@ -218,5 +318,141 @@ impl ModulePortInfo {
     }
 }
 
+def_operation!(TypeScopeOp, "hw.type_scope"; doc = "Holds `hw.typedecl`s, which are emitted as SystemVerilog typedefs.");
+def_operation!(TypedeclOp, "hw.typedecl"; doc = "Declares a named type, referred to by `hw::AliasType`s.");
+
+impl SingleRegionOp for TypeScopeOp {}
+
+impl SingleBlockOp for TypeScopeOp {}
+
+impl TypeScopeOp {
+    pub fn build(builder: &mut OpBuilder, name: &str) -> Option<Self> {
+        let region = Region::default();
+        let block = Block::default();
+        builder.build_with(|builder, state| {
+            region.append_block(&block);
+            state.add_region(&region);
+            state.add_attribute(
+                SymbolTable::symbol_attr_name(),
+                &StringAttr::new(builder.context(), name),
+            );
+        })
+    }
+}
+
+impl TypedeclOp {
+    pub fn build(
+        builder: &mut OpBuilder,
+        name: &str,
+        ty: &impl Ty,
+        verilog_name: Option<&str>,
+    ) -> Option<Self> {
+        builder.build_with(|builder, state| {
+            let ctx = builder.context();
+            state.add_attribute(SymbolTable::symbol_attr_name(), &StringAttr::new(ctx, name));
+            state.add_attribute("type", &TypeAttr::new(ty));
+            if let Some(verilog_name) = verilog_name {
+                state.add_attribute("verilogName", &StringAttr::new(ctx, verilog_name));
+            }
+        })
+    }
+}
+
+/// Declares typedefs in a `hw.type_scope` at the top level of a module and hands out the
+///  `hw::AliasType`s referring to them.
+pub struct TypeScope {
+    op: TypeScopeOp,
+    name: String,
+}
+
+impl TypeScope {
+    /// Create a type scope named `name` at the beginning of `module`, so that its typedefs are
+    ///  emitted before the modules using them.
+    pub fn new(builder: &mut OpBuilder, module: &Module, name: &str) -> Result<Self, Error> {
+        let insert_point = builder.insert_point.clone();
+        builder.set_insertion_point(Some(InsertPoint::BlockStart(module.body())));
+        let op = TypeScopeOp::build(builder, name);
+        builder.set_insertion_point(insert_point);
+        Ok(Self {
+            op: op.ok_or(Error::simple("OpBuilder failed"))?,
+            name: name.to_string(),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn op(&self) -> TypeScopeOp {
+        self.op
+    }
+
+    /// Emit the typedefs of this scope into their own file, e.g. a header, when exporting split
+    ///  Verilog.
+    pub fn set_output_file(&self, file_name: &str) {
+        let ctx = self.op.context();
+        let file_name = StringAttr::new(&ctx, file_name);
+        self.op.set_attribute("output_file", OutputFileAttr::new(file_name, false, false));
+    }
+
+    /// Declare a typedef `name` for `ty` and return the alias type referring to it.
+    pub fn declare(
+        &self,
+        builder: &mut OpBuilder,
+        name: &str,
+        ty: &impl Ty,
+    ) -> Result<AliasType, Error> {
+        let insert_point = builder.insert_point.clone();
+        let body = self.op.first_block().ok_or(Error::IsNone)?;
+        builder.set_insertion_point(Some(InsertPoint::BlockEnd(body)));
+        let decl = TypedeclOp::build(builder, name, ty, None);
+        builder.set_insertion_point(insert_point);
+        decl.ok_or(Error::simple("OpBuilder failed"))?;
+        AliasType::new(&self.name, name, ty.as_type())
+            .ok_or(Error::simple(format!("Failed to create alias type {}::{}", self.name, name)))
+    }
+}
+
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+
+    #[test]
+    fn type_scope_typedefs() -> miette::Result<()> {
+        let ctx = OwnedContext::default();
+        hw::dialect().load(&ctx).unwrap();
+        sv::dialect().load(&ctx).unwrap();
+
+        let mut builder = OpBuilder::new(&ctx);
+        let module = Module::create(builder.loc());
+
+        let i8 = IntegerType::new(&ctx, 8);
+        let scope = TypeScope::new(&mut builder, &module, "pkg")?;
+        let byte_t = scope.declare(&mut builder, "byte_t", &i8)?;
+        assert_eq!(byte_t.scope(), "pkg");
+        assert_eq!(byte_t.name(), "byte_t");
+        assert_eq!(byte_t.canonical_type(), i8.as_type());
+
+        let mut ports = ModulePortInfo::default();
+        ports.add_input("a", &byte_t);
+        ports.add_output("b", &byte_t);
+        HwModuleOp::build_with(
+            &mut builder,
+            &module,
+            "passthrough",
+            &ports,
+            &[],
+            "",
+            |_, _, inputs, outputs| {
+                outputs.insert("b".to_string(), inputs["a"]);
+            },
+        )?;
+        assert!(module.op().verify());
+
+        let mut verilog = String::new();
+        sv::export_verilog(&module, &mut verilog);
+        assert!(verilog.contains("typedef"));
+        assert!(verilog.contains("byte_t"));
+        Ok(())
+    }
+}
@@ -141,6 +141,19 @@ impl HwTy for StructType {}
 
 def_type!(IntType; doc = "parameterized-width integer. Parameterized integer types are equivalent to the MLIR standard integer type: it is signless, and may be any width integer. This type represents the case when the width is a parameter in the HW dialect sense.");
 
+impl IntType {
+    /// Creates an integer type whose width is given by a parameter attribute,
+    /// e.g. `!hw.int<#hw.param.decl.ref<"WIDTH">>`.
+    pub fn new(width: &impl Attr) -> Option<Self> {
+        Self::try_from_raw(unsafe { hwParamIntTypeGet(width.raw()) })
+    }
+
+    /// Returns the attribute describing the width of the type.
+    pub fn width(&self) -> Attribute {
+        Attribute::try_from_raw(unsafe { hwParamIntTypeGetWidthAttr(self.raw()) }).unwrap()
+    }
+}
+
 impl TyIsa for IntType {
     /// If the type is an HW int.
     fn isa(ty: &impl HasRaw<RawType = MlirType>) -> bool {
@@ -193,6 +206,126 @@ impl TyIsa for AliasType {
 }
 impl HwTy for AliasType {}
 
+def_type!(EnumType; doc = "An enumeration of named values, e.g. `!hw.enum<Idle, Busy>`.");
+
+impl EnumType {
+    /// Creates an HW enum type with the given field names.
+    pub fn new(ctx: &Context, fields: impl IntoIterator<Item = impl AsRef<str>>) -> Option<Self> {
+        let fields: Vec<String> = fields.into_iter().map(|f| f.as_ref().to_string()).collect();
+        let fields: Vec<_> = fields.iter().map(|f| StringRef::from_str(f).raw()).collect();
+        Self::try_from_raw(unsafe { hwEnumTypeGet(ctx.raw(), fields.len() as _, fields.as_ptr()) })
+    }
+
+    pub fn num_fields(&self) -> usize {
+        unsafe { hwEnumTypeGetNumFields(self.raw()) }.try_into().unwrap()
+    }
+
+    pub fn field_at(&self, pos: usize) -> Option<String> {
+        if pos >= self.num_fields() {
+            return None;
+        }
+        StringRef::try_from_raw(unsafe { hwEnumTypeGetField(self.raw(), pos as _) })
+            .map(|sr| sr.to_string())
+    }
+
+    /// Get the names of all fields of an enum type.
+    pub fn fields(&self) -> Vec<String> {
+        (0..self.num_fields()).map(|i| self.field_at(i).unwrap()).collect()
+    }
+
+    /// Returns the position of a field, which is also its encoding.
+    pub fn field_index(&self, field_name: &str) -> Option<usize> {
+        self.fields().iter().position(|f| f == field_name)
+    }
+}
+
+impl TyIsa for EnumType {
+    /// If the type is an HW enum.
+    fn isa(ty: &impl HasRaw<RawType = MlirType>) -> bool {
+        unsafe { hwTypeIsAEnumType(ty.raw()) }
+    }
+}
+impl HwTy for EnumType {}
+
+def_type!(UnionType; doc = "A packed union of named fields, e.g. `!hw.union<a: i8, b: i4>`.");
+
+impl UnionType {
+    /// Creates an HW union type in the context associated with the elements.
+    pub fn new<'a, T: Ty>(
+        ctx: &Context,
+        elements: impl IntoIterator<Item = impl Borrow<(&'a str, T)>>,
+    ) -> Option<Self> {
+        let elements: Vec<_> = elements
+            .into_iter()
+            .map(|tup| HWStructFieldInfo {
+                name: Identifier::new(ctx, tup.borrow().0).raw(),
+                type_: tup.borrow().1.raw(),
+            })
+            .collect();
+        Self::try_from_raw(unsafe {
+            hwUnionTypeGet(ctx.raw(), elements.len() as _, elements.as_ptr())
+        })
+    }
+
+    pub fn field(&self, field_name: &str) -> Option<Type> {
+        let field_name = StringRef::from_str(field_name);
+        Type::try_from_raw(unsafe { hwUnionTypeGetField(self.raw(), field_name.raw()) })
+    }
+
+    pub fn field_at(&self, pos: usize) -> Option<(String, Type)> {
+        if pos >= self.num_fields() {
+            return None;
+        }
+        let HWStructFieldInfo { name, type_ } =
+            unsafe { hwUnionTypeGetFieldNum(self.raw(), pos as _) };
+        Some((Identifier::try_from_raw(name)?.to_string(), Type::try_from_raw(type_)?))
+    }
+
+    pub fn num_fields(&self) -> usize {
+        unsafe { hwUnionTypeGetNumFields(self.raw()) }.try_into().unwrap()
+    }
+
+    /// Get the fields of a union type.
+    pub fn fields(&self) -> Vec<(String, Type)> {
+        (0..self.num_fields()).map(|i| self.field_at(i).unwrap()).collect()
+    }
+}
+
+impl TyIsa for UnionType {
+    /// If the type is an HW union.
+    fn isa(ty: &impl HasRaw<RawType = MlirType>) -> bool {
+        unsafe { hwTypeIsAUnionType(ty.raw()) }
+    }
+}
+impl HwTy for UnionType {}
+
+def_type!(UnpackedArrayType; doc = "A fixed-size unpacked array, e.g. `!hw.uarray<4xi8>`.");
+
+impl UnpackedArrayType {
+    /// Creates a fixed-size HW unpacked array type in the context associated with element_type
+    pub fn new(element_type: &impl Ty, size: usize) -> Self {
+        Self::try_from_raw(unsafe { hwUnpackedArrayTypeGet(element_type.raw(), size) }).unwrap()
+    }
+
+    // returns the size of an unpacked array type
+    pub fn size(&self) -> usize {
+        unsafe { hwUnpackedArrayTypeGetSize(self.raw()) }.try_into().unwrap()
+    }
+
+    /// returns the element type of an unpacked array type
+    pub fn element_type(&self) -> Option<Type> {
+        Type::try_from_raw(unsafe { hwUnpackedArrayTypeGetElementType(self.raw()) })
+    }
+}
+
+impl TyIsa for UnpackedArrayType {
+    /// If the type is an HW unpacked array
+    fn isa(ty: &impl HasRaw<RawType = MlirType>) -> bool {
+        unsafe { hwTypeIsAUnpackedArrayType(ty.raw()) }
+    }
+}
+impl HwTy for UnpackedArrayType {}
+
 #[cfg(test)]
 mod tests {
 
@@ -223,4 +356,34 @@ mod tests {
         assert_eq!(type_alias.scope(), scope);
         assert_eq!(type_alias.name(), name);
     }
+
+    #[test]
+    fn hw_enum_union_types() {
+        let ctx = OwnedContext::default();
+        hw::dialect().load(&ctx).unwrap();
+
+        let state = hw::EnumType::new(&ctx, ["Idle", "Busy", "Done"]).unwrap();
+        assert!(hw::EnumType::isa(&state));
+        assert_eq!(state.fields(), ["Idle", "Busy", "Done"]);
+        assert_eq!(state.field_index("Done"), Some(2));
+        assert_eq!(state.field_at(3), None);
+        assert_eq!(state.bitwidth(), Some(2));
+
+        let i8_type = IntegerType::new(&ctx, 8);
+        let i4_type = IntegerType::new(&ctx, 4);
+        let msg = hw::UnionType::new(&ctx, [("data", i8_type.as_type()), ("tag", i4_type.as_type())])
+            .unwrap();
+        assert!(hw::UnionType::isa(&msg));
+        assert!(!hw::StructType::isa(&msg));
+        assert_eq!(msg.num_fields(), 2);
+        assert_eq!(msg.field("tag"), Some(i4_type.as_type()));
+        assert_eq!(msg.fields()[0], ("data".to_string(), i8_type.as_type()));
+        assert_eq!(msg.bitwidth(), Some(8));
+
+        let mem = hw::UnpackedArrayType::new(&i8_type, 16);
+        assert!(hw::UnpackedArrayType::isa(&mem));
+        assert!(!hw::ArrayType::isa(&mem));
+        assert_eq!(mem.size(), 16);
+        assert_eq!(mem.element_type(), Some(i8_type.as_type()));
+    }
 }
@@ -0,0 +1,287 @@
+// Copyright (c) 2022-2023 Kamyar Mohajerani
+
+//! The hwarith dialect provides bit-width aware arithmetic on signed (`si<w>`) and unsigned
+//!  (`ui<w>`) integers: results are wide enough to never overflow, so datapaths need no manual
+//!  sign- or zero-extension. `lower-hwarith-to-hw` (see `hw::register_arith_passes`) lowers it
+//!  to comb.
+//! See https://circt.llvm.org/docs/Dialects/HWArith/ for more details.
+
+use crate::crate_prelude::*;
+use std::cmp::max;
+
+define_dialect!(hwarith);
+
+/// Width and signedness of a hwarith integer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IntInfo {
+    pub width: u32,
+    pub signed: bool,
+}
+
+impl IntInfo {
+    pub fn signed(width: u32) -> Self {
+        Self {
+            width,
+            signed: true,
+        }
+    }
+
+    pub fn unsigned(width: u32) -> Self {
+        Self {
+            width,
+            signed: false,
+        }
+    }
+
+    /// The width and signedness of `ty`, or `None` if it is not a signed or unsigned integer.
+    pub fn of(ty: &impl Ty) -> Option<Self> {
+        let ty = IntegerType::try_from(ty.as_type()).ok()?;
+        if ty.is_signless() {
+            return None;
+        }
+        Some(Self {
+            width: ty.width(),
+            signed: ty.is_signed(),
+        })
+    }
+
+    pub fn to_type(&self, ctx: &Context) -> IntegerType {
+        if self.signed {
+            IntegerType::new_signed(ctx, self.width)
+        } else {
+            IntegerType::new_unsigned(ctx, self.width)
+        }
+    }
+
+    /// Result of `hwarith.add`: one bit wider than the wider operand, signed if any operand is.
+    /// An unsigned operand mixed with a signed one needs an extra bit to be represented as signed.
+    pub fn add(&self, other: &Self) -> Self {
+        match (self.signed, other.signed) {
+            (false, false) => Self::unsigned(max(self.width, other.width) + 1),
+            (true, true) => Self::signed(max(self.width, other.width) + 1),
+            (false, true) => Self::signed(max(self.width + 1, other.width) + 1),
+            (true, false) => Self::signed(max(self.width, other.width + 1) + 1),
+        }
+    }
+
+    /// Result of `hwarith.sub`: as wide as for `add`, but always signed.
+    pub fn sub(&self, other: &Self) -> Self {
+        Self::signed(self.add(other).width)
+    }
+
+    /// Result of `hwarith.mul`: the sum of the operand widths, signed if any operand is.
+    pub fn mul(&self, other: &Self) -> Self {
+        Self {
+            width: self.width + other.width,
+            signed: self.signed || other.signed,
+        }
+    }
+
+    /// Result of `hwarith.div`: as wide as the dividend, plus one bit if the divisor is signed,
+    ///  since e.g. `-128 / -1` overflows `si8`. Signed if any operand is.
+    pub fn div(&self, other: &Self) -> Self {
+        Self {
+            width: self.width + other.signed as u32,
+            signed: self.signed || other.signed,
+        }
+    }
+}
+
+fn operand_info(value: &Value) -> Result<IntInfo, Error> {
+    IntInfo::of(&value.ty()).ok_or(Error::simple(format!(
+        "hwarith operands must be signed or unsigned integers, got {}",
+        value.ty()
+    )))
+}
+
+macro_rules! def_hwarith_binary_operation {
+    ($name:ident, $operation_name:expr, $rule:ident) => {
+        def_operation_single_result!($name, $operation_name);
+
+        impl $name {
+            /// Build the operation with its result type inferred from the operands.
+            pub fn build(builder: &mut OpBuilder, lhs: &Value, rhs: &Value) -> Result<Self, Error> {
+                let ty = operand_info(lhs)?.$rule(&operand_info(rhs)?);
+                builder
+                    .build_with(|builder, state| {
+                        state.add_operand(lhs);
+                        state.add_operand(rhs);
+                        state.add_result(&ty.to_type(builder.context()));
+                    })
+                    .ok_or(Error::simple("OpBuilder failed"))
+            }
+        }
+    };
+}
+
+def_hwarith_binary_operation!(AddOp, "hwarith.add", add);
+def_hwarith_binary_operation!(SubOp, "hwarith.sub", sub);
+def_hwarith_binary_operation!(MulOp, "hwarith.mul", mul);
+def_hwarith_binary_operation!(DivOp, "hwarith.div", div);
+
+def_operation_single_result!(ConstantOp, "hwarith.constant");
+
+impl ConstantOp {
+    /// A constant of the narrowest signed or unsigned type holding `value`, or `None` if `value`
+    ///  is negative and unsigned.
+    pub fn build(builder: &mut OpBuilder, value: i64, signed: bool) -> Option<Self> {
+        if !signed && value < 0 {
+            return None;
+        }
+        let width = if signed {
+            65 - max(value.leading_zeros(), value.leading_ones())
+        } else {
+            max(64 - value.leading_zeros(), 1)
+        };
+        Self::build_typed(builder, value, IntInfo { width, signed })
+    }
+
+    pub fn build_typed(builder: &mut OpBuilder, value: i64, ty: IntInfo) -> Option<Self> {
+        builder.build_with(|builder, state| {
+            let ty = ty.to_type(builder.context());
+            state.add_attribute("rawValue", &IntegerAttr::new(&ty, value));
+            state.add_result(&ty);
+        })
+    }
+}
+
+def_operation_single_result!(CastOp, "hwarith.cast");
+
+impl CastOp {
+    /// Convert `input` to `ty`, truncating or extending according to the signedness of `input`.
+    /// At most one of the input and result types may be signless.
+    pub fn build(builder: &mut OpBuilder, input: &Value, ty: &impl Ty) -> Result<Self, Error> {
+        if IntInfo::of(&input.ty()).is_none() && IntInfo::of(ty).is_none() {
+            return Err(Error::simple("hwarith.cast cannot convert between signless types"));
+        }
+        builder
+            .build_with(|_, state| {
+                state.add_operand(input);
+                state.add_result(ty);
+            })
+            .ok_or(Error::simple("OpBuilder failed"))
+    }
+}
+
+/// Predicate of `hwarith.icmp`. Signedness follows from the operand types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ICmpPredicate {
+    Eq = 0,
+    Ne = 1,
+    Lt = 2,
+    Ge = 3,
+    Le = 4,
+    Gt = 5,
+}
+
+def_operation_single_result!(ICmpOp, "hwarith.icmp");
+
+impl ICmpOp {
+    /// Compare `lhs` and `rhs`, which may differ in width and signedness, yielding an `i1`.
+    pub fn build(
+        builder: &mut OpBuilder,
+        pred: ICmpPredicate,
+        lhs: &Value,
+        rhs: &Value,
+    ) -> Result<Self, Error> {
+        operand_info(lhs)?;
+        operand_info(rhs)?;
+        builder
+            .build_with(|builder, state| {
+                let ctx = builder.context();
+                state.add_operand(lhs);
+                state.add_operand(rhs);
+                let attr = IntegerAttr::new(&IntegerType::new(ctx, 64), pred as i64);
+                state.add_attribute("predicate", &attr);
+                state.add_result(&IntegerType::new(ctx, 1));
+            })
+            .ok_or(Error::simple("OpBuilder failed"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hw::{HwModuleOp, ModulePortInfo};
+
+    #[test]
+    fn width_rules() {
+        let (u4, u8, s4, s8) = (
+            IntInfo::unsigned(4),
+            IntInfo::unsigned(8),
+            IntInfo::signed(4),
+            IntInfo::signed(8),
+        );
+        assert_eq!(u4.add(&u8), IntInfo::unsigned(9));
+        assert_eq!(s4.add(&s8), IntInfo::signed(9));
+        assert_eq!(u8.add(&s4), IntInfo::signed(10));
+        assert_eq!(s8.add(&u4), IntInfo::signed(9));
+        assert_eq!(u4.sub(&u8), IntInfo::signed(9));
+        assert_eq!(u4.mul(&s8), IntInfo::signed(12));
+        assert_eq!(u8.mul(&u4), IntInfo::unsigned(12));
+        assert_eq!(u8.div(&u4), IntInfo::unsigned(8));
+        assert_eq!(s8.div(&s4), IntInfo::signed(9));
+        assert_eq!(u8.div(&s4), IntInfo::signed(9));
+        assert_eq!(s8.div(&u4), IntInfo::signed(8));
+    }
+
+    #[test]
+    fn arith_lowers_to_comb() -> miette::Result<()> {
+        let ctx = OwnedContext::default();
+        hw::dialect().load(&ctx).unwrap();
+        comb::dialect().load(&ctx).unwrap();
+        sv::dialect().load(&ctx).unwrap();
+        dialect().load(&ctx).unwrap();
+        hw::register_arith_passes();
+
+        let mut builder = OpBuilder::new(&ctx);
+        let module = Module::create(builder.loc());
+
+        let i1 = IntegerType::new(&ctx, 1);
+        let i8 = IntegerType::new(&ctx, 8);
+        let i16 = IntegerType::new(&ctx, 16);
+        let mut ports = ModulePortInfo::default();
+        ports.add_input("a", &i8);
+        ports.add_input("b", &i8);
+        ports.add_output("sum", &i16);
+        ports.add_output("lt", &i1);
+
+        HwModuleOp::build_with(
+            &mut builder,
+            &module,
+            "mac",
+            &ports,
+            &[],
+            "",
+            |builder, _, inputs, outputs| {
+                let a = CastOp::build(builder, &inputs["a"], &IntegerType::new_unsigned(&ctx, 8))
+                    .unwrap();
+                let b = CastOp::build(builder, &inputs["b"], &IntegerType::new_signed(&ctx, 8))
+                    .unwrap();
+                let prod = MulOp::build(builder, &a.result(), &b.result()).unwrap();
+                assert_eq!(IntInfo::of(&prod.result().ty()), Some(IntInfo::signed(16)));
+                let k = ConstantOp::build(builder, -3, true).unwrap();
+                assert_eq!(IntInfo::of(&k.result().ty()), Some(IntInfo::signed(3)));
+                assert!(ConstantOp::build(builder, -3, false).is_none());
+                let sum = AddOp::build(builder, &prod.result(), &k.result()).unwrap();
+                assert_eq!(IntInfo::of(&sum.result().ty()), Some(IntInfo::signed(17)));
+                let sum = CastOp::build(builder, &sum.result(), &i16).unwrap();
+                outputs.insert("sum".to_string(), sum.result());
+                let lt =
+                    ICmpOp::build(builder, ICmpPredicate::Lt, &a.result(), &b.result()).unwrap();
+                outputs.insert("lt".to_string(), lt.result());
+                assert!(CastOp::build(builder, &inputs["a"], &i16).is_err());
+            },
+        )?;
+        assert!(module.op().verify());
+
+        let pm = OwnedPassManager::new(&ctx);
+        pm.parse_pass("lower-hwarith-to-hw")?;
+        pm.run(&module)?;
+
+        let mut verilog = String::new();
+        assert!(sv::export_verilog(&module, &mut verilog).is_success());
+        assert!(verilog.contains("module mac"));
+        Ok(())
+    }
+}
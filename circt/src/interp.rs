@@ -0,0 +1,1133 @@
+// Copyright (c) 2022-2023 Kamyar Mohajerani
+
+//! A cycle-accurate reference interpreter for designs in the hw, comb and seq dialects.
+//! Unlike `arc::Simulator`, `Interpreter` needs no lowering or native code: it walks the bodies
+//!  of the HW modules directly and evaluates values of any width as `BigUint`s, with the comb
+//!  semantics of `comb::fold`. Undefined results, e.g. of division by zero, are zero.
+//! Ports, named registers and named wires are exposed as `TracePoint`s for waveform dumping,
+//!  see `vcd::VcdWriter`.
+//! All registers are clocked by `Interpreter::step_clock`, regardless of their clock operand, so
+//!  designs with a single clock domain are modeled exactly.
+
+use crate::analysis::{string_attr, REGISTER_OPS};
+use crate::crate_prelude::*;
+use comb::fold::{mask, Bits};
+use hw::{HwModuleLike, InstanceGraph};
+use num::{BigUint, Zero};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// The value of a signal: an integer, or an aggregate of signals.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Signal {
+    Bits {
+        width: u32,
+        value: BigUint,
+    },
+    /// Elements of an array, starting at index 0.
+    Array(Vec<Signal>),
+    /// Fields of a struct, in declaration order.
+    Struct(Vec<Signal>),
+}
+
+impl Signal {
+    /// An integer of `width` bits, truncating `value`.
+    pub fn bits(width: u32, value: BigUint) -> Self {
+        Self::Bits {
+            value: value & mask(width),
+            width,
+        }
+    }
+
+    /// The all-zero value of `ty`.
+    pub fn zero(ty: &Type) -> Result<Self, Error> {
+        Self::from_bits(ty, &BigUint::zero())
+    }
+
+    /// Unpack `value` into a signal of type `ty`, with the layout used by `hw.bitcast`: the last
+    ///  element of an array and the first field of a struct are the most significant.
+    pub fn from_bits(ty: &Type, value: &BigUint) -> Result<Self, Error> {
+        let ty = canonical_type(ty);
+        if let Ok(ty) = IntegerType::try_from(ty) {
+            return Ok(Self::bits(ty.width(), value.clone()));
+        }
+        if let Ok(ty) = hw::ArrayType::try_from(ty) {
+            let element = ty.element_type().ok_or(Error::IsNone)?;
+            let width = bit_width(&element)?;
+            return (0..ty.size())
+                .map(|i| Self::from_bits(&element, &(value >> (i as u64 * width as u64))))
+                .collect::<Result<_, _>>()
+                .map(Self::Array);
+        }
+        if let Ok(ty) = hw::StructType::try_from(ty) {
+            let mut offset = bit_width(&ty.as_type())?;
+            return ty
+                .fields()
+                .iter()
+                .map(|(_, field)| {
+                    offset -= bit_width(field)?;
+                    Self::from_bits(field, &(value >> offset))
+                })
+                .collect::<Result<_, _>>()
+                .map(Self::Struct);
+        }
+        Err(Error::simple(format!("Unsupported type {}", ty)))
+    }
+
+    /// The number of bits of the signal.
+    pub fn width(&self) -> u32 {
+        match self {
+            Self::Bits { width, .. } => *width,
+            Self::Array(elements) | Self::Struct(elements) => {
+                elements.iter().map(Self::width).sum()
+            }
+        }
+    }
+
+    /// Pack the signal into an integer, the inverse of `from_bits`.
+    pub fn to_bits(&self) -> BigUint {
+        match self {
+            Self::Bits { value, .. } => value.clone(),
+            Self::Array(elements) => pack(elements.iter().rev()),
+            Self::Struct(fields) => pack(fields.iter()),
+        }
+    }
+
+    fn value(&self) -> Result<&BigUint, Error> {
+        match self {
+            Self::Bits { value, .. } => Ok(value),
+            _ => Err(Error::simple("Expected an integer signal")),
+        }
+    }
+
+    fn elements(&self) -> Result<&[Signal], Error> {
+        match self {
+            Self::Array(elements) => Ok(elements),
+            _ => Err(Error::simple("Expected an array signal")),
+        }
+    }
+
+    fn fields(&self) -> Result<&[Signal], Error> {
+        match self {
+            Self::Struct(fields) => Ok(fields),
+            _ => Err(Error::simple("Expected a struct signal")),
+        }
+    }
+
+    fn is_set(&self) -> bool {
+        !self.to_bits().is_zero()
+    }
+}
+
+/// Concatenate signals given from the most significant.
+fn pack<'a>(signals: impl Iterator<Item = &'a Signal>) -> BigUint {
+    signals.fold(BigUint::zero(), |acc, signal| (acc << signal.width()) | signal.to_bits())
+}
+
+fn canonical_type(ty: &Type) -> Type {
+    hw::AliasType::try_from(ty).map(|ty| ty.canonical_type()).unwrap_or(*ty)
+}
+
+fn bit_width(ty: &Type) -> Result<u32, Error> {
+    let ty = canonical_type(ty);
+    if let Ok(ty) = IntegerType::try_from(ty) {
+        Ok(ty.width())
+    } else if let Ok(ty) = hw::ArrayType::try_from(ty) {
+        Ok(ty.size() as u32 * bit_width(&ty.element_type().ok_or(Error::IsNone)?)?)
+    } else if let Ok(ty) = hw::StructType::try_from(ty) {
+        ty.fields().iter().map(|(_, field)| bit_width(field)).sum()
+    } else {
+        Err(Error::simple(format!("Unsupported type {}", ty)))
+    }
+}
+
+/// The value of an integer attribute, truncated to `width` bits.
+fn attr_bits(attr: &Attribute, width: u32) -> Result<BigUint, Error> {
+    IntegerAttr::try_from(*attr)
+        .map(|attr| attr.to_biguint() & mask(width))
+        .map_err(|_| Error::simple(format!("Expected an integer attribute, got {}", attr)))
+}
+
+/// The signal described by the `fields` of a `hw.aggregate_constant`.
+fn attr_signal(attr: &Attribute, ty: &Type) -> Result<Signal, Error> {
+    let ty = canonical_type(ty);
+    if let Ok(ty) = IntegerType::try_from(ty) {
+        return Ok(Signal::Bits {
+            value: attr_bits(attr, ty.width())?,
+            width: ty.width(),
+        });
+    }
+    let elements: Vec<Attribute> =
+        ArrayAttr::try_from(*attr).map_err(|_| Error::IsNone)?.elements().collect();
+    if let Ok(ty) = hw::ArrayType::try_from(ty) {
+        // Like the operands of `hw.array_create`, elements are listed from the highest index.
+        let element = ty.element_type().ok_or(Error::IsNone)?;
+        return elements
+            .iter()
+            .rev()
+            .map(|e| attr_signal(e, &element))
+            .collect::<Result<_, _>>()
+            .map(Signal::Array);
+    }
+    if let Ok(ty) = hw::StructType::try_from(ty) {
+        return elements
+            .iter()
+            .zip(ty.fields())
+            .map(|(e, (_, field))| attr_signal(e, &field))
+            .collect::<Result<_, _>>()
+            .map(Signal::Struct);
+    }
+    Err(Error::simple(format!("Unsupported type {}", ty)))
+}
+
+const COMBINATIONAL_OPS: &[&str] = &[
+    "hw.constant",
+    "hw.aggregate_constant",
+    "hw.bitcast",
+    "hw.wire",
+    "hw.array_create",
+    "hw.array_concat",
+    "hw.array_get",
+    "hw.array_slice",
+    "hw.struct_create",
+    "hw.struct_extract",
+    "hw.struct_inject",
+    "hw.struct_explode",
+    "comb.add",
+    "comb.sub",
+    "comb.mul",
+    "comb.divu",
+    "comb.divs",
+    "comb.modu",
+    "comb.mods",
+    "comb.shl",
+    "comb.shru",
+    "comb.shrs",
+    "comb.and",
+    "comb.or",
+    "comb.xor",
+    "comb.icmp",
+    "comb.concat",
+    "comb.extract",
+    "comb.replicate",
+    "comb.mux",
+    "comb.parity",
+    "sv.read_inout",
+];
+
+/// The reset and reset value operands of a register, if it has a reset.
+fn reset_operands(reg: &Operation) -> Option<(Value, Value)> {
+    let pos = match reg.name().to_string().as_str() {
+        "seq.compreg.ce" => 3,
+        _ => 2,
+    };
+    Some((reg.operand(pos)?, reg.operand(pos + 1)?))
+}
+
+/// A step of the evaluation of a module.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Step {
+    /// Evaluate an operation. An instance is evaluated with all its inputs.
+    Op(Operation),
+    /// Evaluate one output of an instance from only the inputs it depends on combinationally.
+    Output(Operation, usize),
+}
+
+impl Step {
+    fn op(&self) -> Operation {
+        match *self {
+            Self::Op(op) | Self::Output(op, _) => op,
+        }
+    }
+}
+
+/// A module prepared for evaluation.
+struct ModuleModel {
+    inputs: Vec<(String, Type)>,
+    outputs: Vec<(String, Type)>,
+    arguments: Vec<Value>,
+    /// Combinational operations and instances, each after the steps defining its operands.
+    schedule: Vec<Step>,
+    registers: Vec<Operation>,
+    /// For each output, the inputs it depends on combinationally.
+    comb_deps: Vec<Vec<usize>>,
+    /// Operands of the `hw.output`.
+    results: Vec<Value>,
+    /// The values assigned to `sv.wire`s.
+    drivers: HashMap<Value, Value>,
+    /// Ports, named registers and named wires, with the values they carry.
+    traced: Vec<(String, Value)>,
+}
+
+impl ModuleModel {
+    /// Prepare `module`, given the models of the modules it instantiates.
+    fn new(module: &hw::HwModuleOp, design: &HashMap<String, ModuleModel>) -> Result<Self, Error> {
+        let name = module.module_name();
+        let body = module.first_block().ok_or(Error::IsNone)?;
+        let mut ops = vec![];
+        let mut registers = vec![];
+        let mut results = vec![];
+        let mut wires = vec![];
+        let mut drivers = HashMap::new();
+        // The inputs each output of an instance depends on combinationally.
+        let mut instance_deps: HashMap<Operation, &[Vec<usize>]> = HashMap::new();
+        for op in body.operations() {
+            let op_name = op.name().to_string();
+            if op_name == "hw.output" {
+                results = op.operands();
+            } else if op_name == "sv.wire" {
+                wires.push(op);
+            } else if op_name == "sv.assign" {
+                let dest = op.operand(0).ok_or(Error::IsNone)?;
+                drivers.insert(dest, op.operand(1).ok_or(Error::IsNone)?);
+            } else if REGISTER_OPS.contains(&op_name.as_str()) {
+                registers.push(op);
+            } else if let Some(inst) = op.try_into_op::<hw::InstanceOp>() {
+                let child = design
+                    .get(&inst.module_name())
+                    .ok_or(Error::simple(format!("No HW module `{}`", inst.module_name())))?;
+                instance_deps.insert(op, &child.comb_deps);
+                ops.push(op);
+            } else if COMBINATIONAL_OPS.contains(&op_name.as_str()) {
+                ops.push(op);
+            } else {
+                return Err(Error::simple(format!(
+                    "Unsupported operation `{}` in `{}`",
+                    op_name, name
+                )));
+            }
+        }
+
+        let ports = module.port_info();
+        let arguments: Vec<Value> = body.arguments().collect();
+        let name_of = |op: &Operation| string_attr(op, "name");
+        let mut traced: Vec<(String, Value)> = vec![];
+        traced.extend(ports.inputs.iter().map(|pi| pi.name.clone()).zip(arguments.iter().copied()));
+        traced.extend(ports.outputs.iter().map(|pi| pi.name.clone()).zip(results.iter().copied()));
+        let hw_wires = ops.iter().filter(|op| op.name().to_string() == "hw.wire");
+        for op in registers.iter().chain(hw_wires) {
+            if let (Some(op_name), Some(result)) = (name_of(op), op.result_at(0)) {
+                traced.push((op_name, result));
+            }
+        }
+        for wire in &wires {
+            let driver = wire.result_at(0).and_then(|result| drivers.get(&result).copied());
+            match (name_of(wire), driver) {
+                (Some(wire_name), Some(driver)) => traced.push((wire_name, driver)),
+                (wire_name, _) => {
+                    return Err(Error::simple(format!(
+                        "Undriven wire `{}` in `{}`",
+                        wire_name.unwrap_or_default(),
+                        name
+                    )))
+                }
+            }
+        }
+        // Of equally named signals, e.g. a register driving a port of the same name, the first
+        //  is traced.
+        let mut names = HashSet::new();
+        traced.retain(|(name, _)| names.insert(name.clone()));
+
+        // The operands result `pos` of `op` depends on combinationally.
+        let comb_operands = |op: &Operation, pos: usize| -> Vec<Value> {
+            match instance_deps.get(op) {
+                Some(deps) => deps[pos].iter().filter_map(|&i| op.operand(i)).collect(),
+                None => op.operands(),
+            }
+        };
+        // An instance is evaluated once all its inputs are, unless one of its outputs feeds back
+        //  into its inputs, e.g. through a register of the child. Then each output is evaluated
+        //  as soon as the inputs it depends on are.
+        let mut split = HashSet::new();
+        let schedule = loop {
+            let mut steps = vec![];
+            let mut producers = HashMap::new();
+            for op in &ops {
+                steps.push(Step::Op(*op));
+                for (pos, result) in op.results().into_iter().enumerate() {
+                    let step = match split.contains(op) {
+                        true => Step::Output(*op, pos),
+                        false => Step::Op(*op),
+                    };
+                    // Outputs of split instances are evaluated even if only `hw.output` uses them.
+                    if split.contains(op) {
+                        steps.push(step);
+                    }
+                    producers.insert(result, step);
+                }
+            }
+            let operands = |step: &Step| match *step {
+                Step::Op(op) => op.operands(),
+                Step::Output(op, pos) => comb_operands(&op, pos),
+            };
+            match schedule(&steps, &producers, &drivers, operands) {
+                Ok(order) => break order,
+                Err(cycle) => {
+                    let instances: Vec<Operation> = (cycle.iter())
+                        .filter_map(|step| match *step {
+                            Step::Op(op)
+                                if instance_deps.contains_key(&op) && !split.contains(&op) =>
+                            {
+                                Some(op)
+                            }
+                            _ => None,
+                        })
+                        .collect();
+                    if instances.is_empty() {
+                        return Err(Error::simple(format!(
+                            "Combinational loop through `{}` in `{}`",
+                            cycle[0].op().name().to_string(),
+                            name
+                        )));
+                    }
+                    split.extend(instances);
+                }
+            }
+        };
+
+        // Propagate the inputs each value depends on through the schedule, and through the
+        //  resets of asynchronous registers until they no longer change.
+        let resolve = |value: &Value| *drivers.get(value).unwrap_or(value);
+        let mut reach: HashMap<Value, BTreeSet<usize>> =
+            (arguments.iter().enumerate()).map(|(i, arg)| (*arg, BTreeSet::from([i]))).collect();
+        let inputs_of = |reach: &HashMap<Value, BTreeSet<usize>>, values: &[Value]| {
+            (values.iter())
+                .flat_map(|value| reach.get(&resolve(value)).into_iter().flatten().copied())
+                .collect::<BTreeSet<usize>>()
+        };
+        loop {
+            for step in &schedule {
+                let results: Vec<(usize, Value)> = match *step {
+                    Step::Op(op) if split.contains(&op) => vec![],
+                    Step::Op(op) => op.results().into_iter().enumerate().collect(),
+                    Step::Output(op, pos) => vec![(pos, op.result_at(pos).ok_or(Error::IsNone)?)],
+                };
+                for (pos, result) in results {
+                    let inputs = inputs_of(&reach, &comb_operands(&step.op(), pos));
+                    reach.insert(result, inputs);
+                }
+            }
+            let mut changed = false;
+            for reg in registers.iter().filter(|reg| reg.attribute("isAsync").is_some()) {
+                if let Some((reset, reset_value)) = reset_operands(reg) {
+                    let output = reg.result_at(0).ok_or(Error::IsNone)?;
+                    let inputs = inputs_of(&reach, &[reset, reset_value]);
+                    if reach.get(&output) != Some(&inputs) {
+                        reach.insert(output, inputs);
+                        changed = true;
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        let comb_deps = (results.iter())
+            .map(|result| inputs_of(&reach, &[*result]).into_iter().collect())
+            .collect();
+
+        Ok(Self {
+            inputs: ports.inputs.into_iter().map(|pi| (pi.name, pi.ty)).collect(),
+            outputs: ports.outputs.into_iter().map(|pi| (pi.name, pi.ty)).collect(),
+            arguments,
+            schedule,
+            registers,
+            comb_deps,
+            results,
+            drivers,
+            traced,
+        })
+    }
+
+    /// The `hw.instance`s of the module.
+    fn instances(&self) -> impl Iterator<Item = hw::InstanceOp> + '_ {
+        self.schedule.iter().filter_map(|step| match step {
+            Step::Op(op) => op.try_into_op::<hw::InstanceOp>(),
+            Step::Output(..) => None,
+        })
+    }
+
+    /// The value carried by `value`, which differs for the `sv.wire`s driven by `sv.assign`s.
+    fn resolve(&self, value: &Value) -> Value {
+        self.drivers.get(value).copied().unwrap_or(*value)
+    }
+}
+
+/// Order `steps` so that each comes after the producers of its `operands`, or of the values
+///  assigned to them if they are wires. Registers are not producers, as their outputs only change
+///  on the clock. Fails with the steps of a loop, if there is one.
+fn schedule(
+    steps: &[Step],
+    producers: &HashMap<Value, Step>,
+    drivers: &HashMap<Value, Value>,
+    operands: impl Fn(&Step) -> Vec<Value>,
+) -> Result<Vec<Step>, Vec<Step>> {
+    let producer =
+        |operand: Value| producers.get(drivers.get(&operand).unwrap_or(&operand)).copied();
+    let mut order = Vec::with_capacity(steps.len());
+    // `false` while a step is on the stack, `true` once it is scheduled.
+    let mut visited: HashMap<Step, bool> = HashMap::new();
+    for root in steps {
+        if visited.contains_key(root) {
+            continue;
+        }
+        visited.insert(*root, false);
+        let mut stack = vec![(*root, operands(root).into_iter())];
+        while let Some((step, pending)) = stack.last_mut() {
+            let step = *step;
+            match pending.find_map(producer) {
+                Some(producer) => match visited.get(&producer) {
+                    Some(true) => {}
+                    Some(false) => {
+                        let start = stack.iter().position(|(s, _)| *s == producer).unwrap();
+                        return Err(stack[start..].iter().map(|(s, _)| *s).collect());
+                    }
+                    None => {
+                        visited.insert(producer, false);
+                        stack.push((producer, operands(&producer).into_iter()));
+                    }
+                },
+                None => {
+                    visited.insert(step, true);
+                    order.push(step);
+                    stack.pop();
+                }
+            }
+        }
+    }
+    Ok(order)
+}
+
+/// The state of an instance of a module.
+struct InstanceState {
+    module: String,
+    values: HashMap<Value, Signal>,
+    registers: HashMap<Operation, Signal>,
+    children: HashMap<Operation, InstanceState>,
+}
+
+impl InstanceState {
+    fn new(design: &HashMap<String, ModuleModel>, module: &str) -> Result<Self, Error> {
+        let model = &design[module];
+        let mut registers = HashMap::new();
+        for reg in &model.registers {
+            let ty = reg.result_at(0).ok_or(Error::IsNone)?.ty();
+            let init = match reg.attribute("preset") {
+                Some(preset) => Signal::from_bits(&ty, &attr_bits(&preset, bit_width(&ty)?)?)?,
+                None => Signal::zero(&ty)?,
+            };
+            registers.insert(*reg, init);
+        }
+        let children = model
+            .instances()
+            .map(|inst| Ok((Operation::from(inst), Self::new(design, &inst.module_name())?)))
+            .collect::<Result<_, Error>>()?;
+        Ok(Self {
+            module: module.to_string(),
+            values: HashMap::new(),
+            registers,
+            children,
+        })
+    }
+
+    fn get(&self, value: &Value) -> Result<&Signal, Error> {
+        self.values
+            .get(value)
+            .ok_or(Error::simple(format!("Value {} of `{}` was not evaluated", value, self.module)))
+    }
+
+    fn operand(&self, op: &Operation, pos: usize) -> Result<&Signal, Error> {
+        self.get(&op.operand(pos).ok_or(Error::IsNone)?)
+    }
+
+    /// The reset operand and value of a register, if it has a reset.
+    fn reset(&self, reg: &Operation) -> Result<Option<(&Signal, &Signal)>, Error> {
+        match reset_operands(reg) {
+            Some((reset, reset_value)) => Ok(Some((self.get(&reset)?, self.get(&reset_value)?))),
+            None => Ok(None),
+        }
+    }
+
+    /// The value of `reg` after the next clock edge.
+    fn next_state(&self, reg: &Operation) -> Result<Signal, Error> {
+        if let Some((reset, reset_value)) = self.reset(reg)? {
+            if reset.is_set() {
+                return Ok(reset_value.clone());
+            }
+        }
+        if reg.name().to_string() == "seq.compreg.ce" && !self.operand(reg, 2)?.is_set() {
+            return Ok(self.registers[reg].clone());
+        }
+        Ok(self.operand(reg, 0)?.clone())
+    }
+
+    /// Evaluate the module with `inputs`, returning its outputs. Unless `commit` is set,
+    ///  asynchronous resets only take effect for this evaluation.
+    fn eval(
+        &mut self,
+        design: &HashMap<String, ModuleModel>,
+        inputs: Vec<Signal>,
+        commit: bool,
+    ) -> Result<Vec<Signal>, Error> {
+        let model = &design[&self.module];
+        let saved = (!commit).then(|| self.registers.clone());
+        // Asynchronous resets take effect immediately, which may change what they depend on.
+        for _ in 0..=model.registers.len() {
+            self.values.extend(model.arguments.iter().copied().zip(inputs.iter().cloned()));
+            for reg in &model.registers {
+                let output = reg.result_at(0).ok_or(Error::IsNone)?;
+                self.values.insert(output, self.registers[reg].clone());
+            }
+            for step in &model.schedule {
+                match *step {
+                    Step::Op(op) => {
+                        let results = self.eval_op(design, &op, commit)?;
+                        self.values.extend(op.results().into_iter().zip(results));
+                    }
+                    Step::Output(inst, pos) => {
+                        let output = self.eval_instance_output(design, &inst, pos)?;
+                        self.values.insert(inst.result_at(pos).ok_or(Error::IsNone)?, output);
+                    }
+                }
+            }
+            let mut changed = false;
+            for reg in model.registers.iter().filter(|reg| reg.attribute("isAsync").is_some()) {
+                let reset_value = self
+                    .reset(reg)?
+                    .filter(|(reset, _)| reset.is_set())
+                    .map(|(_, reset_value)| reset_value.clone());
+                if let Some(reset_value) = reset_value {
+                    if self.registers[reg] != reset_value {
+                        self.registers.insert(*reg, reset_value);
+                        changed = true;
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        if let Some(saved) = saved {
+            self.registers = saved;
+        }
+        model.results.iter().map(|result| self.get(result).cloned()).collect()
+    }
+
+    /// Evaluate output `pos` of the instance `inst` from the inputs it depends on. The other
+    ///  inputs keep their last values, or zero before the first evaluation.
+    fn eval_instance_output(
+        &mut self,
+        design: &HashMap<String, ModuleModel>,
+        inst: &Operation,
+        pos: usize,
+    ) -> Result<Signal, Error> {
+        let model = &design[&self.module];
+        let args: Vec<Signal> = (inst.operands().iter())
+            .map(|v| match self.values.get(&model.resolve(v)) {
+                Some(signal) => Ok(signal.clone()),
+                None => Signal::zero(&v.ty()),
+            })
+            .collect::<Result<_, _>>()?;
+        let child = self.children.get_mut(inst).ok_or(Error::IsNone)?;
+        child.eval(design, args, false)?.into_iter().nth(pos).ok_or(Error::IsNone)
+    }
+
+    fn eval_op(
+        &mut self,
+        design: &HashMap<String, ModuleModel>,
+        op: &Operation,
+        commit: bool,
+    ) -> Result<Vec<Signal>, Error> {
+        let name = op.name().to_string();
+        let model = &design[&self.module];
+        let args: Vec<Signal> = op
+            .operands()
+            .iter()
+            .map(|v| self.get(&model.resolve(v)).cloned())
+            .collect::<Result<_, _>>()?;
+        let result_ty = op.result_at(0).map(|result| result.ty());
+        let width = match &result_ty {
+            Some(ty) => bit_width(ty)?,
+            None => 0,
+        };
+        let attr = |attr_name: &str| {
+            op.attribute(attr_name)
+                .ok_or(Error::simple(format!("Missing attribute `{}` of `{}`", attr_name, name)))
+        };
+        let int_attr = |attr_name: &str| -> Result<i64, Error> {
+            Ok(IntegerAttr::try_from(attr(attr_name)?).map_err(|_| Error::IsNone)?.value())
+        };
+        let field_index = |ty: &Type| -> Result<usize, Error> {
+            if let Ok(index) = int_attr("fieldIndex") {
+                return Ok(index as usize);
+            }
+            let field = StringAttr::try_from(attr("field")?).map_err(|_| Error::IsNone)?;
+            hw::StructType::try_from(canonical_type(ty))
+                .map_err(|_| Error::simple("Expected a struct type"))?
+                .fields()
+                .iter()
+                .position(|(name, _)| *name == field.get_value())
+                .ok_or(Error::simple(format!("No field `{}`", field.get_value())))
+        };
+        let index = |signal: &Signal| -> Result<usize, Error> {
+            Ok(usize::try_from(signal.value()?).unwrap_or(usize::MAX))
+        };
+        let bits = |value: BigUint| vec![Signal::bits(width, value)];
+
+        Ok(match name.as_str() {
+            "hw.constant" => bits(attr_bits(&attr("value")?, width)?),
+            "hw.aggregate_constant" => {
+                vec![attr_signal(
+                    &attr("fields")?,
+                    &result_ty.ok_or(Error::IsNone)?,
+                )?]
+            }
+            "hw.bitcast" => {
+                vec![Signal::from_bits(
+                    &result_ty.ok_or(Error::IsNone)?,
+                    &args[0].to_bits(),
+                )?]
+            }
+            "hw.wire" | "sv.read_inout" => vec![args[0].clone()],
+            "hw.array_create" => vec![Signal::Array(args.into_iter().rev().collect())],
+            "hw.array_concat" => {
+                let mut elements = vec![];
+                for arg in args.iter().rev() {
+                    elements.extend_from_slice(arg.elements()?);
+                }
+                vec![Signal::Array(elements)]
+            }
+            "hw.array_get" => match args[0].elements()?.get(index(&args[1])?) {
+                Some(element) => vec![element.clone()],
+                None => vec![Signal::zero(&result_ty.ok_or(Error::IsNone)?)?],
+            },
+            "hw.array_slice" => {
+                let ty = hw::ArrayType::try_from(result_ty.ok_or(Error::IsNone)?)
+                    .map_err(|_| Error::IsNone)?;
+                let zero = Signal::zero(&ty.element_type().ok_or(Error::IsNone)?)?;
+                let elements = args[0].elements()?;
+                let low = index(&args[1])?;
+                let slice = (0..ty.size())
+                    .map(|i| elements.get(low.saturating_add(i)).unwrap_or(&zero).clone())
+                    .collect();
+                vec![Signal::Array(slice)]
+            }
+            "hw.struct_create" => vec![Signal::Struct(args)],
+            "hw.struct_extract" => {
+                let field = field_index(&op.operand(0).ok_or(Error::IsNone)?.ty())?;
+                vec![args[0].fields()?[field].clone()]
+            }
+            "hw.struct_inject" => {
+                let field = field_index(&op.operand(0).ok_or(Error::IsNone)?.ty())?;
+                let mut fields = args[0].fields()?.to_vec();
+                fields[field] = args[1].clone();
+                vec![Signal::Struct(fields)]
+            }
+            "hw.struct_explode" => args[0].fields()?.to_vec(),
+            "hw.instance" => {
+                let child = self.children.get_mut(op).ok_or(Error::IsNone)?;
+                child.eval(design, args, commit)?
+            }
+            "comb.mux" => vec![if args[0].is_set() {
+                args[1].clone()
+            } else {
+                args[2].clone()
+            }],
+            _ => {
+                let operands: Vec<Bits> = args
+                    .iter()
+                    .map(|arg| Ok(Bits::new(arg.width(), arg.value()?.clone())))
+                    .collect::<Result<_, Error>>()?;
+                let result = comb::fold::eval(op, &operands).unwrap_or_else(|| Bits::zero(width));
+                bits(result.value().clone())
+            }
+        })
+    }
+
+    /// Update all registers of this instance and its children to their next state.
+    fn clock(&mut self, design: &HashMap<String, ModuleModel>) -> Result<(), Error> {
+        let model = &design[&self.module];
+        let next = model
+            .registers
+            .iter()
+            .map(|reg| Ok((*reg, self.next_state(reg)?)))
+            .collect::<Result<Vec<_>, Error>>()?;
+        self.registers.extend(next);
+        for child in self.children.values_mut() {
+            child.clock(design)?;
+        }
+        Ok(())
+    }
+
+    /// Call `f` with the scope, name and value of each traced signal of this instance, then
+    ///  recurse into the child instances.
+    fn visit_traced(
+        &self,
+        design: &HashMap<String, ModuleModel>,
+        scope: &mut Vec<String>,
+        f: &mut dyn FnMut(&[String], &str, &Signal),
+    ) {
+        let model = &design[&self.module];
+        for (name, value) in &model.traced {
+            if let Some(signal) = self.values.get(value) {
+                f(scope, name, signal);
+            }
+        }
+        for inst in model.instances() {
+            if let Some(child) = self.children.get(&Operation::from(inst)) {
+                scope.push(inst.instance_name());
+                child.visit_traced(design, scope, f);
+                scope.pop();
+            }
+        }
+    }
+}
+
+/// A signal that can be traced in waveforms: a port, or a register or wire with a `name`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TracePoint {
+    /// The top module followed by the names of the instances containing the signal.
+    pub scope: Vec<String>,
+    pub name: String,
+    pub width: u32,
+}
+
+impl TracePoint {
+    /// The hierarchical name of the signal, e.g. `top.sub.count`.
+    pub fn path(&self) -> String {
+        format!("{}.{}", self.scope.join("."), self.name)
+    }
+}
+
+/// A cycle-accurate interpreter of an HW module and the modules it instantiates.
+pub struct Interpreter {
+    design: HashMap<String, ModuleModel>,
+    state: InstanceState,
+    inputs: Vec<Signal>,
+    outputs: Vec<Signal>,
+}
+
+impl Interpreter {
+    /// Prepare the top-level HW module `top` of `module` for evaluation, with all inputs and
+    ///  registers zero. Fails if the module hierarchy contains a cycle, or any module reachable
+    ///  from `top` contains an unsupported operation or a combinational loop.
+    pub fn new(module: &Module, top: &str) -> Result<Self, Error> {
+        let graph = InstanceGraph::new(module)?;
+        let reachable = graph.reachable(&[top])?;
+        // Children come first, as the scheduling of instances depends on their models.
+        let mut design = HashMap::new();
+        for id in graph.post_order()? {
+            if !reachable.contains(&id) {
+                continue;
+            }
+            let node = graph.node(id);
+            let op = (node.op.try_into_op::<hw::HwModuleOp>())
+                .ok_or(Error::simple(format!("No HW module `{}`", node.name)))?;
+            let model = ModuleModel::new(&op, &design)?;
+            design.insert(node.name.clone(), model);
+        }
+        let state = InstanceState::new(&design, top)?;
+        let inputs =
+            design[top].inputs.iter().map(|(_, ty)| Signal::zero(ty)).collect::<Result<_, _>>()?;
+        let mut interp = Self {
+            design,
+            state,
+            inputs,
+            outputs: vec![],
+        };
+        interp.eval()?;
+        Ok(interp)
+    }
+
+    fn top(&self) -> &ModuleModel {
+        &self.design[&self.state.module]
+    }
+
+    fn port_index(ports: &[(String, Type)], port: &str) -> Option<usize> {
+        ports.iter().position(|(name, _)| name == port)
+    }
+
+    /// Set the input `port` to `value`, truncated to the width of the port. Aggregates are
+    ///  unpacked as by `Signal::from_bits`. Takes effect with the next `eval` or `step_clock`.
+    pub fn set_input(&mut self, port: &str, value: BigUint) -> Result<(), Error> {
+        let index = Self::port_index(&self.top().inputs, port)
+            .ok_or(Error::simple(format!("No input port `{}`", port)))?;
+        self.inputs[index] = Signal::from_bits(&self.top().inputs[index].1, &value)?;
+        Ok(())
+    }
+
+    /// The current value of the output `port`, packed into an integer.
+    pub fn get_output(&self, port: &str) -> Result<BigUint, Error> {
+        self.get_output_signal(port).map(Signal::to_bits)
+    }
+
+    /// The current value of the output `port`.
+    pub fn get_output_signal(&self, port: &str) -> Result<&Signal, Error> {
+        Self::port_index(&self.top().outputs, port)
+            .map(|index| &self.outputs[index])
+            .ok_or(Error::simple(format!("No output port `{}`", port)))
+    }
+
+    /// The signals that can be traced, with the signals of a module before those of its
+    ///  instances.
+    pub fn trace_points(&self) -> Vec<TracePoint> {
+        let mut points = vec![];
+        let mut scope = vec![self.state.module.clone()];
+        self.state.visit_traced(&self.design, &mut scope, &mut |scope, name, signal| {
+            points.push(TracePoint {
+                scope: scope.to_vec(),
+                name: name.to_string(),
+                width: signal.width(),
+            })
+        });
+        points
+    }
+
+    /// The current values of the `trace_points`, in the same order.
+    pub fn trace_values(&self) -> Vec<BigUint> {
+        let mut values = vec![];
+        let mut scope = vec![self.state.module.clone()];
+        self.state.visit_traced(&self.design, &mut scope, &mut |_, _, signal| {
+            values.push(signal.to_bits())
+        });
+        values
+    }
+
+    /// Propagate the inputs and register values through the combinational logic.
+    pub fn eval(&mut self) -> Result<(), Error> {
+        self.outputs = self.state.eval(&self.design, self.inputs.clone(), true)?;
+        Ok(())
+    }
+
+    /// Run one clock cycle: update every register from the current inputs, then re-evaluate.
+    pub fn step_clock(&mut self) -> Result<(), Error> {
+        self.eval()?;
+        self.state.clock(&self.design)?;
+        self.eval()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hw::{HwModuleExternOp, HwModuleOp, ModulePortInfo};
+    use num::One;
+
+    #[test]
+    fn counter_with_instance() -> miette::Result<()> {
+        let ctx = OwnedContext::default();
+        hw::dialect().load(&ctx).unwrap();
+        comb::dialect().load(&ctx).unwrap();
+        seq::dialect().load(&ctx).unwrap();
+
+        let mut builder = OpBuilder::new(&ctx);
+        let module = Module::create(builder.loc());
+
+        let i1 = IntegerType::new(&ctx, 1);
+        let i8 = IntegerType::new(&ctx, 8);
+        let mut ports = ModulePortInfo::default();
+        ports.add_input("a", &i8);
+        ports.add_input("b", &i8);
+        ports.add_output("sum", &i8);
+        let adder = HwModuleOp::build_with(
+            &mut builder,
+            &module,
+            "adder",
+            &ports,
+            &[],
+            "",
+            |builder, _, inputs, outputs| {
+                let sum = comb::AddOp::build(builder, &inputs["a"], &inputs["b"]).unwrap();
+                outputs.insert("sum".to_string(), sum.result());
+            },
+        )?;
+
+        let pair = hw::StructType::new(&ctx, [("hi", i8), ("lo", i8)]).unwrap();
+        let mut ports = ModulePortInfo::default();
+        ports.add_input("clk", &i1);
+        ports.add_input("rst", &i1);
+        ports.add_input("en", &i1);
+        ports.add_input("inc", &i8);
+        ports.add_output("count", &i8);
+        ports.add_output("first", &i8);
+        ports.add_output("pair", &pair);
+        HwModuleOp::build_with(
+            &mut builder,
+            &module,
+            "counter",
+            &ports,
+            &[],
+            "",
+            |builder, _, inputs, outputs| {
+                let zero = hw::ConstantOp::build(builder, 8, 0).result();
+                let count = seq::CompRegClockEnabledOp::build(
+                    builder,
+                    "count",
+                    &inputs["inc"],
+                    &inputs["clk"],
+                    &inputs["en"],
+                    Some(&inputs["rst"]),
+                    Some(&zero),
+                )
+                .unwrap();
+                let next = hw::InstanceOp::build(
+                    builder,
+                    "add",
+                    &adder,
+                    [count.output(), inputs["inc"]],
+                    &[],
+                )
+                .unwrap();
+                count.set_input(&next.result_at(0).unwrap());
+                let array_ty = hw::ArrayType::new(&i8, 2).as_type();
+                let array =
+                    hw::ArrayCreateOp::new(builder, &array_ty, [count.output(), inputs["inc"]]);
+                let first = hw::ArrayGetOp::with_const_offset(builder, &array.result(), 0);
+                let pair = hw::StructCreateOp::build(
+                    builder,
+                    &pair.as_type(),
+                    [count.output(), first.result()],
+                );
+                outputs.insert("count".to_string(), count.output());
+                outputs.insert("first".to_string(), first.result());
+                outputs.insert("pair".to_string(), pair.result());
+            },
+        )?;
+
+        let mut interp = Interpreter::new(&module, "counter")?;
+        interp.set_input("inc", BigUint::from(3u32))?;
+        interp.set_input("en", BigUint::one())?;
+        interp.eval()?;
+        // The first operand of `hw.array_create` is the highest index.
+        assert_eq!(interp.get_output("first")?, BigUint::from(3u32));
+        for _ in 0..4 {
+            interp.step_clock()?;
+        }
+        assert_eq!(interp.get_output("count")?, BigUint::from(12u32));
+        assert_eq!(interp.get_output("pair")?, BigUint::from(0x0C03u32));
+        interp.set_input("en", BigUint::zero())?;
+        interp.step_clock()?;
+        assert_eq!(interp.get_output("count")?, BigUint::from(12u32));
+        interp.set_input("rst", BigUint::one())?;
+        interp.step_clock()?;
+        assert_eq!(interp.get_output("count")?, BigUint::zero());
+        assert!(interp.set_input("clock", BigUint::one()).is_err());
+        assert!(Interpreter::new(&module, "missing").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn feedback_through_instance() -> miette::Result<()> {
+        let ctx = OwnedContext::default();
+        hw::dialect().load(&ctx).unwrap();
+        comb::dialect().load(&ctx).unwrap();
+        seq::dialect().load(&ctx).unwrap();
+
+        let mut builder = OpBuilder::new(&ctx);
+        let module = Module::create(builder.loc());
+
+        let i1 = IntegerType::new(&ctx, 1);
+        let i8 = IntegerType::new(&ctx, 8);
+        let mut ports = ModulePortInfo::default();
+        ports.add_input("clk", &i1);
+        ports.add_input("d", &i8);
+        ports.add_output("q", &i8);
+        ports.add_output("next", &i8);
+        let incr = HwModuleOp::build_with(
+            &mut builder,
+            &module,
+            "incr",
+            &ports,
+            &[],
+            "",
+            |builder, _, inputs, outputs| {
+                let one = hw::ConstantOp::build(builder, 8, 1).result();
+                let next = comb::AddOp::build(builder, &inputs["d"], &one).unwrap();
+                let q =
+                    seq::CompRegOp::build(builder, "q", &next.result(), &inputs["clk"], None, None)
+                        .unwrap();
+                outputs.insert("q".to_string(), q.output());
+                outputs.insert("next".to_string(), next.result());
+            },
+        )?;
+
+        let mut ports = ModulePortInfo::default();
+        ports.add_input("clk", &i1);
+        ports.add_output("count", &i8);
+        ports.add_output("next", &i8);
+        // The registered output `q` of the instance feeds back into its input `d`, and the
+        //  combinational output `next` as well in `comb_loop`.
+        for (name, feedback) in [("counter", 0), ("comb_loop", 1)] {
+            HwModuleOp::build_with(
+                &mut builder,
+                &module,
+                name,
+                &ports,
+                &[],
+                "",
+                |builder, _, inputs, outputs| {
+                    let zero = hw::ConstantOp::build(builder, 8, 0).result();
+                    let c = hw::InstanceOp::build(builder, "c", &incr, [inputs["clk"], zero], &[])
+                        .unwrap();
+                    c.set_operand(1, &c.result_at(feedback).unwrap());
+                    outputs.insert("count".to_string(), c.result_at(0).unwrap());
+                    outputs.insert("next".to_string(), c.result_at(1).unwrap());
+                },
+            )?;
+        }
+
+        let mut interp = Interpreter::new(&module, "counter")?;
+        assert_eq!(interp.get_output("next")?, BigUint::one());
+        for _ in 0..3 {
+            interp.step_clock()?;
+        }
+        assert_eq!(interp.get_output("count")?, BigUint::from(3u32));
+        assert_eq!(interp.get_output("next")?, BigUint::from(4u32));
+        assert!(Interpreter::new(&module, "comb_loop").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn recursive_hierarchy() -> miette::Result<()> {
+        let ctx = OwnedContext::default();
+        hw::dialect().load(&ctx).unwrap();
+
+        let mut builder = OpBuilder::new(&ctx);
+        let module = Module::create(builder.loc());
+
+        let i8 = IntegerType::new(&ctx, 8);
+        let mut ports = ModulePortInfo::default();
+        ports.add_input("a", &i8);
+        ports.add_output("b", &i8);
+        let ext = HwModuleExternOp::build(&mut builder, &module, "ext", &ports, &[])?;
+        let mut inst = None;
+        let a = HwModuleOp::build_with(
+            &mut builder,
+            &module,
+            "a",
+            &ports,
+            &[],
+            "",
+            |builder, _, inputs, outputs| {
+                let e = hw::InstanceOp::build(builder, "e", &ext, [inputs["a"]], &[]).unwrap();
+                outputs.insert("b".to_string(), e.result_at(0).unwrap());
+                inst = Some(e);
+            },
+        )?;
+        HwModuleOp::build_with(
+            &mut builder,
+            &module,
+            "b",
+            &ports,
+            &[],
+            "",
+            |builder, _, inputs, outputs| {
+                let a = hw::InstanceOp::build(builder, "a", &a, [inputs["a"]], &[]).unwrap();
+                outputs.insert("b".to_string(), a.result_at(0).unwrap());
+            },
+        )?;
+        // `a` instantiates `b`, which instantiates `a`.
+        inst.unwrap().set_attribute("moduleName", SymbolRefAttr::new(&ctx, "b"));
+        assert!(Interpreter::new(&module, "a").is_err());
+        Ok(())
+    }
+}
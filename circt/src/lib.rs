@@ -1,19 +1,48 @@
 #[macro_use]
 pub(crate) mod macros;
 
+pub mod aig;
+pub mod analysis;
+#[cfg(feature = "arc")]
+pub mod arc;
 pub mod builtin;
+#[cfg(feature = "calyx")]
+pub mod calyx;
 pub mod cf;
 pub mod comb;
+#[cfg(feature = "debug")]
+pub mod debug;
+pub mod equiv;
 pub mod error;
 pub mod esi;
 pub mod firrtl;
 pub mod fsm;
 pub mod func;
+pub mod handshake;
 pub mod hw;
+pub mod hwarith;
+pub mod interp;
+#[cfg(feature = "llhd")]
+pub mod llhd;
+#[cfg(feature = "ltl")]
+pub mod ltl;
 pub mod mlir;
+#[cfg(feature = "moore")]
+pub mod moore;
+#[cfg(feature = "msft")]
+pub mod msft;
+#[cfg(feature = "om")]
+pub mod om;
+pub mod pipeline;
 pub mod seq;
 pub mod sv;
+#[cfg(feature = "systemc")]
+pub mod systemc;
+pub mod vcd;
+#[cfg(feature = "verif")]
+pub mod verif;
 pub mod wrap_raw;
+pub mod yosys;
 
 pub mod prelude {
     pub use crate::error::*;
@@ -125,4 +154,30 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn load_optional_dialects() {
+        let ctx = OwnedContext::default();
+        #[cfg(feature = "llhd")]
+        llhd::dialect().load(&ctx).unwrap();
+        #[cfg(feature = "moore")]
+        moore::dialect().load(&ctx).unwrap();
+        #[cfg(feature = "msft")]
+        msft::dialect().load(&ctx).unwrap();
+        #[cfg(feature = "calyx")]
+        calyx::dialect().load(&ctx).unwrap();
+        #[cfg(feature = "arc")]
+        arc::dialect().load(&ctx).unwrap();
+        #[cfg(feature = "verif")]
+        verif::dialect().load(&ctx).unwrap();
+        #[cfg(feature = "ltl")]
+        ltl::dialect().load(&ctx).unwrap();
+        #[cfg(feature = "om")]
+        om::dialect().load(&ctx).unwrap();
+        #[cfg(feature = "debug")]
+        debug::dialect().load(&ctx).unwrap();
+        #[cfg(feature = "systemc")]
+        systemc::dialect().load(&ctx).unwrap();
+        let _ = &ctx;
+    }
 }
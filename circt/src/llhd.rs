@@ -0,0 +1,13 @@
+// Copyright (c) 2022-2023 Kamyar Mohajerani
+
+//! The LLHD dialect models behavioural and structural descriptions of digital circuits, as
+//!  found in VHDL and SystemVerilog: entities, processes, signals and delays.
+//! See https://circt.llvm.org/docs/Dialects/LLHD/ for more details.
+
+use circt_sys::llhdRegisterPasses;
+
+define_dialect!(llhd);
+
+pub fn register_passes() {
+    unsafe { llhdRegisterPasses() }
+}
@@ -0,0 +1,7 @@
+// Copyright (c) 2022-2023 Kamyar Mohajerani
+
+//! The LTL dialect expresses linear temporal logic sequences and properties, as used by
+//!  SystemVerilog assertions.
+//! See https://circt.llvm.org/docs/Dialects/LTL/ for more details.
+
+define_dialect!(ltl);
@@ -5,7 +5,7 @@
 
 use crate::crate_prelude::*;
 use circt_sys::*;
-use num::Num;
+use num::{BigUint, Num};
 use std::{
     borrow::Borrow,
     fmt::{Debug, Display},
@@ -40,6 +40,12 @@ impl Debug for Attribute {
     }
 }
 
+impl PartialEq for Attribute {
+    fn eq(&self, other: &Self) -> bool {
+        unsafe { mlirAttributeEqual(self.raw(), other.raw()) }
+    }
+}
+
 impl AttrIsa for Attribute {
     fn isa(_: &impl Attr) -> bool {
         true
@@ -92,6 +98,29 @@ impl ArrayAttr {
         })
         .expect("ArrayAttr::new received null")
     }
+
+    /// Returns the number of elements stored in the array attribute.
+    pub fn len(&self) -> usize {
+        unsafe { mlirArrayAttrGetNumElements(self.raw()) as _ }
+    }
+
+    /// Checks whether the array attribute has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns pos-th element stored in the array attribute.
+    pub fn element(&self, pos: usize) -> Option<Attribute> {
+        if pos >= self.len() {
+            return None;
+        }
+        Attribute::try_from_raw(unsafe { mlirArrayAttrGetElement(self.raw(), pos as _) })
+    }
+
+    /// Get an iterator over the elements of the array attribute.
+    pub fn elements(&self) -> impl Iterator<Item = Attribute> + '_ {
+        (0..self.len()).map(move |i| self.element(i).unwrap())
+    }
 }
 
 def_attr!(DictionaryAttr [Dictionary]);
@@ -136,6 +165,33 @@ impl IntegerAttr {
     pub fn from_bigint(ty: &impl Ty, value: impl Num + ToString) -> Self {
         Self::from_str(ty, value.to_string().as_str())
     }
+
+    /// Returns the type of the integer attribute.
+    pub fn ty(&self) -> Type {
+        Type::from_raw(unsafe { mlirAttributeGetType(self.raw()) })
+    }
+
+    /// Returns the value of the integer attribute, which must fit into 64 bits.
+    pub fn value(&self) -> i64 {
+        unsafe { mlirIntegerAttrGetValueInt(self.raw()) }
+    }
+
+    /// Returns the value of the integer attribute of any width, as an unsigned integer.
+    pub fn to_biguint(&self) -> BigUint {
+        let mut value = String::new();
+        {
+            let formatter = FormatterCallback::new(&mut value);
+            unsafe {
+                mlirIntegerAttrPrintValue(
+                    self.raw(),
+                    false,
+                    formatter.callback(),
+                    formatter.user_data(),
+                )
+            };
+        }
+        value.parse().unwrap()
+    }
 }
 
 def_attr!(OpaqueAttr [Opaque]);
@@ -176,6 +232,13 @@ impl SymbolRefAttr {
         let symbol = StringRef::from_str(symbol);
         Self::try_from_raw(unsafe { mlirFlatSymbolRefAttrGet(ctx.raw(), symbol.raw()) }).unwrap()
     }
+
+    /// Returns the referenced symbol as a string. For nested references, this is the root symbol.
+    pub fn value(&self) -> String {
+        StringRef::try_from_raw(unsafe { mlirSymbolRefAttrGetRootReference(self.raw()) })
+            .map(|sr| sr.to_string())
+            .unwrap()
+    }
 }
 
 def_attr!(TypeAttr [Type]);
@@ -35,6 +35,22 @@ impl Block {
         Operation::try_from_raw(unsafe { mlirBlockGetTerminator(self.raw()) })
     }
 
+    /// Returns the first operation in the block or `None` if the block is empty.
+    pub fn first_op(&self) -> Option<Operation> {
+        Operation::try_from_raw(unsafe { mlirBlockGetFirstOperation(self.raw()) })
+    }
+
+    /// Get an iterator over the operations in the block.
+    /// The iterator must not be used while operations are being erased from the block.
+    pub fn operations(&self) -> impl Iterator<Item = Operation> {
+        std::iter::successors(self.first_op(), |op| op.next())
+    }
+
+    /// Returns the block immediately following this block in its parent region.
+    pub fn next(&self) -> Option<Self> {
+        Self::try_from_raw(unsafe { mlirBlockGetNextInRegion(self.raw()) })
+    }
+
     /// Detach a block from the owning region and assume ownership.
     pub fn detach(self) {
         unsafe { mlirBlockDetach(self.raw()) }
@@ -176,6 +192,11 @@ impl Region {
         Block::try_from_raw(unsafe { mlirRegionGetFirstBlock(self.raw()) })
     }
 
+    /// Get an iterator over the blocks in the region.
+    pub fn blocks(&self) -> impl Iterator<Item = Block> {
+        std::iter::successors(self.first_block(), |block| block.next())
+    }
+
     /// Returns the region immediately following the given region in its parent operation.
     pub fn next(&self) -> Option<Self> {
         Self::try_from_raw(unsafe { mlirRegionGetNextInOperation(self.raw()) })
@@ -47,6 +47,25 @@ impl Location {
         let name = StringRef::from_str(name).raw();
         Self::from_raw(unsafe { mlirLocationNameGet(ctx.raw(), name, child_loc) })
     }
+
+    /// Filename, line and column of a file-line-column location, or `None` for other kinds of
+    ///  locations.
+    pub fn file_line_col(&self) -> Option<(String, usize, usize)> {
+        if !unsafe { mlirLocationIsFileLineCol(self.raw()) } {
+            return None;
+        }
+        let filename =
+            StringRef::try_from_raw(unsafe { mlirFileLineColLocGetFilename(self.raw()) })?;
+        let line = unsafe { mlirFileLineColLocGetLine(self.raw()) };
+        let col = unsafe { mlirFileLineColLocGetColumn(self.raw()) };
+        Some((filename.to_string(), line as usize, col as usize))
+    }
+
+    /// Emit an error diagnostic at this location to the handlers of its context.
+    pub fn emit_error(&self, message: &str) {
+        let message = std::ffi::CString::new(message.replace('\0', "")).unwrap();
+        unsafe { mlirEmitError(self.raw(), message.as_ptr()) }
+    }
 }
 
 impl_mlir_print!(Location);
@@ -52,6 +52,34 @@ impl Operation {
     }
 }
 
+impl PartialEq for Operation {
+    fn eq(&self, other: &Self) -> bool {
+        self.same_as(other)
+    }
+}
+
+impl Eq for Operation {}
+
+impl std::hash::Hash for Operation {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.raw().ptr.hash(state)
+    }
+}
+
+impl std::fmt::Display for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.print(f, false);
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.print(f, true);
+        Ok(())
+    }
+}
+
 pub trait Op: WrapRawPtr<RawType = MlirOperation> {
     /// Gets the name of the operation as an identifier.
     fn name(&self) -> Identifier {
@@ -90,6 +118,21 @@ pub trait Op: WrapRawPtr<RawType = MlirOperation> {
         Self::try_from_raw(unsafe { mlirOperationClone(self.raw()) }).unwrap()
     }
 
+    /// Gets the operation that owns this operation, returning `None` if the operation is not owned.
+    fn parent_op(&self) -> Option<Operation> {
+        Operation::try_from_raw(unsafe { mlirOperationGetParentOperation(self.raw()) })
+    }
+
+    /// Returns the number of regions attached to the operation.
+    fn num_regions(&self) -> usize {
+        unsafe { mlirOperationGetNumRegions(self.raw()) as _ }
+    }
+
+    /// Returns all regions attached to the operation.
+    fn regions(&self) -> Vec<Region> {
+        (0..self.num_regions()).filter_map(|i| self.region(i)).collect()
+    }
+
     /// Returns pos-th region attached to the operation.
     fn region(&self, pos: usize) -> Option<Region> {
         Region::try_from_raw(unsafe { mlirOperationGetRegion(self.raw(), pos.try_into().unwrap()) })
@@ -126,6 +169,11 @@ pub trait Op: WrapRawPtr<RawType = MlirOperation> {
         unsafe { mlirOperationSetOperand(self.raw(), pos.try_into().unwrap(), new_value.raw()) }
     }
 
+    /// Returns all operands of the operation.
+    fn operands(&self) -> Vec<Value> {
+        (0..self.num_operands()).filter_map(|i| self.operand(i)).collect()
+    }
+
     /// Returns the number of results of the operation.
     fn num_results(&self) -> usize {
         unsafe { mlirOperationGetNumResults(self.raw()).try_into().unwrap() }
@@ -136,6 +184,11 @@ pub trait Op: WrapRawPtr<RawType = MlirOperation> {
         Value::try_from_raw(unsafe { mlirOperationGetResult(self.raw(), pos.try_into().unwrap()) })
     }
 
+    /// Returns all results of the operation.
+    fn results(&self) -> Vec<Value> {
+        (0..self.num_results()).filter_map(|i| self.result_at(i)).collect()
+    }
+
     /// Returns the number of successor blocks of the operation.
     fn num_successors(&self) -> usize {
         unsafe { mlirOperationGetNumSuccessors(self.raw()).try_into().unwrap() }
@@ -232,6 +285,25 @@ pub trait Op: WrapRawPtr<RawType = MlirOperation> {
         unsafe { mlirOperationMoveBefore(self.raw(), other.raw()) }
     }
 
+    /// Visit this operation and all operations nested in its regions, in pre-order.
+    /// `f` must not erase or move the visited operations.
+    fn walk(&self, f: &mut dyn FnMut(Operation)) {
+        f(Operation::from_raw(self.raw()));
+        for region in self.regions() {
+            for block in region.blocks() {
+                for op in block.operations() {
+                    op.walk(f);
+                }
+            }
+        }
+    }
+
+    /// Removes the operation from its block and destroys it, along with all nested operations.
+    /// All uses of its results must have been removed beforehand.
+    fn erase(self) {
+        unsafe { mlirOperationDestroy(self.raw()) }
+    }
+
     /// Checks whether two operation handles point to the same operation.
     /// This does *not* perform deep comparison.
     fn same_as(&self, other: &impl Op) -> bool {
@@ -371,6 +443,15 @@ impl OperationState {
         self.add_regions_raw(&regions.to_raw_vec())
     }
 
+    /// Add a region with a single block to the operation, returning the block.
+    pub fn add_body(&mut self) -> Block {
+        let region = Region::default();
+        let block = Block::default();
+        region.append_block(&block);
+        self.add_region(&region);
+        block
+    }
+
     pub fn build<T: NamedOp>(&mut self) -> Option<T> {
         T::create(self)
     }
@@ -91,6 +91,22 @@ impl TyIsa for IndexType {
     }
 }
 
+def_type!(NoneType);
+
+impl NoneType {
+    /// Creates a none type in the given context. The type is owned by the context.
+    pub fn new(ctx: &Context) -> Self {
+        Self::try_from_raw(unsafe { mlirNoneTypeGet(ctx.raw()) }).unwrap()
+    }
+}
+
+impl TyIsa for NoneType {
+    /// Checks whether the given type is a none type.
+    fn isa(ty: &impl HasRaw<RawType = MlirType>) -> bool {
+        unsafe { mlirTypeIsANone(ty.raw()) }
+    }
+}
+
 def_type!(IntegerType);
 
 impl IntegerType {
@@ -98,10 +114,35 @@ impl IntegerType {
         Self::try_from_raw(unsafe { mlirIntegerTypeGet(ctx.raw(), width as _) }).unwrap()
     }
 
+    /// Creates a signed integer type, e.g. `si8`, of the given bitwidth.
+    pub fn new_signed(ctx: &Context, width: u32) -> Self {
+        Self::try_from_raw(unsafe { mlirIntegerTypeSignedGet(ctx.raw(), width as _) }).unwrap()
+    }
+
+    /// Creates an unsigned integer type, e.g. `ui8`, of the given bitwidth.
+    pub fn new_unsigned(ctx: &Context, width: u32) -> Self {
+        Self::try_from_raw(unsafe { mlirIntegerTypeUnsignedGet(ctx.raw(), width as _) }).unwrap()
+    }
+
     /// Returns the bitwidth of an integer type.
     pub fn width(&self) -> u32 {
         unsafe { mlirIntegerTypeGetWidth(self.raw()) as _ }
     }
+
+    /// Checks whether the integer type is signless, e.g. `i8`.
+    pub fn is_signless(&self) -> bool {
+        unsafe { mlirIntegerTypeIsSignless(self.raw()) }
+    }
+
+    /// Checks whether the integer type is signed, e.g. `si8`.
+    pub fn is_signed(&self) -> bool {
+        unsafe { mlirIntegerTypeIsSigned(self.raw()) }
+    }
+
+    /// Checks whether the integer type is unsigned, e.g. `ui8`.
+    pub fn is_unsigned(&self) -> bool {
+        unsafe { mlirIntegerTypeIsUnsigned(self.raw()) }
+    }
 }
 
 impl TyIsa for IntegerType {
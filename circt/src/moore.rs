@@ -0,0 +1,7 @@
+// Copyright (c) 2022-2023 Kamyar Mohajerani
+
+//! The Moore dialect represents SystemVerilog constructs as produced by the Moore frontend,
+//!  before lowering to the core dialects.
+//! See https://circt.llvm.org/docs/Dialects/Moore/ for more details.
+
+define_dialect!(moore);
@@ -0,0 +1,351 @@
+// Copyright (c) 2022-2023 Kamyar Mohajerani
+
+//! The MSFT dialect covers device-specific concerns: physical placement of instances and
+//!  emission of placement constraints.
+//! Placements are attached to dynamic instances, i.e. instances at a specific point of the
+//!  hierarchy below a top-level module, and exported as TCL for the vendor tools.
+//! See https://circt.llvm.org/docs/Dialects/MSFT/ for more details.
+
+use crate::crate_prelude::*;
+use circt_sys::msftRegisterPasses;
+use hw::{HwModuleLike, InnerSymbolTable, InstanceOp, OutputFileAttr};
+
+define_dialect!(msft);
+
+pub fn register_passes() {
+    unsafe { msftRegisterPasses() }
+}
+
+/// Name of the file the TCL placement script is emitted to.
+pub const TCL_FILE_NAME: &str = "placements.tcl";
+
+/// Run CIRCT's TCL exporter on `module` and return the placement script.
+/// `tops` are the names of the top-level modules whose instance hierarchies are exported. MSFT
+///  passes must have been registered with `register_passes`.
+pub fn export_tcl(ctx: &Context, module: &Module, tops: &[&str]) -> Result<String, Error> {
+    let pm = OwnedPassManager::new(ctx);
+    pm.parse_pass("msft-lower-instances")?.parse_pass(&format!(
+        "msft-export-tcl{{tops={} tcl-file={}}}",
+        tops.join(","),
+        TCL_FILE_NAME
+    ))?;
+    pm.run(module)?;
+
+    module
+        .body()
+        .operations()
+        .filter(|op| op.name().to_string() == "sv.verbatim")
+        .filter(|op| {
+            op.attribute("output_file")
+                .and_then(|attr| OutputFileAttr::try_from(attr).ok())
+                .map_or(false, |file| file.file_name().ends_with(TCL_FILE_NAME))
+        })
+        .filter_map(|op| StringAttr::try_from(op.attribute("format_string")?).ok())
+        .map(|text| text.get_value())
+        .reduce(|script, text| script + &text)
+        .ok_or(Error::simple("MSFT did not emit a TCL script"))
+}
+
+/// Kind of a device primitive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrimitiveType {
+    M20K,
+    DSP,
+    FF,
+}
+
+impl PrimitiveType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PrimitiveType::M20K => "M20K",
+            PrimitiveType::DSP => "DSP",
+            PrimitiveType::FF => "FF",
+        }
+    }
+}
+
+/// The location of a primitive on the device, i.e. a `#msft.physloc`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PhysLocation {
+    pub prim: PrimitiveType,
+    pub x: u64,
+    pub y: u64,
+    /// Index of the primitive within the tile.
+    pub num: u64,
+}
+
+impl PhysLocation {
+    pub fn new(prim: PrimitiveType, x: u64, y: u64, num: u64) -> Self {
+        Self { prim, x, y, num }
+    }
+
+    pub fn attr(&self, ctx: &Context) -> Attribute {
+        Attribute::parse(
+            ctx,
+            &format!("#msft.physloc<{}, {}, {}, {}>", self.prim.name(), self.x, self.y, self.num),
+        )
+        .unwrap()
+    }
+}
+
+/// An inclusive rectangle of the device, i.e. a `#msft.physical_bounds`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PhysicalBounds {
+    pub x_min: u64,
+    pub x_max: u64,
+    pub y_min: u64,
+    pub y_max: u64,
+}
+
+impl PhysicalBounds {
+    pub fn attr(&self, ctx: &Context) -> Attribute {
+        Attribute::parse(
+            ctx,
+            &format!(
+                "#msft.physical_bounds<x: [{}, {}], y: [{}, {}]>",
+                self.x_min, self.x_max, self.y_min, self.y_max
+            ),
+        )
+        .unwrap()
+    }
+}
+
+def_operation!(InstanceHierarchyOp, "msft.instance.hierarchy"; doc = "The root of the dynamic instances below a top-level module.");
+
+impl SingleRegionOp for InstanceHierarchyOp {}
+
+impl SingleBlockOp for InstanceHierarchyOp {}
+
+impl InstanceHierarchyOp {
+    /// Create the hierarchy of `top` at the end of `module`. `with_fn` is called with the
+    ///  insertion point inside the hierarchy, to add `DynamicInstanceOp`s.
+    pub fn build_with(
+        builder: &mut OpBuilder,
+        module: &Module,
+        top: &impl HwModuleLike,
+        with_fn: impl FnOnce(&mut OpBuilder),
+    ) -> Result<Self, Error> {
+        builder.set_insertion_point(Some(InsertPoint::BlockEnd(module.body())));
+        let mut body = None;
+        let hierarchy: Self = builder
+            .build_with(|builder, state| {
+                body = Some(state.add_body());
+                state.add_attribute(
+                    "topModuleRef",
+                    &SymbolRefAttr::new(builder.context(), &top.module_name()),
+                );
+            })
+            .ok_or(Error::simple("OpBuilder failed"))?;
+        builder.set_insertion_point(Some(InsertPoint::BlockEnd(body.ok_or(Error::IsNone)?)));
+        with_fn(builder);
+        builder.set_insertion_point(Some(InsertPoint::BlockEnd(module.body())));
+        Ok(hierarchy)
+    }
+}
+
+def_operation!(DynamicInstanceOp, "msft.instance.dynamic"; doc = "An instance at a specific point of the instance hierarchy, holding its placements.");
+
+impl SingleRegionOp for DynamicInstanceOp {}
+
+impl SingleBlockOp for DynamicInstanceOp {}
+
+impl DynamicInstanceOp {
+    /// Create the dynamic instance of `instance`, which is instantiated in `parent`, at the
+    ///  current insertion point: inside an `InstanceHierarchyOp`, or inside the dynamic instance
+    ///  of `parent`. The instance is given an inner symbol if it has none. `with_fn` is called with
+    ///  the insertion point inside the dynamic instance, to add placements and nested instances.
+    pub fn build_with(
+        builder: &mut OpBuilder,
+        parent: &impl HwModuleLike,
+        instance: &InstanceOp,
+        with_fn: impl FnOnce(&mut OpBuilder),
+    ) -> Result<Self, Error> {
+        let mut symbols = InnerSymbolTable::new(parent)?;
+        let sym = symbols.get_or_create(instance, &instance.instance_name());
+        let insert_point = builder.insert_point.clone();
+        let mut body = None;
+        let dyn_inst: Self = builder
+            .build_with(|builder, state| {
+                body = Some(state.add_body());
+                state.add_attribute("instanceRef", &symbols.inner_ref(builder.context(), &sym));
+            })
+            .ok_or(Error::simple("OpBuilder failed"))?;
+        builder.set_insertion_point(Some(InsertPoint::BlockEnd(body.ok_or(Error::IsNone)?)));
+        with_fn(builder);
+        builder.set_insertion_point(insert_point);
+        Ok(dyn_inst)
+    }
+}
+
+/// Add `sub_path`, the path to a primitive from the dynamic instance, e.g. `|r`, to `state`.
+fn add_sub_path(builder: &OpBuilder, state: &mut OperationState, sub_path: Option<&str>) {
+    if let Some(sub_path) = sub_path {
+        state.add_attribute("subPath", &StringAttr::new(builder.context(), sub_path));
+    }
+}
+
+def_operation!(PDPhysLocationOp, "msft.pd.location"; doc = "Places a primitive of the enclosing dynamic instance.");
+
+impl PDPhysLocationOp {
+    pub fn build(
+        builder: &mut OpBuilder,
+        loc: &PhysLocation,
+        sub_path: Option<&str>,
+    ) -> Option<Self> {
+        builder.build_with(|builder, state| {
+            state.add_attribute("loc", &loc.attr(builder.context()));
+            add_sub_path(builder, state, sub_path);
+        })
+    }
+}
+
+def_operation!(PDRegPhysLocationOp, "msft.pd.reg_location"; doc = "Places each bit of a register of the enclosing dynamic instance.");
+
+impl PDRegPhysLocationOp {
+    /// Place the bits of the register, least significant first. Bits with no location are left
+    ///  to the placer.
+    pub fn build(
+        builder: &mut OpBuilder,
+        ty: &impl Ty,
+        locs: &[Option<PhysLocation>],
+    ) -> Option<Self> {
+        builder.build_with(|builder, state| {
+            let locs: Vec<String> = locs
+                .iter()
+                .map(|loc| match loc {
+                    Some(loc) => format!("<{}, {}, {}>", loc.x, loc.y, loc.num),
+                    None => "*".to_string(),
+                })
+                .collect();
+            let attr = Attribute::parse(
+                builder.context(),
+                &format!("#msft.location_vec<{}, [{}]>", ty.as_type(), locs.join(", ")),
+            )
+            .unwrap();
+            state.add_attribute("locs", &attr);
+        })
+    }
+}
+
+def_operation!(DeclPhysicalRegionOp, "msft.physical_region"; doc = "Declares a named region of the device, made of one or more rectangles.");
+
+impl DeclPhysicalRegionOp {
+    /// Declare the region `sym_name` at the end of `module`.
+    pub fn build(
+        builder: &mut OpBuilder,
+        module: &Module,
+        sym_name: &str,
+        bounds: &[PhysicalBounds],
+    ) -> Option<Self> {
+        let insert_point = builder.insert_point.clone();
+        builder.set_insertion_point(Some(InsertPoint::BlockEnd(module.body())));
+        let region = builder.build_with(|builder, state| {
+            let ctx = builder.context();
+            state.add_attribute(SymbolTable::symbol_attr_name(), &StringAttr::new(ctx, sym_name));
+            let bounds: Vec<Attribute> = bounds.iter().map(|b| b.attr(ctx)).collect();
+            state.add_attribute("bounds", &ArrayAttr::new::<Attribute>(ctx, bounds));
+        });
+        builder.set_insertion_point(insert_point);
+        region
+    }
+}
+
+def_operation!(PDPhysRegionOp, "msft.pd.physregion"; doc = "Constrains the enclosing dynamic instance to a declared physical region.");
+
+impl PDPhysRegionOp {
+    pub fn build(
+        builder: &mut OpBuilder,
+        region: &DeclPhysicalRegionOp,
+        sub_path: Option<&str>,
+    ) -> Option<Self> {
+        let name = StringAttr::try_from(region.attribute(SymbolTable::symbol_attr_name())?)
+            .ok()?
+            .get_value();
+        builder.build_with(|builder, state| {
+            state.add_attribute("physRegionRef", &SymbolRefAttr::new(builder.context(), &name));
+            add_sub_path(builder, state, sub_path);
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hw::{HwModuleOp, ModulePortInfo};
+
+    #[test]
+    fn place_and_export_tcl() -> miette::Result<()> {
+        let ctx = OwnedContext::default();
+        hw::dialect().load(&ctx).unwrap();
+        seq::dialect().load(&ctx).unwrap();
+        sv::dialect().load(&ctx).unwrap();
+        dialect().load(&ctx).unwrap();
+        register_passes();
+        let mut builder = OpBuilder::new(&ctx);
+        let module = Module::create(builder.loc());
+
+        let i1 = IntegerType::new(&ctx, 1);
+        let i8 = IntegerType::new(&ctx, 8);
+        let mut ports = ModulePortInfo::default();
+        ports.add_input("clk", &i1);
+        ports.add_input("a", &i8);
+        ports.add_output("b", &i8);
+
+        let leaf = HwModuleOp::build_with(
+            &mut builder,
+            &module,
+            "leaf",
+            &ports,
+            &[],
+            "",
+            |builder, _, inputs, outputs| {
+                let r =
+                    seq::CompRegOp::build(builder, "r", &inputs["a"], &inputs["clk"], None, None)
+                        .unwrap();
+                outputs.insert("b".to_string(), r.output());
+            },
+        )?;
+        let mut inst = None;
+        let top = HwModuleOp::build_with(
+            &mut builder,
+            &module,
+            "top",
+            &ports,
+            &[],
+            "",
+            |builder, _, inputs, outputs| {
+                let l = InstanceOp::build(builder, "l", &leaf, [inputs["clk"], inputs["a"]], &[])
+                    .unwrap();
+                outputs.insert("b".to_string(), l.result_at(0).unwrap());
+                inst = Some(l);
+            },
+        )?;
+
+        let region = DeclPhysicalRegionOp::build(
+            &mut builder,
+            &module,
+            "corner",
+            &[PhysicalBounds {
+                x_min: 0,
+                x_max: 10,
+                y_min: 0,
+                y_max: 10,
+            }],
+        )
+        .unwrap();
+        InstanceHierarchyOp::build_with(&mut builder, &module, &top, |builder| {
+            DynamicInstanceOp::build_with(builder, &top, &inst.unwrap(), |builder| {
+                let loc = PhysLocation::new(PrimitiveType::FF, 1, 2, 0);
+                PDPhysLocationOp::build(builder, &loc, Some("|r")).unwrap();
+                PDPhysRegionOp::build(builder, &region, None).unwrap();
+            })
+            .unwrap();
+        })?;
+        assert!(module.op().verify());
+
+        let tcl = export_tcl(&ctx, &module, &["top"])?;
+        assert!(tcl.contains("set_location_assignment"));
+        assert!(tcl.contains("FF_X1_Y2_N0"));
+        Ok(())
+    }
+}
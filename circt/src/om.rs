@@ -0,0 +1,7 @@
+// Copyright (c) 2022-2023 Kamyar Mohajerani
+
+//! The OM (object model) dialect describes classes and objects carrying design metadata,
+//!  which can be evaluated outside of the hardware description.
+//! See https://circt.llvm.org/docs/Dialects/OM/ for more details.
+
+define_dialect!(om);
@@ -0,0 +1,363 @@
+// Copyright (c) 2022-2023 Kamyar Mohajerani
+
+//! The pipeline dialect describes pipelined datapaths. An unscheduled pipeline is a single block
+//!  of operations which a scheduling pass partitions into stages; a scheduled pipeline has one
+//!  block per stage, each ending in a `pipeline.stage` that moves to the next stage.
+//! Values crossing stage boundaries are registered explicitly through the `regs` of a stage, or
+//!  implicitly by using them in a later stage and running `pipeline-explicit-regs`.
+//!  `lower-pipeline-to-hw` then materializes the stage registers and their valid bits.
+//! See https://circt.llvm.org/docs/Dialects/Pipeline/ for more details.
+
+use crate::crate_prelude::*;
+use circt_sys::*;
+use std::borrow::Borrow;
+
+define_dialect!(pipeline);
+
+pub fn register_passes() {
+    unsafe { registerPipelinePasses() }
+}
+
+pub mod passes {
+    use crate::*;
+
+    /// Makes values crossing stage boundaries explicit stage registers.
+    pub fn explicit_regs() -> Pass {
+        Pass::try_from_raw(unsafe { pipelineCreateExplicitRegsPass() }).unwrap()
+    }
+
+    pub fn register_explicit_regs() {
+        unsafe { pipelineRegisterExplicitRegsPass() }
+    }
+
+    /// Lowers scheduled pipelines to HW, inserting the stage registers and valid bits.
+    pub fn lower_pipeline_to_hw() -> Pass {
+        Pass::try_from_raw(unsafe { pipelineCreatePipelineToHWPass() }).unwrap()
+    }
+
+    pub fn register_lower_pipeline_to_hw() {
+        unsafe { pipelineRegisterPipelineToHWPass() }
+    }
+}
+
+/// Control signals of a pipeline.
+#[derive(Debug, Clone, Copy)]
+pub struct PipelineControl {
+    pub clock: Value,
+    pub reset: Value,
+    /// Asserted when the inputs are valid.
+    pub go: Value,
+    /// Holds the contents of all stages while asserted.
+    pub stall: Option<Value>,
+}
+
+macro_rules! def_pipeline_operation {
+    ($name:ident, $operation_name:expr; doc = $doc:tt) => {
+        def_operation!($name, $operation_name; doc = $doc);
+
+        impl SingleRegionOp for $name {}
+
+        impl $name {
+            fn build_pipeline(
+                builder: &mut OpBuilder,
+                inputs: &[Value],
+                control: &PipelineControl,
+                output_types: &[Type],
+            ) -> Result<Self, Error> {
+                let region = Region::default();
+                let entry = Block::default();
+                region.append_block(&entry);
+                builder
+                    .build_with(|builder, state| {
+                        let ctx = builder.context();
+                        for input in inputs {
+                            entry.add_argument(&input.ty(), builder.loc());
+                        }
+                        entry.add_argument(&IntegerType::new(ctx, 1), builder.loc());
+                        state.add_region(&region);
+                        state.add_operands::<Value>(inputs.iter());
+                        state.add_operands::<Value>(control.stall.iter());
+                        state.add_operands::<Value>([control.clock, control.reset, control.go]);
+                        let segments = [inputs.len() as i32, control.stall.is_some() as i32, 1, 1, 1];
+                        let segments = Attribute::from_raw(unsafe {
+                            mlirDenseI32ArrayGet(ctx.raw(), segments.len() as _, segments.as_ptr())
+                        });
+                        state.add_attribute("operand_segment_sizes", &segments);
+                        state.add_results(output_types.iter().copied());
+                        state.add_result(&IntegerType::new(ctx, 1));
+                    })
+                    .ok_or(Error::simple("OpBuilder failed"))
+            }
+
+            /// The block of the first stage, whose arguments are the pipeline inputs followed by
+            ///  their valid bit.
+            pub fn entry_stage(&self) -> Block {
+                self.first_block().unwrap()
+            }
+
+            /// The data outputs of the pipeline.
+            pub fn outputs(&self) -> Vec<Value> {
+                let mut results = self.results();
+                results.pop();
+                results
+            }
+
+            /// Asserted when the outputs are valid.
+            pub fn done(&self) -> Value {
+                self.results().pop().unwrap()
+            }
+        }
+    };
+}
+
+def_pipeline_operation!(UnscheduledPipelineOp, "pipeline.unscheduled"; doc = "A pipeline whose body has not been partitioned into stages yet.");
+
+impl SingleBlockOp for UnscheduledPipelineOp {}
+
+impl UnscheduledPipelineOp {
+    /// Create a pipeline at the current insertion point. `with_fn` receives the pipeline inputs
+    ///  and their valid bit and returns the outputs; the `pipeline.return` is added here.
+    pub fn build_with(
+        builder: &mut OpBuilder,
+        inputs: &[Value],
+        control: &PipelineControl,
+        output_types: &[Type],
+        with_fn: impl FnOnce(&mut OpBuilder, &[Value], Value) -> Vec<Value>,
+    ) -> Result<Self, Error> {
+        let pipeline = Self::build_pipeline(builder, inputs, control, output_types)?;
+        let insert_point = builder.insert_point.clone();
+        let entry = pipeline.entry_stage();
+        let mut args: Vec<Value> = entry.arguments().collect();
+        let valid = args.pop().ok_or(Error::IsNone)?;
+        builder.set_insertion_point(Some(InsertPoint::BlockEnd(entry)));
+        let outputs = with_fn(builder, &args, valid);
+        ReturnOp::build(builder, outputs.iter(), &valid).ok_or(Error::IsNone)?;
+        builder.set_insertion_point(insert_point);
+        pipeline.verify().then_some(pipeline).ok_or(Error::simple(format!(
+            "pipeline.unscheduled verification failed. {:?}",
+            &pipeline
+        )))
+    }
+}
+
+def_pipeline_operation!(ScheduledPipelineOp, "pipeline.scheduled"; doc = "A pipeline with one block per stage.");
+
+impl ScheduledPipelineOp {
+    /// Create a pipeline at the current insertion point. `with_fn` is called with the insertion
+    ///  point at the end of the entry stage; it adds further stages with `add_stage`, and must
+    ///  end each stage with a `StageOp` and the last one with a `ReturnOp`.
+    pub fn build_with(
+        builder: &mut OpBuilder,
+        inputs: &[Value],
+        control: &PipelineControl,
+        output_types: &[Type],
+        with_fn: impl FnOnce(&mut OpBuilder, &Self),
+    ) -> Result<Self, Error> {
+        let pipeline = Self::build_pipeline(builder, inputs, control, output_types)?;
+        let insert_point = builder.insert_point.clone();
+        builder.set_insertion_point(Some(InsertPoint::BlockEnd(pipeline.entry_stage())));
+        with_fn(builder, &pipeline);
+        builder.set_insertion_point(insert_point);
+        pipeline.verify().then_some(pipeline).ok_or(Error::simple(format!(
+            "pipeline.scheduled verification failed. {:?}",
+            &pipeline
+        )))
+    }
+
+    /// Append a stage receiving values of `types` through registers or passthroughs.
+    /// The block gets an additional last argument holding the valid bit of the stage.
+    pub fn add_stage(&self, types: &[Type]) -> Block {
+        let loc = self.loc();
+        let stage = Block::default();
+        for ty in types {
+            stage.add_argument(ty, &loc);
+        }
+        stage.add_argument(&IntegerType::new(&self.context(), 1), &loc);
+        self.first_region().unwrap().append_block(&stage);
+        stage
+    }
+
+    /// The blocks of the stages, in order.
+    pub fn stages(&self) -> Vec<Block> {
+        self.first_region().map(|region| region.blocks().collect()).unwrap_or_default()
+    }
+}
+
+def_operation!(StageOp, "pipeline.stage"; doc = "Ends a stage of a scheduled pipeline.");
+
+impl StageOp {
+    /// Move to `next_stage`, registering `registers` and passing `passthroughs` through
+    ///  unregistered. They become the arguments of `next_stage`, in that order.
+    pub fn build<V: Borrow<Value>>(
+        builder: &mut OpBuilder,
+        next_stage: &Block,
+        registers: impl IntoIterator<Item = V>,
+        passthroughs: impl IntoIterator<Item = V>,
+    ) -> Option<Self> {
+        let registers: Vec<Value> = registers.into_iter().map(|v| *v.borrow()).collect();
+        let passthroughs: Vec<Value> = passthroughs.into_iter().map(|v| *v.borrow()).collect();
+        builder.build_with(|builder, state| {
+            state.add_successor(next_stage);
+            state.add_operands::<Value>(registers.iter());
+            state.add_operands::<Value>(passthroughs.iter());
+            let segments = [registers.len() as i32, passthroughs.len() as i32];
+            let segments = Attribute::from_raw(unsafe {
+                mlirDenseI32ArrayGet(builder.context().raw(), 2, segments.as_ptr())
+            });
+            state.add_attribute("operand_segment_sizes", &segments);
+        })
+    }
+}
+
+def_operation!(ReturnOp, "pipeline.return"; doc = "Ends the last stage of a pipeline, yielding its outputs.");
+
+impl ReturnOp {
+    /// `valid` is the valid bit of the stage returning `outputs`.
+    pub fn build<V: Borrow<Value>>(
+        builder: &mut OpBuilder,
+        outputs: impl IntoIterator<Item = V>,
+        valid: &Value,
+    ) -> Option<Self> {
+        builder.build_with(|_, state| {
+            state.add_operands::<Value>(outputs);
+            state.add_operand(valid);
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hw::{HwModuleOp, ModulePortInfo};
+
+    #[test]
+    fn scheduled_pipeline_to_hw() -> miette::Result<()> {
+        let ctx = OwnedContext::default();
+        hw::dialect().load(&ctx).unwrap();
+        comb::dialect().load(&ctx).unwrap();
+        seq::dialect().load(&ctx).unwrap();
+        sv::dialect().load(&ctx).unwrap();
+        dialect().load(&ctx).unwrap();
+
+        let mut builder = OpBuilder::new(&ctx);
+        let module = Module::create(builder.loc());
+
+        let i1 = IntegerType::new(&ctx, 1);
+        let i32 = IntegerType::new(&ctx, 32);
+        let mut ports = ModulePortInfo::default();
+        ports.add_input("a", &i32);
+        ports.add_input("b", &i32);
+        ports.add_input("clk", &i1);
+        ports.add_input("rst", &i1);
+        ports.add_input("go", &i1);
+        ports.add_output("out", &i32);
+        ports.add_output("done", &i1);
+
+        HwModuleOp::build_with(
+            &mut builder,
+            &module,
+            "mac",
+            &ports,
+            &[],
+            "",
+            |builder, _, inputs, outputs| {
+                let control = PipelineControl {
+                    clock: inputs["clk"],
+                    reset: inputs["rst"],
+                    go: inputs["go"],
+                    stall: None,
+                };
+                let pipeline = ScheduledPipelineOp::build_with(
+                    builder,
+                    &[inputs["a"], inputs["b"]],
+                    &control,
+                    &[i32.as_type()],
+                    |builder, pipeline| {
+                        let args: Vec<Value> = pipeline.entry_stage().arguments().collect();
+                        let prod = comb::MulOp::build(builder, &args[0], &args[1]).unwrap();
+                        let s1 = pipeline.add_stage(&[i32.as_type()]);
+                        StageOp::build(builder, &s1, [prod.result()], []).unwrap();
+
+                        // `b` is used across the stage boundary; explicit-regs registers it.
+                        builder.set_insertion_point(Some(InsertPoint::BlockEnd(s1.clone())));
+                        let sum = comb::AddOp::build(builder, &s1.argument(0).unwrap(), &args[1])
+                            .unwrap();
+                        ReturnOp::build(builder, [sum.result()], &s1.argument(1).unwrap()).unwrap();
+                    },
+                )
+                .unwrap();
+                assert_eq!(pipeline.stages().len(), 2);
+                outputs.insert("out".to_string(), pipeline.outputs()[0]);
+                outputs.insert("done".to_string(), pipeline.done());
+            },
+        )?;
+        assert!(module.op().verify());
+
+        let pm = OwnedPassManager::new(&ctx);
+        pm.nest("hw.module").add_pass(&passes::explicit_regs());
+        pm.add_pass(&passes::lower_pipeline_to_hw());
+        pm.run(&module)?;
+
+        let mut verilog = String::new();
+        assert!(sv::export_verilog(&module, &mut verilog).is_success());
+        assert!(verilog.contains("module mac"));
+        assert!(verilog.contains("always_ff"));
+        Ok(())
+    }
+    #[test]
+    fn unscheduled_pipeline() -> miette::Result<()> {
+        let ctx = OwnedContext::default();
+        hw::dialect().load(&ctx).unwrap();
+        comb::dialect().load(&ctx).unwrap();
+        dialect().load(&ctx).unwrap();
+
+        let mut builder = OpBuilder::new(&ctx);
+        let module = Module::create(builder.loc());
+
+        let i1 = IntegerType::new(&ctx, 1);
+        let i32 = IntegerType::new(&ctx, 32);
+        let mut ports = ModulePortInfo::default();
+        ports.add_input("a", &i32);
+        ports.add_input("b", &i32);
+        ports.add_input("clk", &i1);
+        ports.add_input("rst", &i1);
+        ports.add_input("go", &i1);
+        ports.add_input("stall", &i1);
+        ports.add_output("out", &i32);
+        ports.add_output("done", &i1);
+
+        HwModuleOp::build_with(
+            &mut builder,
+            &module,
+            "mac",
+            &ports,
+            &[],
+            "",
+            |builder, _, inputs, outputs| {
+                let control = PipelineControl {
+                    clock: inputs["clk"],
+                    reset: inputs["rst"],
+                    go: inputs["go"],
+                    stall: Some(inputs["stall"]),
+                };
+                let pipeline = UnscheduledPipelineOp::build_with(
+                    builder,
+                    &[inputs["a"], inputs["b"]],
+                    &control,
+                    &[i32.as_type()],
+                    |builder, args, _| {
+                        let prod = comb::MulOp::build(builder, &args[0], &args[1]).unwrap();
+                        let sum = comb::AddOp::build(builder, &prod.result(), &args[1]).unwrap();
+                        vec![sum.result()]
+                    },
+                )
+                .unwrap();
+                assert_eq!(pipeline.entry_stage().arguments().count(), 3);
+                outputs.insert("out".to_string(), pipeline.outputs()[0]);
+                outputs.insert("done".to_string(), pipeline.done());
+            },
+        )?;
+        assert!(module.op().verify());
+        Ok(())
+    }
+}
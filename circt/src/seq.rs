@@ -154,6 +154,56 @@ impl CompRegOp {
     }
 }
 
+def_operation_single_result!(FirRegOp, "seq.firreg");
+
+impl FirRegOp {
+    pub fn next(&self) -> Value {
+        self.operand(0).unwrap()
+    }
+
+    pub fn set_next(&self, new_value: &Value) {
+        self.set_operand(0, new_value);
+    }
+
+    pub fn clk(&self) -> Value {
+        self.operand(1).unwrap()
+    }
+
+    pub fn set_clk(&self, new_value: &Value) {
+        self.set_operand(1, new_value);
+    }
+
+    pub fn output(&self) -> Value {
+        self.result()
+    }
+
+    /// Create a register loading `reset_value` while `reset` is high, asynchronously if
+    ///  `is_async`.
+    pub fn build(
+        builder: &mut OpBuilder,
+        name: &str,
+        next: &Value,
+        clk: &Value,
+        reset: Option<(&Value, &Value)>,
+        is_async: bool,
+    ) -> Option<Self> {
+        builder.build_with(|builder, state| {
+            let ctx = builder.context();
+            state.add_attribute("name", &StringAttr::new(&ctx, name));
+            state.add_operand(next);
+            state.add_operand(clk);
+            if let Some((reset, reset_value)) = reset {
+                state.add_operand(reset);
+                state.add_operand(reset_value);
+                if is_async {
+                    state.add_attribute("isAsync", &UnitAttr::new(&ctx));
+                }
+            }
+            state.add_result(&next.ty());
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{crate_prelude::*, hw::ConstantOp};
@@ -33,3 +33,51 @@ pub fn export_verilog<W: Write>(module: &Module, w: &mut W) -> LogicalResult {
         mlirExportVerilog(module.raw(), fmt.callback(), fmt.user_data())
     })
 }
+
+def_operation_single_result!(WireOp, "sv.wire");
+
+impl WireOp {
+    /// Declare a wire named `name`, yielding an `inout` of `ty`.
+    pub fn build(builder: &mut OpBuilder, ty: &impl Ty, name: &str) -> Option<Self> {
+        builder.build_with(|builder, state| {
+            state.add_attribute("name", &StringAttr::new(builder.context(), name));
+            state.add_result(&hw::InOutType::new(ty));
+        })
+    }
+}
+
+def_operation!(AssignOp, "sv.assign"; doc = "Continuous assignment of a value to a wire.");
+
+impl AssignOp {
+    pub fn build(builder: &mut OpBuilder, dest: &Value, src: &Value) -> Option<Self> {
+        builder.build_with(|_, state| {
+            state.add_operand(dest);
+            state.add_operand(src);
+        })
+    }
+}
+
+def_operation_single_result!(XMRRefOp, "sv.xmr.ref");
+
+impl XMRRefOp {
+    /// Refer to the signal at the end of the `hw.hierpath` `path`, yielding an `inout` of `ty`.
+    pub fn build(builder: &mut OpBuilder, path: &str, ty: &impl Ty) -> Option<Self> {
+        builder.build_with(|builder, state| {
+            state.add_attribute("ref", &SymbolRefAttr::new(builder.context(), path));
+            state.add_result(&hw::InOutType::new(ty));
+        })
+    }
+}
+
+def_operation_single_result!(ReadInOutOp, "sv.read_inout");
+
+impl ReadInOutOp {
+    /// Read the value of the `inout` `input`, e.g. an `sv.wire`.
+    pub fn build(builder: &mut OpBuilder, input: &Value) -> Option<Self> {
+        let ty = hw::InOutType::try_from(input.ty()).ok()?;
+        builder.build_with(|_, state| {
+            state.add_operand(input);
+            state.add_result(&ty.element_type());
+        })
+    }
+}
@@ -0,0 +1,12 @@
+// Copyright (c) 2022-2023 Kamyar Mohajerani
+
+//! The SystemC dialect models SystemC modules, signals and processes for emission as C++.
+//! See https://circt.llvm.org/docs/Dialects/SystemC/ for more details.
+
+use circt_sys::systemcRegisterPasses;
+
+define_dialect!(systemc);
+
+pub fn register_passes() {
+    unsafe { systemcRegisterPasses() }
+}
@@ -0,0 +1,225 @@
+// Copyright (c) 2022-2023 Kamyar Mohajerani
+
+//! Value change dump (VCD) waveforms of an `interp::Interpreter`, e.g. for viewing in GTKWave.
+//! Signals are traced from their `interp::TracePoint`s, in one VCD scope per module instance.
+
+use crate::crate_prelude::*;
+use glob::Pattern;
+use interp::{Interpreter, TracePoint};
+use num::BigUint;
+use std::io::Write;
+
+/// The identifier code of the `index`-th variable, in base 94 over the printable ASCII characters.
+fn id_code(mut index: usize) -> String {
+    let mut code = String::new();
+    loop {
+        code.push((b'!' + (index % 94) as u8) as char);
+        index /= 94;
+        if index == 0 {
+            return code;
+        }
+        index -= 1;
+    }
+}
+
+/// Writes the values of the traced signals of an `Interpreter` in VCD format.
+pub struct VcdWriter<W: Write> {
+    writer: W,
+    /// Indices of the dumped signals in `Interpreter::trace_points`, with their identifier codes
+    ///  and widths.
+    vars: Vec<(usize, String, u32)>,
+    last_values: Vec<Option<BigUint>>,
+}
+
+impl<W: Write> VcdWriter<W> {
+    /// Write the VCD header declaring the signals of `interp` whose `TracePoint::path` matches any
+    ///  of the glob `patterns`, or all signals if there are none. `timescale` is e.g. `1ns`.
+    pub fn new(
+        mut writer: W,
+        interp: &Interpreter,
+        patterns: &[&str],
+        timescale: &str,
+    ) -> Result<Self, Error> {
+        let patterns = patterns
+            .iter()
+            .map(|pattern| Pattern::new(pattern))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| Error::simple(format!("Invalid signal pattern: {}", e)))?;
+        let points: Vec<(usize, TracePoint)> = interp
+            .trace_points()
+            .into_iter()
+            .enumerate()
+            .filter(|(_, point)| {
+                patterns.is_empty() || patterns.iter().any(|p| p.matches(&point.path()))
+            })
+            .collect();
+
+        writeln!(writer, "$version circt-rs $end")?;
+        writeln!(writer, "$timescale {} $end", timescale)?;
+        let mut scope: &[String] = &[];
+        let mut vars = vec![];
+        for (index, point) in &points {
+            let common = scope.iter().zip(&point.scope).take_while(|(a, b)| a == b).count();
+            for _ in common..scope.len() {
+                writeln!(writer, "$upscope $end")?;
+            }
+            for name in &point.scope[common..] {
+                writeln!(writer, "$scope module {} $end", name)?;
+            }
+            scope = &point.scope;
+            let code = id_code(vars.len());
+            writeln!(writer, "$var wire {} {} {} $end", point.width, code, point.name)?;
+            vars.push((*index, code, point.width));
+        }
+        for _ in 0..scope.len() {
+            writeln!(writer, "$upscope $end")?;
+        }
+        writeln!(writer, "$enddefinitions $end")?;
+
+        Ok(Self {
+            writer,
+            last_values: vec![None; vars.len()],
+            vars,
+        })
+    }
+
+    /// Write the signals that changed since the last dump, or all of them on the first dump,
+    ///  at `time` in units of the timescale.
+    pub fn dump(&mut self, time: u64, interp: &Interpreter) -> Result<(), Error> {
+        let values = interp.trace_values();
+        let mut time_written = false;
+        for ((index, code, width), last) in self.vars.iter().zip(self.last_values.iter_mut()) {
+            let value = &values[*index];
+            if last.as_ref() == Some(value) {
+                continue;
+            }
+            if !time_written {
+                writeln!(self.writer, "#{}", time)?;
+                time_written = true;
+            }
+            if *width == 1 {
+                writeln!(self.writer, "{}{}", value, code)?;
+            } else {
+                writeln!(self.writer, "b{} {}", value.to_str_radix(2), code)?;
+            }
+            *last = Some(value.clone());
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), Error> {
+        Ok(self.writer.flush()?)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hw::{HwModuleOp, ModulePortInfo};
+    use num::One;
+
+    #[test]
+    fn id_codes() {
+        assert_eq!(id_code(0), "!");
+        assert_eq!(id_code(93), "~");
+        assert_eq!(id_code(94), "!!");
+        assert_eq!(id_code(94 + 94 * 94), "!!!");
+    }
+
+    #[test]
+    fn dump_counter() -> miette::Result<()> {
+        let ctx = OwnedContext::default();
+        hw::dialect().load(&ctx).unwrap();
+        comb::dialect().load(&ctx).unwrap();
+        seq::dialect().load(&ctx).unwrap();
+        sv::dialect().load(&ctx).unwrap();
+
+        let mut builder = OpBuilder::new(&ctx);
+        let module = Module::create(builder.loc());
+
+        let i1 = IntegerType::new(&ctx, 1);
+        let i4 = IntegerType::new(&ctx, 4);
+        let mut ports = ModulePortInfo::default();
+        ports.add_input("clk", &i1);
+        ports.add_output("count", &i4);
+        let counter = HwModuleOp::build_with(
+            &mut builder,
+            &module,
+            "counter",
+            &ports,
+            &[],
+            "",
+            |builder, _, inputs, outputs| {
+                let one = hw::ConstantOp::build(builder, 4, 1).result();
+                let reg = seq::CompRegOp::build(builder, "reg", &one, &inputs["clk"], None, None)
+                    .unwrap();
+                let next = comb::AddOp::build(builder, &reg.output(), &one).unwrap();
+                reg.set_input(&next.result());
+                let wire = sv::WireOp::build(builder, &i4, "next").unwrap();
+                sv::AssignOp::build(builder, &wire.result(), &next.result()).unwrap();
+                let read = sv::ReadInOutOp::build(builder, &wire.result()).unwrap();
+                outputs.insert("count".to_string(), read.result());
+            },
+        )?;
+
+        let mut ports = ModulePortInfo::default();
+        ports.add_input("clk", &i1);
+        ports.add_output("out", &i4);
+        HwModuleOp::build_with(
+            &mut builder,
+            &module,
+            "top",
+            &ports,
+            &[],
+            "",
+            |builder, _, inputs, outputs| {
+                let inst =
+                    hw::InstanceOp::build(builder, "u0", &counter, [inputs["clk"]], &[]).unwrap();
+                outputs.insert("out".to_string(), inst.result_at(0).unwrap());
+            },
+        )?;
+
+        let mut interp = interp::Interpreter::new(&module, "top")?;
+        let paths: Vec<String> = interp.trace_points().iter().map(TracePoint::path).collect();
+        assert_eq!(
+            paths,
+            [
+                "top.clk",
+                "top.out",
+                "top.u0.clk",
+                "top.u0.count",
+                "top.u0.reg",
+                "top.u0.next"
+            ]
+        );
+
+        let mut vcd = VcdWriter::new(Vec::new(), &interp, &[], "1ns")?;
+        for cycle in 0..3 {
+            vcd.dump(cycle * 10, &interp)?;
+            interp.set_input("clk", BigUint::one())?;
+            interp.step_clock()?;
+            vcd.dump(cycle * 10 + 5, &interp)?;
+            interp.set_input("clk", BigUint::default())?;
+            interp.eval()?;
+        }
+        let text = String::from_utf8(vcd.into_inner()).unwrap();
+        assert!(text.contains("$scope module top $end\n$var wire 1 ! clk $end"));
+        assert!(text.contains("$scope module u0 $end"));
+        assert!(text.contains("$var wire 4 % reg $end"));
+        assert!(text.contains("#0\n0!\nb1 \"\n0#\nb1 $\nb0 %\nb1 &\n#5\n"));
+        // Only the clocks change on falling edges.
+        assert!(text.contains("#20\n0!\n0#\n#25\n"));
+        assert!(text.ends_with("#25\n1!\nb100 \"\n1#\nb100 $\nb11 %\nb100 &\n"));
+
+        let vcd = VcdWriter::new(Vec::new(), &interp, &["top.u0.*e*"], "1ps")?;
+        let text = String::from_utf8(vcd.into_inner()).unwrap();
+        assert!(text.contains("reg $end") && text.contains("next $end"));
+        assert!(!text.contains("count $end"));
+        assert!(VcdWriter::new(Vec::new(), &interp, &["[a"], "1ns").is_err());
+        Ok(())
+    }
+}
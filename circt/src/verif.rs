@@ -0,0 +1,7 @@
+// Copyright (c) 2022-2023 Kamyar Mohajerani
+
+//! The Verif dialect holds verification constructs: assertions, assumptions and cover
+//!  statements over LTL properties.
+//! See https://circt.llvm.org/docs/Dialects/Verif/ for more details.
+
+define_dialect!(verif);
@@ -0,0 +1,1321 @@
+// Copyright (c) 2022-2023 Kamyar Mohajerani
+
+//! Yosys JSON netlists, as written by `write_json` and read by `read_json` in Yosys, and drawn by
+//!  netlistsvg. `export` writes each `hw.module` with its comb operations as word-level cells,
+//!  its registers as `$dff` cells, or `$dffe`, `$sdff` and `$adff` ones with enables and resets,
+//!  and its instances as cells of the instantiated module's type. `import` builds `hw.module`s
+//!  back from such netlists.
+//! See https://yosyshq.readthedocs.io/projects/yosys/en/latest/cmd/write_json.html for the format.
+
+use crate::analysis::loops::{operand_order, topological_order};
+use crate::analysis::{int_width, string_array, string_attr};
+use crate::crate_prelude::*;
+use hw::{HwModuleExternOp, HwModuleLike, HwModuleOp, ModulePortInfo};
+use num::{BigUint, One, Zero};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as Json;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// (De)serialization of named entries as a JSON object, keeping their order, which is significant
+///  for ports.
+mod ordered {
+    use serde::de::{Deserialize, Deserializer, MapAccess, Visitor};
+    use serde::{Serialize, Serializer};
+    use std::{fmt, marker::PhantomData};
+
+    pub fn serialize<S: Serializer, T: Serialize>(
+        entries: &[(String, T)],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(entries.iter().map(|(name, value)| (name, value)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, T: Deserialize<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<(String, T)>, D::Error> {
+        struct EntriesVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: Deserialize<'de>> Visitor<'de> for EntriesVisitor<T> {
+            type Value = Vec<(String, T)>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a JSON object")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut entries = vec![];
+                while let Some(entry) = map.next_entry()? {
+                    entries.push(entry);
+                }
+                Ok(entries)
+            }
+        }
+
+        deserializer.deserialize_map(EntriesVisitor(PhantomData))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Const {
+    #[serde(rename = "0")]
+    Zero,
+    #[serde(rename = "1")]
+    One,
+    #[serde(rename = "x")]
+    X,
+    #[serde(rename = "z")]
+    Z,
+}
+
+/// A bit of a signal: a net, numbered from 2, or a constant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Bit {
+    Net(usize),
+    Const(Const),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Input,
+    Output,
+    Inout,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Port {
+    pub direction: Direction,
+    /// The bits of the port, least significant first.
+    pub bits: Vec<Bit>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Cell {
+    #[serde(default)]
+    pub hide_name: u8,
+    /// A built-in cell type such as `$add`, or the name of a module.
+    #[serde(rename = "type")]
+    pub cell_type: String,
+    #[serde(default)]
+    pub parameters: BTreeMap<String, Json>,
+    #[serde(default)]
+    pub attributes: BTreeMap<String, Json>,
+    #[serde(default)]
+    pub port_directions: BTreeMap<String, Direction>,
+    #[serde(with = "ordered")]
+    pub connections: Vec<(String, Vec<Bit>)>,
+}
+
+impl Cell {
+    /// The value of an integer parameter, which Yosys writes as a binary string.
+    pub fn parameter(&self, name: &str) -> Option<BigUint> {
+        match self.parameters.get(name)? {
+            Json::Number(number) => number.as_u64().map(BigUint::from),
+            Json::String(bits) => BigUint::parse_bytes(bits.as_bytes(), 2),
+            _ => None,
+        }
+    }
+
+    pub fn connection(&self, port: &str) -> Result<&[Bit], Error> {
+        self.connections
+            .iter()
+            .find(|(name, _)| name == port)
+            .map(|(_, bits)| bits.as_slice())
+            .ok_or_else(|| {
+                Error::simple(format!("Missing port `{}` of cell type `{}`", port, self.cell_type))
+            })
+    }
+
+    fn is_output(&self, port: &str) -> bool {
+        match self.port_directions.get(port) {
+            Some(direction) => *direction == Direction::Output,
+            None => port == "Y" || port == "Q",
+        }
+    }
+
+    fn is_set(&self, parameter: &str) -> bool {
+        self.parameter(parameter).map_or(false, |value| !value.is_zero())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NetName {
+    #[serde(default)]
+    pub hide_name: u8,
+    pub bits: Vec<Bit>,
+    #[serde(default)]
+    pub attributes: BTreeMap<String, Json>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NetlistModule {
+    #[serde(default)]
+    pub attributes: BTreeMap<String, Json>,
+    #[serde(default, with = "ordered")]
+    pub ports: Vec<(String, Port)>,
+    #[serde(default, with = "ordered")]
+    pub cells: Vec<(String, Cell)>,
+    #[serde(default, with = "ordered")]
+    pub netnames: Vec<(String, NetName)>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Netlist {
+    #[serde(default)]
+    pub creator: String,
+    #[serde(with = "ordered")]
+    pub modules: Vec<(String, NetlistModule)>,
+}
+
+impl Netlist {
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        serde_json::from_str(json)
+            .map_err(|e| Error::simple(format!("Failed to parse Yosys JSON: {}", e)))
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    pub fn module(&self, name: &str) -> Option<&NetlistModule> {
+        self.modules.iter().find(|(module_name, _)| module_name == name).map(|(_, m)| m)
+    }
+}
+
+/// An integer parameter as Yosys writes it.
+fn param(value: usize) -> Json {
+    Json::String(format!("{:032b}", value))
+}
+
+/// The operations whose results are wired from the bits of their operands, without a cell.
+const WIRING_OPS: &[&str] = &[
+    "hw.constant",
+    "hw.wire",
+    "comb.concat",
+    "comb.extract",
+    "comb.replicate",
+];
+
+type Connection = (String, Direction, Vec<Bit>);
+
+fn input(port: &str, bits: Vec<Bit>) -> Connection {
+    (port.to_string(), Direction::Input, bits)
+}
+
+fn output(port: &str, bits: Vec<Bit>) -> Connection {
+    (port.to_string(), Direction::Output, bits)
+}
+
+struct Exporter {
+    netlist_module: NetlistModule,
+    nets: HashMap<Value, Vec<Bit>>,
+    next_net: usize,
+}
+
+impl Exporter {
+    fn fresh(&mut self, value: &Value) -> Result<Vec<Bit>, Error> {
+        let width = int_width(value)? as usize;
+        let bits: Vec<Bit> = (self.next_net..self.next_net + width).map(Bit::Net).collect();
+        self.next_net += width;
+        self.nets.insert(*value, bits.clone());
+        Ok(bits)
+    }
+
+    fn bits(&self, value: &Value) -> Result<Vec<Bit>, Error> {
+        self.nets.get(value).cloned().ok_or(Error::IsNone)
+    }
+
+    /// Add a cell, with a hidden name unless it is given one.
+    fn add_cell<'a>(
+        &mut self,
+        name: Option<String>,
+        cell_type: &str,
+        parameters: impl IntoIterator<Item = (&'a str, Json)>,
+        connections: Vec<Connection>,
+    ) {
+        let hide_name = name.is_none() as u8;
+        let name =
+            name.unwrap_or_else(|| format!("{}${}", cell_type, self.netlist_module.cells.len()));
+        let cell = Cell {
+            hide_name,
+            cell_type: cell_type.to_string(),
+            parameters: parameters.into_iter().map(|(name, v)| (name.to_string(), v)).collect(),
+            attributes: BTreeMap::new(),
+            port_directions: (connections.iter())
+                .map(|(port, direction, _)| (port.clone(), *direction))
+                .collect(),
+            connections: connections.into_iter().map(|(port, _, bits)| (port, bits)).collect(),
+        };
+        self.netlist_module.cells.push((name, cell));
+    }
+
+    fn binary_cell(
+        &mut self,
+        cell_type: &str,
+        signed: (bool, bool),
+        a: Vec<Bit>,
+        b: Vec<Bit>,
+        y: Vec<Bit>,
+    ) {
+        let parameters = [
+            ("A_SIGNED", param(signed.0 as usize)),
+            ("A_WIDTH", param(a.len())),
+            ("B_SIGNED", param(signed.1 as usize)),
+            ("B_WIDTH", param(b.len())),
+            ("Y_WIDTH", param(y.len())),
+        ];
+        self.add_cell(
+            None,
+            cell_type,
+            parameters,
+            vec![input("A", a), input("B", b), output("Y", y)],
+        );
+    }
+
+    /// The bits of the result of a wiring operation.
+    fn wire(&self, op: &Operation) -> Result<Vec<Bit>, Error> {
+        let args = op.operands().iter().map(|arg| self.bits(arg)).collect::<Result<Vec<_>, _>>()?;
+        let width = int_width(&op.result_at(0).ok_or(Error::IsNone)?)? as usize;
+        let int_attr = |attr_name: &str| {
+            op.attribute(attr_name)
+                .and_then(|attr| IntegerAttr::try_from(attr).ok())
+                .ok_or(Error::IsNone)
+        };
+        let arg = |i: usize| args.get(i).ok_or(Error::IsNone);
+        Ok(match op.name().to_string().as_str() {
+            "hw.constant" => {
+                let value = int_attr("value")?.to_biguint();
+                (0..width)
+                    .map(|i| {
+                        Bit::Const(if value.bit(i as u64) {
+                            Const::One
+                        } else {
+                            Const::Zero
+                        })
+                    })
+                    .collect()
+            }
+            "hw.wire" => arg(0)?.clone(),
+            // The first operand is the most significant.
+            "comb.concat" => args.iter().rev().flatten().copied().collect(),
+            "comb.extract" => {
+                let low = int_attr("lowBit")?.value() as usize;
+                arg(0)?.get(low..low + width).ok_or(Error::IsNone)?.to_vec()
+            }
+            _ => arg(0)?.iter().copied().cycle().take(width).collect(),
+        })
+    }
+
+    fn export_op(&mut self, op: &Operation) -> Result<(), Error> {
+        let name = op.name().to_string();
+        let args = op.operands().iter().map(|arg| self.bits(arg)).collect::<Result<Vec<_>, _>>()?;
+        let results =
+            op.results().iter().map(|result| self.bits(result)).collect::<Result<Vec<_>, _>>()?;
+        let arg = |i: usize| args.get(i).cloned().ok_or(Error::IsNone);
+        let result = || results.first().cloned().ok_or(Error::IsNone);
+        match name.as_str() {
+            "comb.add" | "comb.mul" | "comb.and" | "comb.or" | "comb.xor" => {
+                let cell_type = format!("${}", name.trim_start_matches("comb."));
+                let (first, rest) = args.split_first().ok_or(Error::IsNone)?;
+                if rest.is_empty() {
+                    let parameters = [
+                        ("A_SIGNED", param(0)),
+                        ("A_WIDTH", param(first.len())),
+                        ("Y_WIDTH", param(first.len())),
+                    ];
+                    let connections = vec![input("A", first.clone()), output("Y", result()?)];
+                    self.add_cell(None, "$pos", parameters, connections);
+                }
+                // Variadic operations are chains of binary cells.
+                let mut acc = first.clone();
+                for (i, arg) in rest.iter().enumerate() {
+                    let y = match i + 1 == rest.len() {
+                        true => result()?,
+                        false => {
+                            let bits = (self.next_net..self.next_net + acc.len()).map(Bit::Net);
+                            let bits: Vec<Bit> = bits.collect();
+                            self.next_net += bits.len();
+                            bits
+                        }
+                    };
+                    self.binary_cell(&cell_type, (false, false), acc, arg.clone(), y.clone());
+                    acc = y;
+                }
+            }
+            "comb.sub" | "comb.divu" | "comb.divs" | "comb.modu" | "comb.mods" | "comb.shl"
+            | "comb.shru" | "comb.shrs" => {
+                let (cell_type, signed) = match name.as_str() {
+                    "comb.sub" => ("$sub", (false, false)),
+                    "comb.divu" => ("$div", (false, false)),
+                    "comb.divs" => ("$div", (true, true)),
+                    "comb.modu" => ("$mod", (false, false)),
+                    "comb.mods" => ("$mod", (true, true)),
+                    "comb.shl" => ("$shl", (false, false)),
+                    "comb.shru" => ("$shr", (false, false)),
+                    _ => ("$sshr", (true, false)),
+                };
+                self.binary_cell(cell_type, signed, arg(0)?, arg(1)?, result()?);
+            }
+            "comb.icmp" => {
+                use comb::CmpPred::*;
+                let pred = op
+                    .attribute("predicate")
+                    .and_then(|attr| IntegerAttr::try_from(attr).ok())
+                    .and_then(|attr| comb::CmpPred::from_attr_value(attr.value()))
+                    .ok_or(Error::IsNone)?;
+                let cell_type = match pred {
+                    Eq => "$eq",
+                    Neq => "$ne",
+                    Slt | Ult => "$lt",
+                    Sle | Ule => "$le",
+                    Sgt | Ugt => "$gt",
+                    Sge | Uge => "$ge",
+                };
+                let signed = matches!(pred, Slt | Sle | Sgt | Sge);
+                self.binary_cell(cell_type, (signed, signed), arg(0)?, arg(1)?, result()?);
+            }
+            "comb.mux" => {
+                let y = result()?;
+                let connections = vec![
+                    input("A", arg(2)?),
+                    input("B", arg(1)?),
+                    input("S", arg(0)?),
+                    output("Y", y.clone()),
+                ];
+                self.add_cell(None, "$mux", [("WIDTH", param(y.len()))], connections);
+            }
+            "comb.parity" => {
+                let a = arg(0)?;
+                let parameters = [
+                    ("A_SIGNED", param(0)),
+                    ("A_WIDTH", param(a.len())),
+                    ("Y_WIDTH", param(1)),
+                ];
+                let connections = vec![input("A", a), output("Y", result()?)];
+                self.add_cell(None, "$reduce_xor", parameters, connections);
+            }
+            "seq.compreg" | "seq.compreg.ce" | "seq.firreg" => {
+                self.export_register(op, &args, result()?)?;
+            }
+            "hw.instance" => {
+                let inst = op.try_into_op::<hw::InstanceOp>().ok_or(Error::IsNone)?;
+                let inputs = string_array(op, "argNames").into_iter().zip(args.iter().cloned());
+                let outputs =
+                    string_array(op, "resultNames").into_iter().zip(results.iter().cloned());
+                let connections = inputs
+                    .map(|(port, bits)| (port, Direction::Input, bits))
+                    .chain(outputs.map(|(port, bits)| (port, Direction::Output, bits)))
+                    .collect();
+                self.add_cell(Some(inst.instance_name()), &inst.module_name(), [], connections);
+            }
+            _ => return Err(Error::simple(format!("Unsupported operation `{}`", name))),
+        }
+        Ok(())
+    }
+
+    fn export_register(
+        &mut self,
+        op: &Operation,
+        args: &[Vec<Bit>],
+        q: Vec<Bit>,
+    ) -> Result<(), Error> {
+        let arg = |i: usize| args.get(i).cloned().ok_or(Error::IsNone);
+        let (enable, reset_at) = match op.name().to_string().as_str() {
+            "seq.compreg.ce" => (Some(arg(2)?), 3),
+            _ => (None, 2),
+        };
+        let mut cell_type = "$dff".to_string();
+        let mut parameters = vec![("CLK_POLARITY", param(1)), ("WIDTH", param(q.len()))];
+        let mut connections = vec![input("CLK", arg(1)?), input("D", arg(0)?), output("Q", q)];
+        if let (Ok(reset), Ok(value)) = (arg(reset_at), arg(reset_at + 1)) {
+            let value: String = (value.iter().rev())
+                .map(|bit| match bit {
+                    Bit::Const(Const::One) => Ok('1'),
+                    Bit::Const(_) => Ok('0'),
+                    Bit::Net(_) => Err(Error::simple(format!(
+                        "Non-constant reset value of register `{}`",
+                        string_attr(op, "name").unwrap_or_default()
+                    ))),
+                })
+                .collect::<Result<_, _>>()?;
+            let (kind, port, polarity, reset_value) = match op.attribute("isAsync") {
+                Some(_) => ("$adff", "ARST", "ARST_POLARITY", "ARST_VALUE"),
+                None => ("$sdff", "SRST", "SRST_POLARITY", "SRST_VALUE"),
+            };
+            cell_type = kind.to_string();
+            parameters.push((polarity, param(1)));
+            parameters.push((reset_value, Json::String(value)));
+            connections.push(input(port, reset));
+        }
+        if let Some(enable) = enable {
+            cell_type.push('e');
+            parameters.push(("EN_POLARITY", param(1)));
+            connections.push(input("EN", enable));
+        }
+        self.add_cell(string_attr(op, "name"), &cell_type, parameters, connections);
+        Ok(())
+    }
+}
+
+fn export_module(hw_module: &HwModuleOp) -> Result<NetlistModule, Error> {
+    let body = hw_module.first_block().ok_or(Error::IsNone)?;
+    let ports = hw_module.port_info();
+    let mut exporter = Exporter {
+        netlist_module: NetlistModule::default(),
+        nets: HashMap::new(),
+        next_net: 2,
+    };
+    let mut named = vec![];
+    for (port, arg) in ports.inputs.iter().zip(body.arguments()) {
+        let bits = exporter.fresh(&arg)?;
+        let port_bits = Port {
+            direction: Direction::Input,
+            bits,
+        };
+        exporter.netlist_module.ports.push((port.name.clone(), port_bits));
+        named.push((port.name.clone(), arg));
+    }
+
+    let mut results = vec![];
+    let mut cells = vec![];
+    let mut wiring = vec![];
+    for op in body.operations() {
+        let op_name = op.name().to_string();
+        if op_name == "hw.output" {
+            results = op.operands();
+        } else if WIRING_OPS.contains(&op_name.as_str()) {
+            wiring.push(op);
+        } else {
+            for result in op.results() {
+                exporter.fresh(&result)?;
+            }
+            cells.push(op);
+        }
+        if let (Some(name), Some(result)) = (string_attr(&op, "name"), op.result_at(0)) {
+            named.push((name, result));
+        }
+    }
+
+    // Wiring operations are resolved after the operations defining their operands.
+    let order = operand_order(&wiring).map_err(|op| {
+        Error::simple(format!(
+            "Combinational loop through `{}` in `{}`",
+            op.name().to_string(),
+            hw_module.module_name()
+        ))
+    })?;
+    for op in order {
+        let bits = exporter.wire(&op)?;
+        exporter.nets.insert(op.result_at(0).ok_or(Error::IsNone)?, bits);
+    }
+
+    for op in &cells {
+        exporter.export_op(op)?;
+    }
+    for (port, value) in ports.outputs.iter().zip(results) {
+        let bits = exporter.bits(&value)?;
+        let port_bits = Port {
+            direction: Direction::Output,
+            bits,
+        };
+        exporter.netlist_module.ports.push((port.name.clone(), port_bits));
+        named.push((port.name.clone(), value));
+    }
+    let mut names = HashSet::new();
+    for (name, value) in named {
+        if names.insert(name.clone()) {
+            let net_name = NetName {
+                bits: exporter.bits(&value)?,
+                ..Default::default()
+            };
+            exporter.netlist_module.netnames.push((name, net_name));
+        }
+    }
+    Ok(exporter.netlist_module)
+}
+
+/// Export the `hw.module`s of `module` to a netlist, with its `hw.module.extern`s as black boxes.
+pub fn export(module: &Module) -> Result<Netlist, Error> {
+    let mut netlist = Netlist {
+        creator: "circt-rs".to_string(),
+        modules: vec![],
+    };
+    for op in module.body().operations() {
+        if let Some(hw_module) = op.try_into_op::<HwModuleOp>() {
+            netlist.modules.push((hw_module.module_name(), export_module(&hw_module)?));
+        } else if let Some(extern_module) = op.try_into_op::<HwModuleExternOp>() {
+            let ports = extern_module.port_info();
+            let mut black_box = NetlistModule::default();
+            black_box.attributes.insert("blackbox".to_string(), param(1));
+            let mut next_net = 2;
+            let inputs = ports.inputs.iter().map(|port| (port, Direction::Input));
+            for (port, direction) in
+                inputs.chain(ports.outputs.iter().map(|port| (port, Direction::Output)))
+            {
+                let width = IntegerType::try_from(port.ty)
+                    .map_err(|_| Error::simple(format!("Unsupported type {}", port.ty)))?
+                    .width() as usize;
+                let bits = (next_net..next_net + width).map(Bit::Net).collect();
+                next_net += width;
+                black_box.ports.push((port.name.clone(), Port { direction, bits }));
+            }
+            netlist.modules.push((extern_module.module_name(), black_box));
+        }
+    }
+    Ok(netlist)
+}
+
+/// The modules imported so far, which later ones can instantiate.
+#[derive(Default)]
+struct Submodules {
+    modules: HashMap<String, HwModuleOp>,
+    externs: HashMap<String, HwModuleExternOp>,
+}
+
+impl Submodules {
+    fn contains(&self, name: &str) -> bool {
+        self.modules.contains_key(name) || self.externs.contains_key(name)
+    }
+
+    fn port_info(&self, name: &str) -> Result<ModulePortInfo, Error> {
+        match (self.modules.get(name), self.externs.get(name)) {
+            (Some(module), _) => Ok(module.port_info()),
+            (_, Some(module)) => Ok(module.port_info()),
+            _ => Err(Error::simple(format!("Unknown module `{}`", name))),
+        }
+    }
+
+    fn instantiate(
+        &self,
+        builder: &mut OpBuilder,
+        instance_name: &str,
+        name: &str,
+        inputs: Vec<Value>,
+    ) -> Result<hw::InstanceOp, Error> {
+        match (self.modules.get(name), self.externs.get(name)) {
+            (Some(module), _) => hw::InstanceOp::build(builder, instance_name, module, inputs, &[]),
+            (_, Some(module)) => hw::InstanceOp::build(builder, instance_name, module, inputs, &[]),
+            _ => Err(Error::simple(format!("Unknown module `{}`", name))),
+        }
+    }
+}
+
+const REGISTER_CELLS: &[&str] = &["$dff", "$dffe", "$sdff", "$sdffe", "$adff", "$adffe"];
+
+/// Builds the operations of one module, mapping each net to the value and bit driving it.
+struct Importer<'a> {
+    builder: &'a mut OpBuilder,
+    drivers: HashMap<usize, (Value, usize)>,
+}
+
+impl<'a> Importer<'a> {
+    fn constant(&mut self, width: usize, value: BigUint) -> Value {
+        hw::ConstantOp::build(self.builder, width as u32, value).result()
+    }
+
+    fn extract(&mut self, value: &Value, offset: usize, width: usize) -> Result<Value, Error> {
+        (comb::ExtractOp::with_sizes(self.builder, value, offset, width))
+            .map(|op| op.result())
+            .ok_or(Error::IsNone)
+    }
+
+    /// Truncate or extend `value` to `width` bits.
+    fn resize(&mut self, value: Value, width: usize, signed: bool) -> Result<Value, Error> {
+        let current = int_width(&value)? as usize;
+        if current >= width {
+            return match current == width {
+                true => Ok(value),
+                false => self.extract(&value, 0, width),
+            };
+        }
+        let fill = match signed && current > 0 {
+            true => vec![self.extract(&value, current - 1, 1)?; width - current],
+            false => vec![self.constant(width - current, BigUint::zero())],
+        };
+        (comb::ConcatOp::build(self.builder, fill.into_iter().chain([value])))
+            .map(|op| op.result())
+            .ok_or(Error::IsNone)
+    }
+
+    fn drive(&mut self, bits: &[Bit], value: Value) {
+        for (i, bit) in bits.iter().enumerate() {
+            if let Bit::Net(net) = bit {
+                self.drivers.entry(*net).or_insert((value, i));
+            }
+        }
+    }
+
+    /// The value of the signal `bits`, concatenated from slices of the values driving its nets
+    ///  and from constants.
+    fn value(&mut self, bits: &[Bit]) -> Result<Value, Error> {
+        let mut parts = vec![];
+        let mut i = 0;
+        while i < bits.len() {
+            let len = match bits[i] {
+                Bit::Net(net) => {
+                    let (value, offset) = *self
+                        .drivers
+                        .get(&net)
+                        .ok_or_else(|| Error::simple(format!("Undriven net {}", net)))?;
+                    let len = 1 + bits[i + 1..]
+                        .iter()
+                        .enumerate()
+                        .take_while(|(j, bit)| match bit {
+                            Bit::Net(net) => {
+                                self.drivers.get(net) == Some(&(value, offset + j + 1))
+                            }
+                            Bit::Const(_) => false,
+                        })
+                        .count();
+                    if offset == 0 && len == int_width(&value)? as usize {
+                        parts.push(value);
+                    } else {
+                        parts.push(self.extract(&value, offset, len)?);
+                    }
+                    len
+                }
+                Bit::Const(_) => {
+                    let len =
+                        bits[i..].iter().take_while(|bit| matches!(bit, Bit::Const(_))).count();
+                    let value = (bits[i..i + len].iter().enumerate())
+                        .filter(|(_, bit)| **bit == Bit::Const(Const::One))
+                        .fold(BigUint::zero(), |acc, (j, _)| acc | (BigUint::one() << j));
+                    parts.push(self.constant(len, value));
+                    len
+                }
+            };
+            i += len;
+        }
+        match parts.as_slice() {
+            [value] => Ok(*value),
+            // The first operand of a concatenation is the most significant.
+            _ => (comb::ConcatOp::build(self.builder, parts.iter().rev()))
+                .map(|op| op.result())
+                .ok_or(Error::IsNone),
+        }
+    }
+
+    /// The value of the control port `port` of `cell`, inverted if it is active low.
+    fn control(&mut self, cell: &Cell, port: &str, polarity: &str) -> Result<Value, Error> {
+        let value = self.value(cell.connection(port)?)?;
+        if cell.parameter(polarity).map_or(true, |value| !value.is_zero()) {
+            return Ok(value);
+        }
+        let one = self.constant(1, BigUint::one());
+        (comb::XorOp::build(self.builder, [value, one])).map(|op| op.result()).ok_or(Error::IsNone)
+    }
+
+    /// Build the register of `cell` with constant operands standing in for its inputs until
+    ///  `connect_register`, returning it and the placeholders.
+    fn register(
+        &mut self,
+        name: &str,
+        cell: &Cell,
+    ) -> Result<(Operation, Vec<hw::ConstantOp>), Error> {
+        let q = cell.connection("Q")?;
+        let width = q.len();
+        let data = hw::ConstantOp::build(self.builder, width as u32, 0);
+        let bit = hw::ConstantOp::build(self.builder, 1, 0);
+        let (d, b) = (data.result(), bit.result());
+        let reset_value = match cell.cell_type.as_str() {
+            "$dff" | "$dffe" => None,
+            _ => {
+                let value = ["SRST_VALUE", "ARST_VALUE"]
+                    .iter()
+                    .find_map(|param_name| cell.parameter(param_name))
+                    .unwrap_or_default();
+                Some(self.constant(width, value))
+            }
+        };
+        let reset = reset_value.as_ref().map(|value| (&b, value));
+        let op: Option<Operation> = match cell.cell_type.as_str() {
+            "$dff" => seq::CompRegOp::build(self.builder, name, &d, &b, None, None).map(Into::into),
+            "$sdff" => {
+                (seq::CompRegOp::build(self.builder, name, &d, &b, Some(&b), reset_value.as_ref()))
+                    .map(Into::into)
+            }
+            "$dffe" => {
+                seq::CompRegClockEnabledOp::build(self.builder, name, &d, &b, &b, None, None)
+                    .map(Into::into)
+            }
+            "$sdffe" => seq::CompRegClockEnabledOp::build(
+                self.builder,
+                name,
+                &d,
+                &b,
+                &b,
+                Some(&b),
+                reset_value.as_ref(),
+            )
+            .map(Into::into),
+            _ => seq::FirRegOp::build(self.builder, name, &d, &b, reset, true).map(Into::into),
+        };
+        let op = op.ok_or(Error::IsNone)?;
+        self.drive(q, op.result_at(0).ok_or(Error::IsNone)?);
+        Ok((op, vec![data, bit]))
+    }
+
+    /// Replace the placeholder operands of the register `op` of `cell` by its inputs.
+    fn connect_register(&mut self, op: &Operation, cell: &Cell) -> Result<(), Error> {
+        let mut data = self.value(cell.connection("D")?)?;
+        let clk = self.control(cell, "CLK", "CLK_POLARITY")?;
+        let enable = match cell.cell_type.ends_with('e') {
+            true => Some(self.control(cell, "EN", "EN_POLARITY")?),
+            false => None,
+        };
+        let reset = match cell.cell_type.as_str() {
+            "$sdff" | "$sdffe" => Some(self.control(cell, "SRST", "SRST_POLARITY")?),
+            "$adff" | "$adffe" => Some(self.control(cell, "ARST", "ARST_POLARITY")?),
+            _ => None,
+        };
+        let mut operands = vec![];
+        match (op.name().to_string().as_str(), enable) {
+            // `seq.firreg` has no enable, so it holds its value through a mux.
+            ("seq.firreg", Some(enable)) => {
+                let q = op.result_at(0).ok_or(Error::IsNone)?;
+                data = (comb::MuxOp::build(self.builder, &enable, &data, &q))
+                    .map(|op| op.result())
+                    .ok_or(Error::IsNone)?;
+                operands.extend([data, clk]);
+            }
+            (_, enable) => operands.extend([data, clk].into_iter().chain(enable)),
+        }
+        operands.extend(reset);
+        for (i, operand) in operands.iter().enumerate() {
+            op.set_operand(i, operand);
+        }
+        Ok(())
+    }
+
+    fn instance(&mut self, name: &str, cell: &Cell, submodules: &Submodules) -> Result<(), Error> {
+        let ports = submodules.port_info(&cell.cell_type)?;
+        let mut inputs = vec![];
+        for port in &ports.inputs {
+            let value = self.value(cell.connection(&port.name)?)?;
+            let width = IntegerType::try_from(port.ty).map_err(|_| Error::IsNone)?.width();
+            inputs.push(self.resize(value, width as usize, false)?);
+        }
+        let inst = submodules.instantiate(self.builder, name, &cell.cell_type, inputs)?;
+        for (port, result) in ports.outputs.iter().zip(inst.results()) {
+            if let Ok(bits) = cell.connection(&port.name) {
+                let result = self.resize(result, bits.len(), false)?;
+                self.drive(bits, result);
+            }
+        }
+        Ok(())
+    }
+
+    fn cell(&mut self, name: &str, cell: &Cell, submodules: &Submodules) -> Result<(), Error> {
+        use comb::CmpPred::*;
+        let cell_type = cell.cell_type.as_str();
+        if !cell_type.starts_with('$') {
+            return self.instance(name, cell, submodules);
+        }
+        let y = cell.connection("Y")?;
+        let width = y.len();
+        let signed = cell.is_set("A_SIGNED") && cell.is_set("B_SIGNED");
+        let operand = |importer: &mut Self, port: &str, width: usize, signed: bool| {
+            let value = importer.value(cell.connection(port)?)?;
+            importer.resize(value, width, signed)
+        };
+        let operand_width = |port: &str| cell.connection(port).map(|bits| bits.len());
+        let value = match cell_type {
+            "$add" | "$sub" | "$mul" | "$div" | "$mod" | "$and" | "$or" | "$xor" => {
+                let full = width.max(operand_width("A")?).max(operand_width("B")?);
+                let lhs = operand(self, "A", full, signed)?;
+                let rhs = operand(self, "B", full, signed)?;
+                let b = &mut *self.builder;
+                let value = match cell_type {
+                    "$add" => comb::AddOp::build(b, &lhs, &rhs).map(|op| op.result()),
+                    "$sub" => comb::SubOp::build(b, &lhs, &rhs).map(|op| op.result()),
+                    "$mul" => comb::MulOp::build(b, &lhs, &rhs).map(|op| op.result()),
+                    "$div" if signed => comb::DivSOp::build(b, &lhs, &rhs).map(|op| op.result()),
+                    "$div" => comb::DivUOp::build(b, &lhs, &rhs).map(|op| op.result()),
+                    "$mod" if signed => comb::ModSOp::build(b, &lhs, &rhs).map(|op| op.result()),
+                    "$mod" => comb::ModUOp::build(b, &lhs, &rhs).map(|op| op.result()),
+                    "$and" => comb::AndOp::build(b, [lhs, rhs]).map(|op| op.result()),
+                    "$or" => comb::OrOp::build(b, [lhs, rhs]).map(|op| op.result()),
+                    _ => comb::XorOp::build(b, [lhs, rhs]).map(|op| op.result()),
+                };
+                self.resize(value.ok_or(Error::IsNone)?, width, signed)?
+            }
+            "$shl" | "$shr" | "$sshr" => {
+                let signed = cell_type == "$sshr" && cell.is_set("A_SIGNED");
+                let full = width.max(operand_width("A")?).max(operand_width("B")?);
+                let lhs = operand(self, "A", full, signed)?;
+                let rhs = operand(self, "B", full, false)?;
+                let b = &mut *self.builder;
+                let value = match cell_type {
+                    "$shl" => comb::ShlOp::build(b, &lhs, &rhs).map(|op| op.result()),
+                    _ if signed => comb::ShrSOp::build(b, &lhs, &rhs).map(|op| op.result()),
+                    _ => comb::ShrUOp::build(b, &lhs, &rhs).map(|op| op.result()),
+                };
+                self.resize(value.ok_or(Error::IsNone)?, width, signed)?
+            }
+            "$eq" | "$ne" | "$lt" | "$le" | "$gt" | "$ge" => {
+                let full = operand_width("A")?.max(operand_width("B")?);
+                let lhs = operand(self, "A", full, signed)?;
+                let rhs = operand(self, "B", full, signed)?;
+                let pred = match (cell_type, signed) {
+                    ("$eq", _) => Eq,
+                    ("$ne", _) => Neq,
+                    ("$lt", true) => Slt,
+                    ("$lt", false) => Ult,
+                    ("$le", true) => Sle,
+                    ("$le", false) => Ule,
+                    ("$gt", true) => Sgt,
+                    ("$gt", false) => Ugt,
+                    (_, true) => Sge,
+                    (_, false) => Uge,
+                };
+                let value =
+                    comb::ICmpOp::build(self.builder, pred, &lhs, &rhs).ok_or(Error::IsNone)?;
+                self.resize(value.result(), width, false)?
+            }
+            "$not" | "$pos" | "$neg" => {
+                let value = operand(self, "A", width, cell.is_set("A_SIGNED"))?;
+                let value = match cell_type {
+                    "$not" => {
+                        let ones = self.constant(width, (BigUint::one() << width) - 1u32);
+                        comb::XorOp::build(self.builder, [value, ones]).map(|op| op.result())
+                    }
+                    "$neg" => {
+                        let zero = self.constant(width, BigUint::zero());
+                        comb::SubOp::build(self.builder, &zero, &value).map(|op| op.result())
+                    }
+                    _ => Some(value),
+                };
+                value.ok_or(Error::IsNone)?
+            }
+            "$reduce_and" | "$reduce_or" | "$reduce_bool" | "$reduce_xor" | "$logic_not" => {
+                let a = operand_width("A")?;
+                let value = operand(self, "A", a, false)?;
+                let value = match cell_type {
+                    "$reduce_xor" => {
+                        comb::ParityOp::build(self.builder, &value).map(|op| op.result())
+                    }
+                    _ => {
+                        let (pred, constant) = match cell_type {
+                            "$reduce_and" => (Eq, (BigUint::one() << a) - 1u32),
+                            "$logic_not" => (Eq, BigUint::zero()),
+                            _ => (Neq, BigUint::zero()),
+                        };
+                        let constant = self.constant(a, constant);
+                        comb::ICmpOp::build(self.builder, pred, &value, &constant)
+                            .map(|op| op.result())
+                    }
+                };
+                self.resize(value.ok_or(Error::IsNone)?, width, false)?
+            }
+            "$mux" => {
+                let cond = operand(self, "S", 1, false)?;
+                let (a, b) = (operand(self, "A", width, false)?, operand(self, "B", width, false)?);
+                (comb::MuxOp::build(self.builder, &cond, &b, &a))
+                    .map(|op| op.result())
+                    .ok_or(Error::IsNone)?
+            }
+            _ => {
+                return Err(Error::simple(format!(
+                    "Unsupported cell type `{}` of `{}`",
+                    cell_type, name
+                )))
+            }
+        };
+        self.drive(y, value);
+        Ok(())
+    }
+
+    /// Build the cells of `netlist_module`, which is named `module_name`, in the body of the module
+    ///  with the given port values.
+    fn run(
+        &mut self,
+        module_name: &str,
+        netlist_module: &NetlistModule,
+        inputs: &HashMap<String, Value>,
+        outputs: &mut HashMap<String, Value>,
+        submodules: &Submodules,
+    ) -> Result<(), Error> {
+        for (name, port) in &netlist_module.ports {
+            if let Some(value) = inputs.get(name) {
+                self.drive(&port.bits, *value);
+            }
+        }
+        // Registers come first, so that the cells they feed back into are ready.
+        let mut registers = vec![];
+        let mut cells = vec![];
+        for (name, cell) in &netlist_module.cells {
+            match REGISTER_CELLS.contains(&cell.cell_type.as_str()) {
+                true => registers.push((self.register(name, cell)?, cell)),
+                false => cells.push((name, cell)),
+            }
+        }
+        // The other cells are built after the cells driving their inputs.
+        let mut cell_drivers = HashMap::new();
+        for (i, (_, cell)) in cells.iter().enumerate() {
+            let outputs = cell.connections.iter().filter(|(port, _)| cell.is_output(port));
+            for (_, bits) in outputs {
+                cell_drivers.extend(bits.iter().filter_map(|bit| match bit {
+                    Bit::Net(net) => Some((*net, i)),
+                    Bit::Const(_) => None,
+                }));
+            }
+        }
+        let preds: Vec<Vec<usize>> = (cells.iter())
+            .map(|(_, cell)| {
+                (cell.connections.iter())
+                    .filter(|(port, _)| !cell.is_output(port))
+                    .flat_map(|(_, bits)| bits)
+                    .filter_map(|bit| match bit {
+                        Bit::Net(net) => cell_drivers.get(net).copied(),
+                        Bit::Const(_) => None,
+                    })
+                    .collect()
+            })
+            .collect();
+        let order = topological_order(&preds).map_err(|blocked| {
+            Error::simple(format!(
+                "Combinational loop through cell `{}` in `{}`",
+                cells[blocked[0]].0, module_name
+            ))
+        })?;
+        for i in order {
+            let (name, cell) = cells[i];
+            self.cell(name, cell, submodules)?;
+        }
+        for ((op, placeholders), cell) in registers {
+            self.connect_register(&op, cell)?;
+            for placeholder in placeholders {
+                placeholder.erase();
+            }
+        }
+        for (name, port) in &netlist_module.ports {
+            if port.direction == Direction::Output {
+                outputs.insert(name.clone(), self.value(&port.bits)?);
+            }
+        }
+        Ok(())
+    }
+}
+
+struct DesignImporter<'a> {
+    netlist: &'a Netlist,
+    submodules: Submodules,
+    visiting: HashSet<String>,
+    imported: Vec<HwModuleOp>,
+}
+
+impl<'a> DesignImporter<'a> {
+    fn port_info(
+        builder: &OpBuilder,
+        netlist_module: &NetlistModule,
+    ) -> Result<ModulePortInfo, Error> {
+        let mut ports = ModulePortInfo::default();
+        for (name, port) in &netlist_module.ports {
+            let ty = IntegerType::new(builder.context(), port.bits.len() as u32);
+            match port.direction {
+                Direction::Input => ports.add_input(name, &ty),
+                Direction::Output => ports.add_output(name, &ty),
+                Direction::Inout => {
+                    return Err(Error::simple(format!("Unsupported inout port `{}`", name)))
+                }
+            }
+        }
+        Ok(ports)
+    }
+
+    /// Declare the module of a cell that is not in the netlist as a black box.
+    fn import_black_box(
+        &mut self,
+        builder: &mut OpBuilder,
+        module: &Module,
+        cell: &Cell,
+    ) -> Result<(), Error> {
+        let mut ports = ModulePortInfo::default();
+        for (port, bits) in &cell.connections {
+            let ty = IntegerType::new(builder.context(), bits.len() as u32);
+            match cell.port_directions.get(port) {
+                Some(Direction::Input) => ports.add_input(port, &ty),
+                Some(Direction::Output) => ports.add_output(port, &ty),
+                _ => {
+                    return Err(Error::simple(format!(
+                        "Unknown direction of port `{}` of `{}`",
+                        port, cell.cell_type
+                    )))
+                }
+            }
+        }
+        let extern_module = HwModuleExternOp::build(builder, module, &cell.cell_type, &ports, &[])?;
+        self.submodules.externs.insert(cell.cell_type.clone(), extern_module);
+        Ok(())
+    }
+
+    /// Import the module `name` after the modules it instantiates. If it fails, neither the module
+    ///  nor the black boxes declared for its cells are left behind.
+    fn import_module(
+        &mut self,
+        builder: &mut OpBuilder,
+        module: &Module,
+        name: &str,
+    ) -> Result<(), Error> {
+        if self.submodules.contains(name) {
+            return Ok(());
+        }
+        if !self.visiting.insert(name.to_string()) {
+            return Err(Error::simple(format!("Recursive instantiation of `{}`", name)));
+        }
+        let netlist_module = self.netlist.module(name).ok_or(Error::IsNone)?;
+        let mut black_boxes = vec![];
+        let result = self
+            .import_submodules(builder, module, netlist_module, &mut black_boxes)
+            .and_then(|()| self.import_definition(builder, module, name, netlist_module));
+        if result.is_err() {
+            for cell_type in black_boxes {
+                if let Some(extern_module) = self.submodules.externs.remove(&cell_type) {
+                    extern_module.erase();
+                }
+            }
+        }
+        result
+    }
+
+    /// Import the modules instantiated by the cells of `netlist_module`, collecting the types of
+    ///  the cells declared as black boxes in `black_boxes`.
+    fn import_submodules(
+        &mut self,
+        builder: &mut OpBuilder,
+        module: &Module,
+        netlist_module: &NetlistModule,
+        black_boxes: &mut Vec<String>,
+    ) -> Result<(), Error> {
+        for (_, cell) in &netlist_module.cells {
+            if cell.cell_type.starts_with('$') || self.submodules.contains(&cell.cell_type) {
+                continue;
+            }
+            match self.netlist.module(&cell.cell_type) {
+                Some(_) => self.import_module(builder, module, &cell.cell_type)?,
+                None => {
+                    self.import_black_box(builder, module, cell)?;
+                    black_boxes.push(cell.cell_type.clone());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Import `netlist_module` itself, as a `hw.module` or, with a `blackbox` attribute, as a
+    ///  `hw.module.extern`.
+    fn import_definition(
+        &mut self,
+        builder: &mut OpBuilder,
+        module: &Module,
+        name: &str,
+        netlist_module: &NetlistModule,
+    ) -> Result<(), Error> {
+        let ports = Self::port_info(builder, netlist_module)?;
+        if netlist_module.attributes.contains_key("blackbox") {
+            let extern_module = HwModuleExternOp::build(builder, module, name, &ports, &[])?;
+            self.submodules.externs.insert(name.to_string(), extern_module);
+            return Ok(());
+        }
+        let hw_module = HwModuleOp::build_in_module(builder, module, name, &ports, &[], "")?;
+        let submodules = &self.submodules;
+        let build_body = |builder: &mut OpBuilder| -> Result<(), Error> {
+            let body = hw_module.first_block().ok_or(Error::IsNone)?;
+            let inputs: HashMap<String, Value> =
+                ports.inputs.iter().map(|pi| pi.name.clone()).zip(body.arguments()).collect();
+            let mut outputs = HashMap::new();
+            let mut importer = Importer {
+                builder: &mut *builder,
+                drivers: HashMap::new(),
+            };
+            importer.run(name, netlist_module, &inputs, &mut outputs, submodules)?;
+            let output_values = (ports.outputs.iter())
+                .map(|pi| {
+                    outputs.remove(&pi.name).ok_or(Error::simple(format!(
+                        "Output port `{}` of `{}` is undriven",
+                        pi.name, name
+                    )))
+                })
+                .collect::<Result<Vec<Value>, Error>>()?;
+            builder.set_insertion_point(Some(InsertPoint::BlockEnd(body)));
+            hw::OutputOp::build::<Value>(builder, output_values.iter()).ok_or(Error::IsNone)?;
+            (hw_module.verify().then_some(()))
+                .ok_or(Error::simple(format!("Imported module `{}` failed to verify", name)))
+        };
+        if let Err(e) = build_body(builder) {
+            // Do not leave the partially imported module behind.
+            hw_module.erase();
+            return Err(e);
+        }
+        self.submodules.modules.insert(name.to_string(), hw_module);
+        self.imported.push(hw_module);
+        Ok(())
+    }
+}
+
+/// Import the modules of `netlist` at the end of `module`, declaring the modules of cells it does
+///  not define, and those with a `blackbox` attribute, as `hw.module.extern`s. Returns the
+///  imported `hw.module`s, each after the modules it instantiates.
+pub fn import(
+    builder: &mut OpBuilder,
+    module: &Module,
+    netlist: &Netlist,
+) -> Result<Vec<HwModuleOp>, Error> {
+    let mut importer = DesignImporter {
+        netlist,
+        submodules: Submodules::default(),
+        visiting: HashSet::new(),
+        imported: vec![],
+    };
+    for (name, _) in &netlist.modules {
+        importer.import_module(builder, module, name)?;
+    }
+    Ok(importer.imported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::equiv::{self, Equivalence};
+
+    #[test]
+    fn json_round_trip() -> miette::Result<()> {
+        let json = r#"{
+            "creator": "Yosys",
+            "modules": {
+                "top": {
+                    "ports": {
+                        "b": { "direction": "input", "bits": [ 2, 3 ] },
+                        "a": { "direction": "input", "bits": [ 4, 5 ] },
+                        "y": { "direction": "output", "bits": [ 6, "0" ] }
+                    },
+                    "cells": {
+                        "$and$top.v:3$1": {
+                            "hide_name": 1,
+                            "type": "$and",
+                            "parameters": {
+                                "A_WIDTH": "00000000000000000000000000000010",
+                                "Y_WIDTH": 1
+                            },
+                            "connections": { "A": [ 2, 3 ], "B": [ 4, 5 ], "Y": [ 6 ] }
+                        }
+                    }
+                }
+            }
+        }"#;
+        let netlist = Netlist::from_json(json)?;
+        let top = netlist.module("top").unwrap();
+        let names: Vec<&str> = top.ports.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["b", "a", "y"]);
+        assert_eq!(top.ports[2].1.bits, [Bit::Net(6), Bit::Const(Const::Zero)]);
+        let cell = &top.cells[0].1;
+        assert_eq!(cell.parameter("A_WIDTH"), Some(BigUint::from(2u32)));
+        assert_eq!(cell.parameter("Y_WIDTH"), Some(BigUint::one()));
+        assert_eq!(cell.connection("B")?, [Bit::Net(4), Bit::Net(5)]);
+        assert!(cell.connection("C").is_err());
+        assert_eq!(Netlist::from_json(&netlist.to_json())?, netlist);
+        Ok(())
+    }
+
+    #[test]
+    fn export_import() -> miette::Result<()> {
+        let ctx = OwnedContext::default();
+        hw::dialect().load(&ctx).unwrap();
+        comb::dialect().load(&ctx).unwrap();
+        seq::dialect().load(&ctx).unwrap();
+
+        let mut builder = OpBuilder::new(&ctx);
+        let module = Module::create(builder.loc());
+        let i1 = IntegerType::new(&ctx, 1);
+        let i8 = IntegerType::new(&ctx, 8);
+        let mut ports = ModulePortInfo::default();
+        ports.add_input("clk", &i1);
+        ports.add_input("rst", &i1);
+        ports.add_input("en", &i1);
+        ports.add_input("inc", &i8);
+        ports.add_output("count", &i8);
+        ports.add_output("big", &i1);
+        let counter = HwModuleOp::build_with(
+            &mut builder,
+            &module,
+            "counter",
+            &ports,
+            &[],
+            "",
+            |builder, _, inputs, outputs| {
+                let zero = hw::ConstantOp::build(builder, 8, 0).result();
+                let count = seq::CompRegClockEnabledOp::build(
+                    builder,
+                    "count",
+                    &inputs["inc"],
+                    &inputs["clk"],
+                    &inputs["en"],
+                    Some(&inputs["rst"]),
+                    Some(&zero),
+                )
+                .unwrap();
+                let next = comb::AddOp::build(builder, &count.output(), &inputs["inc"]).unwrap();
+                count.set_input(&next.result());
+                let high = comb::ExtractOp::with_sizes(builder, &count.output(), 4, 4).unwrap();
+                let limit = hw::ConstantOp::build(builder, 4, 9).result();
+                let big = comb::ICmpOp::build(builder, comb::CmpPred::Ugt, &high.result(), &limit)
+                    .unwrap();
+                outputs.insert("count".to_string(), count.output());
+                outputs.insert("big".to_string(), big.result());
+            },
+        )?;
+        let mut top_ports = ModulePortInfo::default();
+        top_ports.add_input("clk", &i1);
+        top_ports.add_input("rst", &i1);
+        top_ports.add_output("count", &i8);
+        HwModuleOp::build_with(
+            &mut builder,
+            &module,
+            "top",
+            &top_ports,
+            &[],
+            "",
+            |builder, _, inputs, outputs| {
+                let one = hw::ConstantOp::build(builder, 1, 1).result();
+                let step = hw::ConstantOp::build(builder, 8, 3).result();
+                let args = [inputs["clk"], inputs["rst"], one, step];
+                let inst =
+                    hw::InstanceOp::build(builder, "u_counter", &counter, args, &[]).unwrap();
+                outputs.insert("count".to_string(), inst.result_at(0).unwrap());
+            },
+        )?;
+
+        let netlist = export(&module)?;
+        let counter_cells = &netlist.module("counter").unwrap().cells;
+        let types: Vec<&str> = counter_cells.iter().map(|(_, c)| c.cell_type.as_str()).collect();
+        assert_eq!(types, ["$sdffe", "$add", "$gt"]);
+        let (name, register) = &counter_cells[0];
+        assert_eq!(name, "count");
+        assert_eq!(register.parameter("SRST_VALUE"), Some(BigUint::zero()));
+        let (name, inst) = &netlist.module("top").unwrap().cells[0];
+        assert_eq!((name.as_str(), inst.cell_type.as_str()), ("u_counter", "counter"));
+        assert_eq!(inst.connection("en")?, [Bit::Const(Const::One)]);
+
+        let imported_module = Module::create(builder.loc());
+        let imported = import(&mut builder, &imported_module, &netlist)?;
+        let names: Vec<String> = imported.iter().map(|m| m.module_name()).collect();
+        assert_eq!(names, ["counter", "top"]);
+        assert!(matches!(
+            equiv::check_combinational(&counter, &imported[0])?,
+            Equivalence::Equivalent
+        ));
+        assert_eq!(export(&imported_module)?.module("top"), netlist.module("top"));
+
+        // A module which fails to import is erased, with the black boxes declared for it.
+        let mut broken = netlist.clone();
+        let (_, broken_counter) =
+            broken.modules.iter_mut().find(|(name, _)| name == "counter").unwrap();
+        broken_counter.cells[1].1.cell_type = "$lut".to_string();
+        broken_counter.cells[2].1.cell_type = "vendor_gt".to_string();
+        let broken_module = Module::create(builder.loc());
+        assert!(import(&mut builder, &broken_module, &broken).is_err());
+        assert_eq!(broken_module.body().operations().count(), 0);
+        Ok(())
+    }
+}